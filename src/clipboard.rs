@@ -0,0 +1,147 @@
+use std::{
+    future::Future,
+    io::Write as _,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use base64::prelude::*;
+
+// how long a clipboard tool may take before the next one is tried
+const TOOL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The OSC 52 escape sequence that asks the terminal to put `text` on the clipboard.
+/// This works over SSH and in tmux, where there may be no clipboard tool to run.
+/// Inside tmux, the sequence is wrapped so tmux passes it through to the outer terminal.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text));
+    match tmux {
+        true => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+        false => sequence,
+    }
+}
+
+// Clipboard tools to try, given the values of $WAYLAND_DISPLAY and $DISPLAY, and whether this is macOS.
+fn commands(wayland: Option<&str>, display: Option<&str>, macos: bool) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if macos {
+        commands.push(Command::new("pbcopy"));
+    }
+    if wayland.is_some_and(|d| !d.is_empty()) {
+        commands.push(Command::new("wl-copy"));
+    }
+    if display.is_some_and(|d| !d.is_empty()) {
+        let mut cmd = Command::new("xclip");
+        cmd.args(["-selection", "clipboard"]);
+        commands.push(cmd);
+    }
+    commands
+}
+
+// Run `cmd` with `text` as its input, killing it if it takes longer than `timeout`.
+fn pipe(mut cmd: Command, text: &str, timeout: Duration) -> Result<()> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Running {cmd:?}"))?;
+    // closing stdin lets the tool finish reading
+    child
+        .stdin
+        .take()
+        .context("No stdin")?
+        .write_all(text.as_bytes())?;
+    // xclip and wl-copy leave a child of their own running to serve the selection
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .with_context(|| format!("Running {cmd:?}"))?
+        {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("{cmd:?} took longer than {timeout:?}");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    anyhow::ensure!(status.success(), "{cmd:?} exited with {status}");
+    Ok(())
+}
+
+/// Copy `text` to the clipboard with OSC 52, and with the first clipboard tool that runs.
+/// The escape sequence is written right away, so it can't land in the middle of drawing,
+/// and the returned future runs the tools off the UI thread.
+/// Terminals can't report whether they honored OSC 52,
+/// so the future fails unless a tool copied the text.
+pub fn copy(text: &str) -> Result<impl Future<Output = Result<()>> + Send + 'static> {
+    let mut stdout = std::io::stdout();
+    let tmux = std::env::var_os("TMUX").is_some_and(|t| !t.is_empty());
+    stdout.write_all(osc52(text, tmux).as_bytes())?;
+    stdout.flush()?;
+
+    let wayland = std::env::var("WAYLAND_DISPLAY").ok();
+    let display = std::env::var("DISPLAY").ok();
+    let macos = cfg!(target_os = "macos");
+    let commands = commands(wayland.as_deref(), display.as_deref(), macos);
+    let text = text.to_string();
+    Ok(async move {
+        tokio::task::spawn_blocking(move || {
+            for cmd in commands {
+                match pipe(cmd, &text, TOOL_TIMEOUT) {
+                    Ok(()) => return Ok(()),
+                    Err(err) => tracing::debug!("Clipboard tool failed: {err:?}"),
+                }
+            }
+            anyhow::bail!("no clipboard tool worked, only the terminal was asked to copy it")
+        })
+        .await
+        .context("Copying")?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("hello", false), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(
+            osc52("hello", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_commands() {
+        let programs = |wayland, display| {
+            commands(wayland, display, false)
+                .iter()
+                .map(|c| c.get_program().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert!(programs(None, None).is_empty());
+        assert!(programs(Some(""), None).is_empty());
+        assert_eq!(
+            programs(Some("wayland-0"), Some(":0")),
+            vec!["wl-copy", "xclip"]
+        );
+        assert_eq!(programs(None, Some(":0")), vec!["xclip"]);
+        assert_eq!(commands(None, None, true)[0].get_program(), "pbcopy");
+        // the tool has to take all of the text
+        let timeout = Duration::from_secs(5);
+        pipe(Command::new("cat"), "hello", timeout).unwrap();
+        assert!(pipe(Command::new("false"), "hello", timeout).is_err());
+        // a tool that hangs is given up on
+        let mut sleep = Command::new("sleep");
+        sleep.arg("10");
+        let start = Instant::now();
+        assert!(pipe(sleep, "hello", Duration::from_millis(100)).is_err());
+        assert!(start.elapsed() < timeout);
+    }
+}
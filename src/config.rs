@@ -0,0 +1,214 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{query::Query, Column, QueryArgs};
+
+/// A named query from the config file, used in queries as `@name`.
+/// Either just a query string, or a table that can also set the repo and columns.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(from = "SavedSearchDef")]
+pub struct SavedSearch {
+    pub query: String,
+    pub repo: Option<String>,
+    pub columns: Option<Vec<Column>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedSearchDef {
+    Query(String),
+    Table {
+        query: String,
+        repo: Option<String>,
+        columns: Option<Vec<Column>>,
+    },
+}
+
+impl From<SavedSearchDef> for SavedSearch {
+    fn from(def: SavedSearchDef) -> Self {
+        match def {
+            SavedSearchDef::Query(query) => Self {
+                query,
+                ..Default::default()
+            },
+            SavedSearchDef::Table {
+                query,
+                repo,
+                columns,
+            } => Self {
+                query,
+                repo,
+                columns,
+            },
+        }
+    }
+}
+
+/// A section of the dashboard: a query and what to call its results.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct DashboardSection {
+    pub name: String,
+    pub query: String,
+}
+
+// The dashboard's sections unless the config file has its own.
+const DEFAULT_DASHBOARD: &[(&str, &str)] = &[
+    ("Assigned to me", "is:open assignee:@me"),
+    ("Review requested", "is:open is:pr review-requested:@me"),
+    ("My open pull requests", "is:open is:pr author:@me"),
+    ("Mentions", "is:open mentions:@me"),
+    ("Recently closed", "is:closed involves:@me closed:>1w"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub searches: BTreeMap<String, SavedSearch>,
+    // local clones of repos, e.g. "foo/bar" = "~/src/bar"
+    #[serde(default)]
+    pub checkouts: BTreeMap<String, PathBuf>,
+    // how branches for issues are named, e.g. "{number}-{slug}"
+    pub branch_template: Option<String>,
+    // the sections of the dashboard, replacing the default ones
+    #[serde(default)]
+    pub dashboard: Vec<DashboardSection>,
+}
+
+impl Config {
+    /// Load the config from the XDG config directory, if there is one.
+    pub fn open() -> Result<Self> {
+        let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+        match xdg_dirs.find_config_file("config.toml") {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading config {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Parsing config {}", path.display()))
+    }
+
+    /// The sections of the dashboard, in order.
+    pub fn dashboard(&self) -> Vec<DashboardSection> {
+        if !self.dashboard.is_empty() {
+            return self.dashboard.clone();
+        }
+        DEFAULT_DASHBOARD
+            .iter()
+            .map(|(name, query)| DashboardSection {
+                name: name.to_string(),
+                query: query.to_string(),
+            })
+            .collect()
+    }
+
+    /// Expand the saved searches used in the query.
+    /// The repo and columns of the saved searches used replace the scope and columns of `args`.
+    /// Saved searches are not expanded within other saved searches, and unknown names are left as they are.
+    pub fn resolve(&self, args: &QueryArgs) -> QueryArgs {
+        let mut resolved = args.clone();
+        let (mut query, _) = Query::parse(&args.query);
+        let unknown = query.expand_saved(|name| {
+            let saved = self.searches.get(name)?;
+            if let Some(repo) = &saved.repo {
+                resolved.repo = vec![repo.clone()];
+                resolved.org.clear();
+                resolved.user.clear();
+            }
+            if let Some(columns) = &saved.columns {
+                resolved.columns = columns.clone();
+            }
+            Some(Query::parse(&saved.query).0)
+        });
+        // searched for as plain text, like any other word
+        if !unknown.is_empty() {
+            tracing::debug!("Not saved searches: {unknown:?}");
+        }
+        resolved.query = query.to_string();
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [searches]
+            triage = "is:open no:label"
+            mine = { query = "is:open assignee:@me", repo = "foo/bar", columns = ["number", "author", "title"] }
+            "#,
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.searches.len(), 2);
+
+        let args = QueryArgs {
+            query: "@triage crash".into(),
+            repo: vec!["baz/qux".into()],
+            ..Default::default()
+        };
+        let resolved = config.resolve(&args);
+        assert_eq!(resolved.query, "is:open no:label crash");
+        assert_eq!(resolved.repo, vec!["baz/qux"]);
+        assert_eq!(resolved.columns(), &[Column::Number, Column::Title]);
+
+        let args = QueryArgs {
+            query: "@mine".into(),
+            repo: vec!["baz/qux".into()],
+            ..Default::default()
+        };
+        let resolved = config.resolve(&args);
+        assert_eq!(resolved.query, "is:open assignee:@me");
+        assert_eq!(resolved.repo, vec!["foo/bar"]);
+        assert_eq!(
+            resolved.columns(),
+            &[Column::Number, Column::Author, Column::Title]
+        );
+
+        let args = QueryArgs {
+            query: "@nope @triage".into(),
+            ..Default::default()
+        };
+        assert_eq!(config.resolve(&args).query, "@nope is:open no:label");
+    }
+
+    #[test]
+    fn test_dashboard() {
+        assert_eq!(Config::default().dashboard().len(), DEFAULT_DASHBOARD.len());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [[dashboard]]
+            name = "Triage"
+            query = "is:open no:label"
+            "#,
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.dashboard(),
+            [DashboardSection {
+                name: "Triage".into(),
+                query: "is:open no:label".into(),
+            }]
+        );
+    }
+}
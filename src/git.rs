@@ -0,0 +1,407 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::open::remote_matches;
+
+/// How branches for issues are named, unless the config says otherwise.
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "{number}-{slug}";
+// the longest slug of a title put in a branch name
+const MAX_SLUG_LENGTH: usize = 40;
+
+/// A pull request to check out, and where its head can be fetched from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PullHead {
+    // the repo the pull request is in, e.g. "foo/bar"
+    pub repo: String,
+    pub number: u32,
+    pub url: String,
+    // the branch the pull request merges, and the url of the repo it is in, which may be a fork
+    pub branch: String,
+    pub head_url: Option<String>,
+}
+
+impl PullHead {
+    /// The local branch the pull request is checked out into.
+    pub fn local_branch(&self) -> String {
+        format!("pr-{}", self.number)
+    }
+}
+
+// Run git in `dir`, failing with what it printed to stderr.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // fail rather than ask for credentials under the TUI
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Running git {}", args.join(" ")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("git {} failed: {}", args[0], stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The name of the remote of the clone in `dir` that points at `repo`.
+pub fn remote_for(dir: &Path, repo: &str) -> Option<String> {
+    let remotes = git(dir, &["remote"]).ok()?;
+    remotes
+        .lines()
+        .find(|remote| {
+            git(dir, &["remote", "get-url", remote]).is_ok_and(|url| remote_matches(&url, repo))
+        })
+        .map(str::to_string)
+}
+
+/// Whether the clone in `dir` has uncommitted changes to tracked files.
+pub fn is_dirty(dir: &Path) -> Result<bool> {
+    let status = git(dir, &["status", "--porcelain", "--untracked-files=no"])?;
+    Ok(!status.is_empty())
+}
+
+// The git config recording the url of the issue a branch is for.
+fn issue_key(branch: &str) -> String {
+    format!("branch.{branch}.hubl-issue")
+}
+
+/// The url of the issue or pull request the branch checked out in `dir` is for,
+/// if hubl created it.
+pub fn current_issue(dir: &Path) -> Result<Option<String>> {
+    let branch = git(dir, &["branch", "--show-current"])?;
+    if branch.is_empty() {
+        return Ok(None);
+    }
+    // git config fails if the key isn't set
+    Ok(git(dir, &["config", "--get", &issue_key(&branch)]).ok())
+}
+
+fn branch_exists(dir: &Path, branch: &str) -> bool {
+    let name = format!("refs/heads/{branch}");
+    git(dir, &["rev-parse", "--verify", "--quiet", &name]).is_ok()
+}
+
+/// Check out a pull request into a branch of the clone in `dir`, returning the branch.
+/// The head is fetched from `refs/pull/N/head` of the remote pointing at the pull request's repo,
+/// or else from the branch of the repo it is in.
+/// A branch checked out before is fast-forwarded, so commits made on it are kept.
+/// Uncommitted changes are refused unless `stash` is set, which stashes them once the head
+/// is fetched, so they stay in place if the fetch fails.
+pub fn checkout_pull(dir: &Path, head: &PullHead, stash: bool) -> Result<String> {
+    let dirty = is_dirty(dir)?;
+    if dirty && !stash {
+        bail!("{} has uncommitted changes", dir.display());
+    }
+
+    let (source, merge) = match (remote_for(dir, &head.repo), &head.head_url) {
+        (Some(remote), _) => (remote, format!("refs/pull/{}/head", head.number)),
+        (None, Some(url)) if !head.branch.is_empty() => {
+            (url.clone(), format!("refs/heads/{}", head.branch))
+        }
+        _ => bail!("No remote of {} points at {}", dir.display(), head.repo),
+    };
+    git(dir, &["fetch", "--quiet", &source, &merge])?;
+
+    let stashed = match dirty {
+        true => {
+            let message = format!("hubl: before checking out #{}", head.number);
+            git(dir, &["stash", "push", "--message", &message])?;
+            Some(message)
+        }
+        false => None,
+    };
+    let branch = head.local_branch();
+    let checked_out = match branch_exists(dir, &branch) {
+        true => git(dir, &["checkout", "--quiet", &branch])
+            .and_then(|_| git(dir, &["merge", "--quiet", "--ff-only", "FETCH_HEAD"])),
+        false => git(dir, &["checkout", "--quiet", "-b", &branch, "FETCH_HEAD"]),
+    };
+    if let Some(message) = stashed {
+        checked_out.with_context(|| format!("Your changes are stashed as \"{message}\""))?;
+    } else {
+        checked_out?;
+    }
+    // so that pulling gets new commits of the pull request
+    git(
+        dir,
+        &["config", &format!("branch.{branch}.remote"), &source],
+    )?;
+    git(dir, &["config", &format!("branch.{branch}.merge"), &merge])?;
+    git(dir, &["config", &issue_key(&branch), &head.url])?;
+    Ok(branch)
+}
+
+/// A title in lowercase words joined by dashes, cut short at a dash if it is long.
+pub fn slug(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut slug = String::new();
+    for word in words {
+        let len = slug.chars().count() + word.chars().count() + 1;
+        if !slug.is_empty() && len > MAX_SLUG_LENGTH {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    slug.chars().take(MAX_SLUG_LENGTH).collect()
+}
+
+/// The branch for an issue, named by a template like "{number}-{slug}",
+/// where the slug is made from the title.
+pub fn branch_name(template: &str, number: u32, title: &str) -> String {
+    template
+        .replace("{number}", &number.to_string())
+        .replace("{slug}", &slug(title))
+}
+
+/// Where a worktree for `branch` of the clone in `dir` goes: beside it, e.g. "../bar-12-fix-it".
+pub fn worktree_path(dir: &Path, branch: &str) -> PathBuf {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    dir.with_file_name(format!("{name}-{}", branch.replace('/', "-")))
+}
+
+/// Create `branch` for the issue at `url` in the clone of `repo` in `dir` and check it out,
+/// in a new worktree if `worktree` is set. Returns where it is checked out.
+/// New branches start from the default branch of the remote pointing at `repo`, or else from HEAD.
+/// A branch that already exists is checked out as it is.
+pub fn branch_for_issue(
+    dir: &Path,
+    repo: &str,
+    branch: &str,
+    url: &str,
+    worktree: bool,
+) -> Result<PathBuf> {
+    let start = remote_for(dir, repo)
+        .and_then(|remote| {
+            let head = format!("{remote}/HEAD");
+            git(dir, &["rev-parse", "--abbrev-ref", &head]).ok()
+        })
+        .unwrap_or_else(|| "HEAD".to_string());
+    let exists = branch_exists(dir, branch);
+    let path = match worktree {
+        true => {
+            let path = worktree_path(dir, branch);
+            let path_arg = path.to_string_lossy();
+            if exists {
+                git(dir, &["worktree", "add", "--quiet", &path_arg, branch])?;
+            } else {
+                git(
+                    dir,
+                    &[
+                        "worktree", "add", "--quiet", "-b", branch, &path_arg, &start,
+                    ],
+                )?;
+            }
+            path
+        }
+        false => {
+            if exists {
+                git(dir, &["checkout", "--quiet", branch])?;
+            } else {
+                git(
+                    dir,
+                    &["checkout", "--quiet", "--no-track", "-b", branch, &start],
+                )?;
+            }
+            dir.to_path_buf()
+        }
+    };
+    git(dir, &["config", &issue_key(branch), url])?;
+    Ok(path)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A commit of a file with the given contents, on whatever is checked out.
+    fn commit(dir: &Path, contents: &str) -> String {
+        std::fs::write(dir.join("file.txt"), contents).unwrap();
+        git(dir, &["add", "file.txt"]).unwrap();
+        git(dir, &["commit", "--quiet", "--message", contents]).unwrap();
+        git(dir, &["rev-parse", "HEAD"]).unwrap()
+    }
+
+    // A repo to work in, set up to commit without global config.
+    fn init(dir: &Path) {
+        git(dir, &["init", "--quiet", "--initial-branch", "main"]).unwrap();
+        git(dir, &["config", "user.name", "Mona"]).unwrap();
+        git(dir, &["config", "user.email", "mona@example.com"]).unwrap();
+    }
+
+    /// A bare repo of `repo` at e.g. `root/foo/bar.git` with a main branch and pull request
+    /// `number`, and a clone of it at `root/work`.
+    pub(crate) fn clone_with_pull(root: &Path, repo: &str, number: u32) -> std::path::PathBuf {
+        let author = root.join("author");
+        std::fs::create_dir_all(&author).unwrap();
+        init(&author);
+        commit(&author, "main");
+        let origin = root.join(format!("{repo}.git"));
+        std::fs::create_dir_all(&origin).unwrap();
+        git(
+            &origin,
+            &["init", "--quiet", "--bare", "--initial-branch", "main"],
+        )
+        .unwrap();
+        let url = origin.to_str().unwrap();
+        git(&author, &["push", "--quiet", url, "main"]).unwrap();
+        git(&author, &["checkout", "--quiet", "-b", "topic"]).unwrap();
+        commit(&author, "topic");
+        let pull = format!("topic:refs/pull/{number}/head");
+        git(&author, &["push", "--quiet", url, &pull]).unwrap();
+
+        let work = root.join("work");
+        git(root, &["clone", "--quiet", url, "work"]).unwrap();
+        git(&work, &["config", "user.name", "Mona"]).unwrap();
+        git(&work, &["config", "user.email", "mona@example.com"]).unwrap();
+        work
+    }
+
+    #[test]
+    fn test_checkout_pull() {
+        let root = tempfile::tempdir().unwrap();
+        let work = clone_with_pull(root.path(), "foo/bar", 7);
+        assert_eq!(remote_for(&work, "foo/bar").as_deref(), Some("origin"));
+        assert_eq!(remote_for(&work, "foo/baz"), None);
+
+        let head = PullHead {
+            repo: "foo/bar".into(),
+            number: 7,
+            url: "https://github.com/foo/bar/pull/7".into(),
+            branch: "topic".into(),
+            head_url: None,
+        };
+        // uncommitted changes are refused, and kept
+        std::fs::write(work.join("file.txt"), "changed").unwrap();
+        let err = checkout_pull(&work, &head, false).unwrap_err();
+        assert!(
+            err.to_string().ends_with("has uncommitted changes"),
+            "{err}"
+        );
+        assert_eq!(git(&work, &["branch", "--show-current"]).unwrap(), "main");
+
+        // they aren't stashed if there's nothing to check out
+        let missing = PullHead {
+            number: 9,
+            ..head.clone()
+        };
+        checkout_pull(&work, &missing, true).unwrap_err();
+        assert!(is_dirty(&work).unwrap());
+        assert_eq!(git(&work, &["stash", "list"]).unwrap(), "");
+
+        // unless they are stashed
+        assert_eq!(checkout_pull(&work, &head, true).unwrap(), "pr-7");
+        assert!(!is_dirty(&work).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(work.join("file.txt")).unwrap(),
+            "topic"
+        );
+        let stashes = git(&work, &["stash", "list"]).unwrap();
+        assert!(
+            stashes.contains("hubl: before checking out #7"),
+            "{stashes}"
+        );
+        assert_eq!(
+            git(&work, &["config", "branch.pr-7.merge"]).unwrap(),
+            "refs/pull/7/head"
+        );
+        assert_eq!(
+            current_issue(&work).unwrap().as_deref(),
+            Some("https://github.com/foo/bar/pull/7")
+        );
+
+        // local commits are kept when checking out again
+        let local = commit(&work, "local");
+        git(&work, &["checkout", "--quiet", "main"]).unwrap();
+        checkout_pull(&work, &head, false).unwrap();
+        assert_eq!(git(&work, &["rev-parse", "HEAD"]).unwrap(), local);
+
+        // without a remote for the repo, the branch is fetched from the fork
+        let fork = PullHead {
+            repo: "foo/baz".into(),
+            number: 8,
+            url: "https://github.com/foo/baz/pull/8".into(),
+            branch: "topic".into(),
+            head_url: Some(root.path().join("author").to_str().unwrap().into()),
+        };
+        assert_eq!(checkout_pull(&work, &fork, false).unwrap(), "pr-8");
+        assert_eq!(
+            std::fs::read_to_string(work.join("file.txt")).unwrap(),
+            "topic"
+        );
+        let err = checkout_pull(
+            &work,
+            &PullHead {
+                head_url: None,
+                ..fork
+            },
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("points at foo/baz"), "{err}");
+    }
+
+    #[test]
+    fn test_branch_for_issue() {
+        assert_eq!(
+            slug("Fix: the parser's  crash (on Ümlauts)"),
+            "fix-the-parser-s-crash-on-ümlauts"
+        );
+        assert_eq!(
+            slug("a very long title that goes on and on about the problem"),
+            "a-very-long-title-that-goes-on-and-on"
+        );
+        assert_eq!(slug("x".repeat(50).as_str()).len(), MAX_SLUG_LENGTH);
+        assert_eq!(
+            branch_name(DEFAULT_BRANCH_TEMPLATE, 12, "Crash on start"),
+            "12-crash-on-start"
+        );
+        assert_eq!(
+            branch_name("issue/{number}", 12, "Crash on start"),
+            "issue/12"
+        );
+
+        let root = tempfile::tempdir().unwrap();
+        let work = clone_with_pull(root.path(), "foo/bar", 7);
+        let url = "https://github.com/foo/bar/issues/12";
+        assert_eq!(current_issue(&work).unwrap(), None);
+
+        // branches start from the default branch, wherever HEAD is
+        let main = git(&work, &["rev-parse", "HEAD"]).unwrap();
+        git(&work, &["checkout", "--quiet", "-b", "elsewhere"]).unwrap();
+        commit(&work, "elsewhere");
+        let path = branch_for_issue(&work, "foo/bar", "12-crash", url, false).unwrap();
+        assert_eq!(path, work);
+        assert_eq!(git(&work, &["rev-parse", "HEAD"]).unwrap(), main);
+        assert_eq!(current_issue(&work).unwrap().as_deref(), Some(url));
+
+        // a worktree for an existing branch
+        git(&work, &["checkout", "--quiet", "main"]).unwrap();
+        let path = branch_for_issue(&work, "foo/bar", "12-crash", url, true).unwrap();
+        assert_eq!(path, root.path().join("work-12-crash"));
+        assert_eq!(
+            git(&path, &["branch", "--show-current"]).unwrap(),
+            "12-crash"
+        );
+        assert_eq!(current_issue(&path).unwrap().as_deref(), Some(url));
+        assert_eq!(current_issue(&work).unwrap(), None);
+
+        // and for a new one
+        let url = "https://github.com/foo/bar/issues/13";
+        let path = branch_for_issue(&work, "foo/bar", "13-other", url, true).unwrap();
+        assert_eq!(git(&path, &["rev-parse", "HEAD"]).unwrap(), main);
+        assert_eq!(current_issue(&path).unwrap().as_deref(), Some(url));
+    }
+}
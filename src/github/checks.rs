@@ -0,0 +1,271 @@
+use super::{repo::split_repo, Github};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+// the app that runs GitHub Actions, the only checks with logs we can fetch
+const ACTIONS_APP: &str = "github-actions";
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CheckApp {
+    pub slug: String,
+}
+
+/// A check run on a commit. For GitHub Actions, each job is a check run with the same id.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CheckRun {
+    pub id: u64,
+    pub name: String,
+    // queued, in_progress or completed
+    pub status: String,
+    // success, failure, neutral, cancelled, skipped, timed_out or action_required, once completed
+    pub conclusion: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub html_url: String,
+    pub app: Option<CheckApp>,
+}
+
+impl CheckRun {
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.conclusion.as_deref(),
+            Some("failure" | "timed_out" | "cancelled" | "action_required")
+        )
+    }
+
+    /// Whether the run is a GitHub Actions job, which has a log and can be re-run.
+    pub fn is_job(&self) -> bool {
+        self.app.as_ref().is_some_and(|app| app.slug == ACTIONS_APP)
+    }
+
+    /// How long the run took, or has taken so far.
+    pub fn duration(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        Some(self.completed_at.unwrap_or(now) - self.started_at?)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+/// Fetch the check runs on commit `sha` of `repo`, up to 100 of them.
+pub async fn fetch_check_runs(github: &Github, repo: &str, sha: &str) -> Result<Vec<CheckRun>> {
+    tracing::debug!("fetching check runs of {repo}@{sha}");
+    let (owner, name) = split_repo(repo)?;
+    let runs: CheckRuns = github
+        .get(
+            &format!("/repos/{owner}/{name}/commits/{sha}/check-runs"),
+            &[("per_page", "100")],
+        )
+        .await
+        .with_context(|| format!("Fetching checks of {repo}@{sha}"))?;
+    Ok(runs.check_runs)
+}
+
+/// Fetch the log of a GitHub Actions job.
+pub async fn fetch_log(github: &Github, repo: &str, job_id: u64) -> Result<String> {
+    tracing::debug!("fetching log of job {job_id} of {repo}");
+    let (owner, name) = split_repo(repo)?;
+    let path = format!("/repos/{owner}/{name}/actions/jobs/{job_id}/logs");
+    // the log is a redirect to plain text, which reqwest follows
+    github
+        .send_text(github.rest(reqwest::Method::GET, &path))
+        .await
+        .with_context(|| format!("Fetching log of job {job_id}"))
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Job {
+    run_id: u64,
+}
+
+/// Re-run the failed jobs of the workflow run a job is part of.
+pub async fn rerun_failed_jobs(github: &Github, repo: &str, job_id: u64) -> Result<()> {
+    tracing::debug!("re-running failed jobs with job {job_id} of {repo}");
+    let (owner, name) = split_repo(repo)?;
+    let job: Job = github
+        .get(&format!("/repos/{owner}/{name}/actions/jobs/{job_id}"), &[])
+        .await
+        .with_context(|| format!("Fetching job {job_id}"))?;
+    let path = format!(
+        "/repos/{owner}/{name}/actions/runs/{}/rerun-failed-jobs",
+        job.run_id
+    );
+    github
+        .send_text(github.rest(reqwest::Method::POST, &path))
+        .await
+        .context("Re-running failed jobs")?;
+    Ok(())
+}
+
+/// Make a job's log readable: remove the timestamp Actions puts before each line,
+/// terminal escape sequences, and the markers of collapsed groups.
+pub fn clean_log(log: &str) -> Vec<String> {
+    log.trim_start_matches('\u{feff}')
+        .lines()
+        .filter_map(|line| {
+            let line = strip_timestamp(line);
+            let line = strip_ansi(line);
+            match line.strip_prefix("##[group]") {
+                Some(group) => Some(format!("▸ {group}")),
+                None if line.starts_with("##[endgroup]") => None,
+                None => Some(line),
+            }
+        })
+        .collect()
+}
+
+// "2024-05-01T12:34:56.1234567Z message" -> "message"
+fn strip_timestamp(line: &str) -> &str {
+    let Some((timestamp, rest)) = line.split_once(' ') else {
+        return line;
+    };
+    if DateTime::parse_from_rfc3339(timestamp).is_ok() {
+        rest
+    } else {
+        line
+    }
+}
+
+// Remove ANSI escape sequences, such as colors (CSI) and hyperlinks (OSC).
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters, then a final byte from @ to ~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ends with BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The index of the first line of a cleaned log that reports an error.
+pub fn first_error(lines: &[String]) -> Option<usize> {
+    lines.iter().position(|line| {
+        let line = line.trim_start();
+        line.starts_with("##[error]")
+            || line.starts_with("error")
+            || line.starts_with("Error:")
+            || line.starts_with("FAILED")
+            || line.contains("panicked at")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::Server;
+
+    #[test]
+    fn test_clean_log() {
+        let log = "\u{feff}2024-05-01T12:34:56.1234567Z ##[group]Run cargo test\n\
+                   2024-05-01T12:34:56.2Z \x1b[1m\x1b[32m   Compiling\x1b[0m hubl v0.1.0\n\
+                   2024-05-01T12:34:56.3Z ##[endgroup]\n\
+                   2024-05-01T12:34:57.0Z \x1b[0m\x1b[1m\x1b[38;5;9merror[E0308]\x1b[0m: mismatched types\n\
+                   not a timestamp line\n\
+                   2024-05-01T12:34:58.0Z ##[error]Process completed with exit code 101.";
+        let lines = clean_log(log);
+        assert_eq!(
+            lines,
+            vec![
+                "▸ Run cargo test",
+                "   Compiling hubl v0.1.0",
+                "error[E0308]: mismatched types",
+                "not a timestamp line",
+                "##[error]Process completed with exit code 101.",
+            ]
+        );
+        assert_eq!(first_error(&lines), Some(2));
+        assert_eq!(first_error(&lines[..2]), None);
+        assert_eq!(
+            strip_ansi("\x1b]8;;http://x\x1b\\link\x1b]8;;\x07!"),
+            "link!"
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_checks() {
+        let mut server = Server::new_async().await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let runs = server
+            .mock(
+                "GET",
+                "/repos/octocat/Hello-World/commits/6dcb09b/check-runs",
+            )
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/check_runs.json").unwrap())
+            .create_async()
+            .await;
+        let checks = fetch_check_runs(&github, "octocat/Hello-World", "6dcb09b")
+            .await
+            .unwrap();
+        runs.assert_async().await;
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].is_failed());
+        assert!(checks[0].is_job());
+        assert_eq!(
+            checks[0].duration(Utc::now()),
+            Some(chrono::Duration::seconds(133))
+        );
+        assert!(!checks[1].is_failed());
+        assert!(!checks[1].is_job());
+
+        let job = server
+            .mock("GET", "/repos/octocat/Hello-World/actions/jobs/399444496")
+            .with_status(200)
+            .with_body(r#"{"id":399444496,"run_id":29679449}"#)
+            .create_async()
+            .await;
+        let rerun = server
+            .mock(
+                "POST",
+                "/repos/octocat/Hello-World/actions/runs/29679449/rerun-failed-jobs",
+            )
+            .with_status(403)
+            .with_body(r#"{"message":"Must have admin rights to Repository."}"#)
+            .create_async()
+            .await;
+        let err = rerun_failed_jobs(&github, "octocat/Hello-World", 399444496)
+            .await
+            .unwrap_err();
+        job.assert_async().await;
+        rerun.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Re-running failed jobs: GitHub returned 403 Forbidden: Must have admin rights to Repository."
+        );
+    }
+}
@@ -15,11 +15,58 @@ pub struct SearchRepository {
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct SearchItem {
     pub url: String,
+    // the file on the web, at the commit it was found in
+    #[serde(default)]
+    pub html_url: String,
     pub path: String,
     pub repository: SearchRepository,
     pub text_matches: Vec<TextMatch>,
 }
 
+impl SearchItem {
+    /// The 1-based line of the first match in the item's `content`.
+    pub fn first_match_line(&self, content: &str) -> Option<usize> {
+        content
+            .lines()
+            .position(|line| {
+                self.text_matches
+                    .iter()
+                    .flat_map(|m| &m.matches)
+                    .any(|m| !m.text.is_empty() && line.contains(&m.text))
+            })
+            .map(|i| i + 1)
+    }
+
+    /// A link to the file at the commit it was found in, at `line` if given.
+    /// Unlike a branch link, this keeps pointing at the matched line as the file changes.
+    pub fn permalink(&self, line: Option<usize>) -> String {
+        match line {
+            Some(line) => format!("{}#L{line}", self.html_url),
+            None => self.html_url.clone(),
+        }
+    }
+}
+
+/// A code search result with the content of its file, which is needed to find the matched line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CodeHit {
+    pub item: SearchItem,
+    // None if the content couldn't be fetched
+    pub content: Option<Arc<str>>,
+    pub line: Option<usize>,
+}
+
+impl CodeHit {
+    pub fn new(item: SearchItem, content: Option<String>) -> Self {
+        let line = content.as_deref().and_then(|c| item.first_match_line(c));
+        Self {
+            item,
+            content: content.map(Arc::from),
+            line,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct SearchResponse {
     pub items: Vec<SearchItem>,
@@ -157,6 +204,21 @@ async fn item_content_task(
     }
 }
 
+/// Fetch the content of the file a result was found in, at the commit it was found in.
+pub async fn fetch_content(github: &Github, item: &SearchItem) -> Result<String> {
+    tracing::debug!("fetching the content of {}", item.url);
+    let path = item
+        .url
+        .strip_prefix(&github.host)
+        .with_context(|| format!("{} isn't on {}", item.url, github.host))?;
+    let content: ContentResponse = github
+        .send(github.rest(reqwest::Method::GET, path))
+        .await
+        .with_context(|| format!("Fetching {}", item.path))?;
+    let data = BASE64_STANDARD.decode(content.content.replace('\n', ""))?;
+    Ok(String::from_utf8(data)?)
+}
+
 pub fn search_code(
     github: Github,
     term: &str,
@@ -248,6 +310,7 @@ mod tests {
                 rx.recv().await.context(format!("Awaiting {name}")).unwrap(),
                 SearchItem {
                     url: format!("example.com/{name}"),
+                    html_url: String::new(),
                     path: format!("{name}.txt"),
                     repository: SearchRepository {
                         full_name: format!("{name}repo"),
@@ -327,4 +390,28 @@ mod tests {
             mock.assert_async().await;
         }
     }
+
+    #[test]
+    fn test_permalink() {
+        let item = SearchItem {
+            html_url: "https://github.com/foo/bar/blob/abc123/src/lib.rs".into(),
+            text_matches: vec![TextMatch {
+                matches: vec![crate::github::Match {
+                    text: "stuff".into(),
+                }],
+            }],
+            ..Default::default()
+        };
+        let line = item.first_match_line("fn main() {\n    stuff();\n}\n");
+        assert_eq!(line, Some(2));
+        assert_eq!(
+            item.permalink(line),
+            "https://github.com/foo/bar/blob/abc123/src/lib.rs#L2"
+        );
+        assert_eq!(item.first_match_line("nothing here"), None);
+        assert_eq!(
+            item.permalink(None),
+            "https://github.com/foo/bar/blob/abc123/src/lib.rs"
+        );
+    }
 }
@@ -0,0 +1,410 @@
+use super::{
+    issues::{CheckState, PullRequestCommit, User},
+    mutation::mutate,
+    repo::{split_repo, Nodes},
+    Github,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const MERGE_STATUS_GRAPHQL: &str = include_str!("merge_status.graphql");
+const MERGE_PULL_REQUEST_GRAPHQL: &str = include_str!("merge_pull_request.graphql");
+const ENABLE_AUTO_MERGE_GRAPHQL: &str = include_str!("enable_auto_merge.graphql");
+const DISABLE_AUTO_MERGE_GRAPHQL: &str = include_str!("disable_auto_merge.graphql");
+
+/// How a pull request's commits are added to the base branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MergeMethod {
+    Merge,
+    Squash,
+    Rebase,
+}
+
+impl MergeMethod {
+    pub const ALL: [MergeMethod; 3] =
+        [MergeMethod::Merge, MergeMethod::Squash, MergeMethod::Rebase];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MergeMethod::Merge => "Merge",
+            MergeMethod::Squash => "Squash and merge",
+            MergeMethod::Rebase => "Rebase and merge",
+        }
+    }
+
+    /// Whether merging this way makes a commit whose message can be written.
+    /// Rebasing keeps the messages of the pull request's commits.
+    pub fn has_message(self) -> bool {
+        self != MergeMethod::Rebase
+    }
+}
+
+/// Whether a pull request can be merged without conflicts, as far as GitHub knows yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Mergeable {
+    Mergeable,
+    Conflicting,
+    #[default]
+    Unknown,
+}
+
+/// What merging a pull request would run into, including branch protection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MergeStateStatus {
+    // the head branch is out of date, and branch protection requires it to be up to date
+    Behind,
+    // branch protection requirements, like reviews or checks, aren't met
+    Blocked,
+    Clean,
+    // there are conflicts
+    Dirty,
+    Draft,
+    // mergeable, with passing checks and pre-receive hooks
+    HasHooks,
+    #[default]
+    Unknown,
+    // mergeable, with checks that aren't required failing
+    Unstable,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewDecision {
+    Approved,
+    ChangesRequested,
+    ReviewRequired,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoMergeRequest {
+    pub merge_method: MergeMethod,
+}
+
+/// What a pull request needs before it can be merged, and the ways it can be.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeStatus {
+    pub id: String,
+    pub number: u32,
+    pub title: String,
+    pub body: String,
+    pub is_draft: bool,
+    pub mergeable: Mergeable,
+    pub merge_state_status: MergeStateStatus,
+    pub review_decision: Option<ReviewDecision>,
+    pub head_ref_name: String,
+    pub head_repository_owner: Option<User>,
+    pub auto_merge_request: Option<AutoMergeRequest>,
+    // the head commit, with its checks
+    pub commits: Nodes<PullRequestCommit>,
+    // the merge methods the repo allows, and whether it allows auto-merge
+    #[serde(skip)]
+    pub methods: Vec<MergeMethod>,
+    #[serde(skip)]
+    pub auto_merge_allowed: bool,
+}
+
+impl MergeStatus {
+    fn check_state(&self) -> Option<CheckState> {
+        let commit = &self.commits.nodes.last()?.commit;
+        Some(commit.status_check_rollup.as_ref()?.state)
+    }
+
+    /// Why merging now would fail, if GitHub says it would.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.is_draft || self.merge_state_status == MergeStateStatus::Draft {
+            return Some("it is a draft");
+        }
+        if self.mergeable == Mergeable::Conflicting
+            || self.merge_state_status == MergeStateStatus::Dirty
+        {
+            return Some("it has conflicts that must be resolved");
+        }
+        match self.merge_state_status {
+            MergeStateStatus::Behind => Some("the head branch is behind the base branch"),
+            MergeStateStatus::Blocked => Some(match (self.review_decision, self.check_state()) {
+                (Some(ReviewDecision::ChangesRequested), _) => "changes were requested",
+                (Some(ReviewDecision::ReviewRequired), _) => "an approving review is required",
+                (_, Some(CheckState::Failure | CheckState::Error)) => "required checks are failing",
+                (_, Some(CheckState::Pending | CheckState::Expected)) => {
+                    "required checks haven't finished"
+                }
+                _ => "branch protection rules aren't met",
+            }),
+            _ => None,
+        }
+    }
+
+    /// The state of the things merging depends on, e.g. "mergeable · review required · checks failing".
+    pub fn summary(&self) -> String {
+        let mergeable = match (self.is_draft, self.mergeable) {
+            (true, _) => "draft",
+            (false, Mergeable::Mergeable) => "mergeable",
+            (false, Mergeable::Conflicting) => "conflicting",
+            (false, Mergeable::Unknown) => "checking mergeability",
+        };
+        let review = self.review_decision.map(|decision| match decision {
+            ReviewDecision::Approved => "approved",
+            ReviewDecision::ChangesRequested => "changes requested",
+            ReviewDecision::ReviewRequired => "review required",
+        });
+        let checks = self.check_state().map(|state| match state {
+            CheckState::Success => "checks passed",
+            CheckState::Failure | CheckState::Error => "checks failing",
+            CheckState::Pending | CheckState::Expected => "checks pending",
+        });
+        let auto = self.auto_merge_request.as_ref().map(|_| "auto-merge on");
+        [Some(mergeable), review, checks, auto]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+
+    /// The commit message GitHub would suggest for merging this way.
+    pub fn default_message(&self, method: MergeMethod) -> String {
+        match method {
+            MergeMethod::Merge => {
+                let owner = self.head_repository_owner.as_ref();
+                let owner = owner.map(|o| o.login.as_str()).unwrap_or_default();
+                format!(
+                    "Merge pull request #{} from {owner}/{}\n\n{}\n",
+                    self.number, self.head_ref_name, self.title
+                )
+            }
+            MergeMethod::Squash => {
+                format!("{} (#{})\n\n{}\n", self.title, self.number, self.body)
+            }
+            MergeMethod::Rebase => String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+struct PullRequestVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    number: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct MergeRepository {
+    merge_commit_allowed: bool,
+    squash_merge_allowed: bool,
+    rebase_merge_allowed: bool,
+    auto_merge_allowed: bool,
+    pull_request: MergeStatus,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct MergeStatusData {
+    repository: MergeRepository,
+}
+
+/// Fetch whether pull request `number` of `repo` can be merged, and how.
+pub async fn fetch_merge_status(github: &Github, repo: &str, number: u32) -> Result<MergeStatus> {
+    tracing::debug!("fetching merge status of {repo}#{number}");
+    let (owner, name) = split_repo(repo)?;
+    let data: MergeStatusData = github
+        .graphql(
+            MERGE_STATUS_GRAPHQL,
+            PullRequestVariables {
+                owner,
+                name,
+                number,
+            },
+        )
+        .await
+        .with_context(|| format!("Fetching merge status of {repo}#{number}"))?;
+    let repository = data.repository;
+    let allowed = [
+        repository.merge_commit_allowed,
+        repository.squash_merge_allowed,
+        repository.rebase_merge_allowed,
+    ];
+    Ok(MergeStatus {
+        methods: MergeMethod::ALL
+            .into_iter()
+            .zip(allowed)
+            .filter_map(|(method, allowed)| allowed.then_some(method))
+            .collect(),
+        auto_merge_allowed: repository.auto_merge_allowed,
+        ..repository.pull_request
+    })
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct MergeInput<'a> {
+    pull_request_id: &'a str,
+    merge_method: MergeMethod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit_headline: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit_body: Option<&'a str>,
+}
+
+impl<'a> MergeInput<'a> {
+    // The first line of the message is the commit's headline, and the rest its body.
+    fn new(pull_request_id: &'a str, method: MergeMethod, message: &'a str) -> Self {
+        let (headline, body) = message.trim().split_once('\n').unwrap_or((message, ""));
+        let (headline, body) = (headline.trim(), body.trim());
+        Self {
+            pull_request_id,
+            merge_method: method,
+            commit_headline: (method.has_message() && !headline.is_empty()).then_some(headline),
+            commit_body: method.has_message().then_some(body),
+        }
+    }
+}
+
+/// Merge a pull request, with a commit message unless it is rebased.
+pub async fn merge(github: &Github, id: &str, method: MergeMethod, message: &str) -> Result<()> {
+    tracing::debug!("merging {id} with {method:?}");
+    let input = MergeInput::new(id, method, message);
+    mutate(github, MERGE_PULL_REQUEST_GRAPHQL, input)
+        .await
+        .context("Merging")
+}
+
+/// Merge a pull request once its requirements are met.
+pub async fn enable_auto_merge(
+    github: &Github,
+    id: &str,
+    method: MergeMethod,
+    message: &str,
+) -> Result<()> {
+    tracing::debug!("enabling auto-merge of {id} with {method:?}");
+    let input = MergeInput::new(id, method, message);
+    mutate(github, ENABLE_AUTO_MERGE_GRAPHQL, input)
+        .await
+        .context("Enabling auto-merge")
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct DisableInput<'a> {
+    pull_request_id: &'a str,
+}
+
+pub async fn disable_auto_merge(github: &Github, id: &str) -> Result<()> {
+    tracing::debug!("disabling auto-merge of {id}");
+    let input = DisableInput {
+        pull_request_id: id,
+    };
+    mutate(github, DISABLE_AUTO_MERGE_GRAPHQL, input)
+        .await
+        .context("Disabling auto-merge")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{Matcher, Server};
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_merge() {
+        let mut server = Server::new_async().await;
+        let fetch = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"owner":"octocat","name":"Hello-World","number":2648}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/merge_status.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let mut status = fetch_merge_status(&github, "octocat/Hello-World", 2648)
+            .await
+            .unwrap();
+        fetch.assert_async().await;
+        assert_eq!(status.methods, MergeMethod::ALL);
+        assert!(status.auto_merge_allowed);
+        assert_eq!(
+            status.summary(),
+            "mergeable · review required · checks failing"
+        );
+        assert_eq!(status.problem(), Some("an approving review is required"));
+        status.review_decision = Some(ReviewDecision::Approved);
+        assert_eq!(status.problem(), Some("required checks are failing"));
+        status.mergeable = Mergeable::Conflicting;
+        assert_eq!(
+            status.problem(),
+            Some("it has conflicts that must be resolved")
+        );
+        status.merge_state_status = MergeStateStatus::Unstable;
+        status.mergeable = Mergeable::Mergeable;
+        assert_eq!(status.problem(), None);
+        assert_eq!(
+            status.default_message(MergeMethod::Merge),
+            "Merge pull request #2648 from codeblue1230/new-topic\n\nchanged a bit of code\n"
+        );
+
+        let input = MergeInput::new(
+            "PR_1",
+            MergeMethod::Squash,
+            "Fix (#1)\n\nBecause\nreasons\n",
+        );
+        assert_eq!(input.commit_headline, Some("Fix (#1)"));
+        assert_eq!(input.commit_body, Some("Because\nreasons"));
+        let input = MergeInput::new("PR_1", MergeMethod::Rebase, "ignored");
+        assert_eq!((input.commit_headline, input.commit_body), (None, None));
+
+        let refused = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestId":"PR_1","mergeMethod":"REBASE"}}}"#
+                    .into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"errors":[{"message":"At least 1 approving review is required by reviewers with write access."}]}"#,
+            )
+            .create_async()
+            .await;
+        let err = merge(&github, "PR_1", MergeMethod::Rebase, "")
+            .await
+            .unwrap_err();
+        refused.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Merging: At least 1 approving review is required by reviewers with write access."
+        );
+
+        // GitHub returns null data alongside the errors
+        let unprotected = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestId":"PR_2","mergeMethod":"MERGE"}}}"#
+                    .into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"data":{"enablePullRequestAutoMerge":null},"errors":[{"type":"UNPROCESSABLE","path":["enablePullRequestAutoMerge"],"message":"Pull request is in clean status"}]}"#,
+            )
+            .create_async()
+            .await;
+        let err = enable_auto_merge(&github, "PR_2", MergeMethod::Merge, "")
+            .await
+            .unwrap_err();
+        unprotected.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Enabling auto-merge: Pull request is in clean status"
+        );
+    }
+}
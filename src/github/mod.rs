@@ -1,7 +1,18 @@
+pub mod checks;
 pub mod code;
 pub mod issues;
+pub mod merge;
+pub mod mutation;
+pub mod notifications;
+pub mod pulls;
+pub mod reactions;
+pub mod repo;
+pub mod review;
+pub mod search;
+pub mod template;
 
-use serde::Deserialize;
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct Match {
@@ -18,3 +29,121 @@ pub struct Github {
     pub host: String,
     pub token: String,
 }
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GraphqlError {
+    pub message: String,
+}
+
+// A response may have data and errors together, such as a mutation that returned null.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct GraphqlResponse {
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+// The body of REST API errors.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct RestError {
+    message: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+struct GraphqlRequest<'a, V> {
+    query: &'a str,
+    variables: V,
+}
+
+impl Github {
+    /// Send a GraphQL query or mutation and parse the response data.
+    /// Fails if there are any errors, even if there is data too.
+    pub async fn graphql<V: Serialize, T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: V,
+    ) -> Result<T> {
+        let client = reqwest::Client::new();
+        let req = client
+            .request(reqwest::Method::POST, format!("{}/graphql", self.host))
+            .bearer_auth(&self.token)
+            .header(reqwest::header::USER_AGENT, env!("CARGO_PKG_NAME"))
+            .json(&GraphqlRequest { query, variables })
+            .build()?;
+        tracing::debug!("sending request: {req:?}");
+
+        let resp = client.execute(req).await?;
+        tracing::trace!("got response: {resp:?}");
+
+        let response_text = resp.text().await?;
+        let response: GraphqlResponse = serde_json::from_str(&response_text)
+            .with_context(|| format!("Failed to parse JSON response: {response_text}"))?;
+        if !response.errors.is_empty() {
+            let messages: Vec<_> = response.errors.into_iter().map(|e| e.message).collect();
+            bail!("{}", messages.join("; "))
+        }
+        let data = response
+            .data
+            .with_context(|| format!("No data in response: {response_text}"))?;
+        serde_json::from_value(data)
+            .with_context(|| format!("Failed to parse JSON response: {response_text}"))
+    }
+
+    /// Start a REST API request, e.g. to "/repos/foo/bar/pulls/1/files".
+    pub fn rest(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.rest_as(method, path, "application/vnd.github+json")
+    }
+
+    /// Start a REST API request for another media type, such as "application/vnd.github.diff".
+    pub fn rest_as(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        accept: &str,
+    ) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .request(method, format!("{}{path}", self.host))
+            .bearer_auth(&self.token)
+            .header(reqwest::header::USER_AGENT, env!("CARGO_PKG_NAME"))
+            .header(reqwest::header::ACCEPT, accept)
+    }
+
+    /// Send a REST API request and parse the JSON response.
+    /// Error statuses fail with the message GitHub gives.
+    pub async fn send<T: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<T> {
+        let response_text = self.send_text(req).await?;
+        serde_json::from_str(&response_text)
+            .with_context(|| format!("Failed to parse JSON response: {response_text}"))
+    }
+
+    /// Send a REST API request that responds with plain text, like a job's log,
+    /// or with nothing of interest.
+    pub async fn send_text(&self, req: reqwest::RequestBuilder) -> Result<String> {
+        Ok(self.send_raw(req).await?.text().await?)
+    }
+
+    /// Send a REST API request and return the response, for callers that need its headers.
+    /// Error statuses fail with the message GitHub gives, but 304 Not Modified doesn't.
+    pub async fn send_raw(&self, req: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let req = req.build()?;
+        tracing::debug!("sending request: {req:?}");
+        let resp = reqwest::Client::new().execute(req).await?;
+        tracing::trace!("got response: {resp:?}");
+
+        let status = resp.status();
+        if !status.is_success() && status != reqwest::StatusCode::NOT_MODIFIED {
+            let response_text = resp.text().await?;
+            let message = serde_json::from_str::<RestError>(&response_text)
+                .map(|e| e.message)
+                .unwrap_or(response_text);
+            bail!("GitHub returned {status}: {message}");
+        }
+        Ok(resp)
+    }
+
+    /// Get a REST API resource.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        self.send(self.rest(reqwest::Method::GET, path).query(query))
+            .await
+    }
+}
@@ -0,0 +1,624 @@
+use super::{
+    issues::{Issue, IssueKind, IssueState},
+    repo::{Actor, Label, Milestone},
+    Github,
+};
+use anyhow::{Context, Result};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+const ADD_COMMENT_GRAPHQL: &str = include_str!("add_comment.graphql");
+const CLOSE_ISSUE_GRAPHQL: &str = include_str!("close_issue.graphql");
+const CLOSE_PULL_REQUEST_GRAPHQL: &str = include_str!("close_pull_request.graphql");
+const REOPEN_ISSUE_GRAPHQL: &str = include_str!("reopen_issue.graphql");
+const REOPEN_PULL_REQUEST_GRAPHQL: &str = include_str!("reopen_pull_request.graphql");
+const ADD_LABELS_GRAPHQL: &str = include_str!("add_labels.graphql");
+const REMOVE_LABELS_GRAPHQL: &str = include_str!("remove_labels.graphql");
+const ADD_ASSIGNEES_GRAPHQL: &str = include_str!("add_assignees.graphql");
+const REMOVE_ASSIGNEES_GRAPHQL: &str = include_str!("remove_assignees.graphql");
+const UPDATE_ISSUE_GRAPHQL: &str = include_str!("update_issue.graphql");
+const UPDATE_PULL_REQUEST_GRAPHQL: &str = include_str!("update_pull_request.graphql");
+const CREATE_ISSUE_GRAPHQL: &str = include_str!("create_issue.graphql");
+
+/// Why an issue was closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CloseReason {
+    Completed,
+    NotPlanned,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub(super) struct Input<T> {
+    pub input: T,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct IssueInput<'a> {
+    issue_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_reason: Option<CloseReason>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestInput<'a> {
+    pull_request_id: &'a str,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct LabelsInput<'a> {
+    labelable_id: &'a str,
+    label_ids: &'a [&'a str],
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AssigneesInput<'a> {
+    assignable_id: &'a str,
+    assignee_ids: &'a [&'a str],
+}
+
+// A null milestone removes the milestone, so it is always sent.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct MilestoneInput<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pull_request_id: Option<&'a str>,
+    milestone_id: Option<&'a str>,
+}
+
+pub(super) async fn mutate<T: Serialize>(github: &Github, mutation: &str, input: T) -> Result<()> {
+    let _: IgnoredAny = github.graphql(mutation, Input { input }).await?;
+    Ok(())
+}
+
+/// Close an issue for the given reason, or a pull request.
+pub async fn close(
+    github: &Github,
+    kind: &IssueKind,
+    id: &str,
+    reason: Option<CloseReason>,
+) -> Result<()> {
+    tracing::debug!("closing {id} as {reason:?}");
+    match kind {
+        IssueKind::Issue => {
+            let input = IssueInput {
+                issue_id: id,
+                state_reason: reason,
+            };
+            mutate(github, CLOSE_ISSUE_GRAPHQL, input).await
+        }
+        IssueKind::PullRequest => {
+            let input = PullRequestInput {
+                pull_request_id: id,
+            };
+            mutate(github, CLOSE_PULL_REQUEST_GRAPHQL, input).await
+        }
+    }
+    .context("Closing")
+}
+
+pub async fn reopen(github: &Github, kind: &IssueKind, id: &str) -> Result<()> {
+    tracing::debug!("reopening {id}");
+    match kind {
+        IssueKind::Issue => {
+            let input = IssueInput {
+                issue_id: id,
+                state_reason: None,
+            };
+            mutate(github, REOPEN_ISSUE_GRAPHQL, input).await
+        }
+        IssueKind::PullRequest => {
+            let input = PullRequestInput {
+                pull_request_id: id,
+            };
+            mutate(github, REOPEN_PULL_REQUEST_GRAPHQL, input).await
+        }
+    }
+    .context("Reopening")
+}
+
+/// Add (or remove) labels from an issue or pull request.
+pub async fn set_labels(github: &Github, id: &str, label_ids: &[&str], add: bool) -> Result<()> {
+    tracing::debug!("setting labels {label_ids:?} on {id}: {add}");
+    let input = LabelsInput {
+        labelable_id: id,
+        label_ids,
+    };
+    let mutation = if add {
+        ADD_LABELS_GRAPHQL
+    } else {
+        REMOVE_LABELS_GRAPHQL
+    };
+    mutate(github, mutation, input)
+        .await
+        .context("Editing labels")
+}
+
+/// Add (or remove) assignees from an issue or pull request.
+pub async fn set_assignees(
+    github: &Github,
+    id: &str,
+    assignee_ids: &[&str],
+    add: bool,
+) -> Result<()> {
+    tracing::debug!("setting assignees {assignee_ids:?} on {id}: {add}");
+    let input = AssigneesInput {
+        assignable_id: id,
+        assignee_ids,
+    };
+    let mutation = if add {
+        ADD_ASSIGNEES_GRAPHQL
+    } else {
+        REMOVE_ASSIGNEES_GRAPHQL
+    };
+    mutate(github, mutation, input)
+        .await
+        .context("Editing assignees")
+}
+
+/// Set or clear the milestone of an issue or pull request.
+pub async fn set_milestone(
+    github: &Github,
+    kind: &IssueKind,
+    id: &str,
+    milestone_id: Option<&str>,
+) -> Result<()> {
+    tracing::debug!("setting milestone of {id} to {milestone_id:?}");
+    let (mutation, input) = match kind {
+        IssueKind::Issue => (
+            UPDATE_ISSUE_GRAPHQL,
+            MilestoneInput {
+                id: Some(id),
+                milestone_id,
+                ..Default::default()
+            },
+        ),
+        IssueKind::PullRequest => (
+            UPDATE_PULL_REQUEST_GRAPHQL,
+            MilestoneInput {
+                pull_request_id: Some(id),
+                milestone_id,
+                ..Default::default()
+            },
+        ),
+    };
+    mutate(github, mutation, input)
+        .await
+        .context("Setting milestone")
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddCommentVariables<'a> {
+    subject_id: &'a str,
+    body: &'a str,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Url {
+    url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct CommentEdge {
+    node: Url,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddComment {
+    comment_edge: CommentEdge,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddCommentData {
+    add_comment: AddComment,
+}
+
+/// Comment on the issue or pull request with the given node id.
+/// Returns the url of the new comment.
+pub async fn add_comment(github: &Github, subject_id: &str, body: &str) -> Result<String> {
+    tracing::debug!("adding comment to {subject_id}");
+    let data: AddCommentData = github
+        .graphql(
+            ADD_COMMENT_GRAPHQL,
+            AddCommentVariables { subject_id, body },
+        )
+        .await
+        .context("Adding comment")?;
+    Ok(data.add_comment.comment_edge.node.url)
+}
+
+/// An issue to create.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NewIssue {
+    pub repository_id: String,
+    pub title: String,
+    pub body: String,
+    pub label_ids: Vec<String>,
+    pub assignee_ids: Vec<String>,
+}
+
+/// The number and url of a created issue.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Created {
+    pub number: u32,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct CreateIssue {
+    issue: Created,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CreateIssueData {
+    create_issue: CreateIssue,
+}
+
+/// Create an issue.
+pub async fn create_issue(github: &Github, issue: &NewIssue) -> Result<Created> {
+    tracing::debug!("creating issue {:?}", issue.title);
+    let data: CreateIssueData = github
+        .graphql(CREATE_ISSUE_GRAPHQL, Input { input: issue })
+        .await
+        .context("Creating issue")?;
+    Ok(data.create_issue.issue)
+}
+
+/// A change to an issue or pull request.
+/// Changes are applied to the local copy while the mutation is sent, and undone if it fails.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Close(Option<CloseReason>),
+    Reopen,
+    AddLabel(Label),
+    RemoveLabel(Label),
+    AddAssignee(Actor),
+    RemoveAssignee(Actor),
+    SetMilestone(Option<Milestone>),
+}
+
+impl Change {
+    /// Apply the change to a local copy of an issue.
+    /// Returns the change that undoes it.
+    pub fn apply(&self, issue: &mut Issue) -> Change {
+        match self {
+            Change::Close(_) | Change::Reopen => {
+                let undo = match issue.state {
+                    IssueState::Open => Change::Reopen,
+                    _ => Change::Close(None),
+                };
+                issue.state = match self {
+                    Change::Reopen => IssueState::Open,
+                    _ => IssueState::Closed,
+                };
+                undo
+            }
+            Change::AddLabel(label) => {
+                if issue.labels.nodes.iter().any(|l| l.id == label.id) {
+                    return self.clone();
+                }
+                issue.labels.nodes.push(label.clone());
+                Change::RemoveLabel(label.clone())
+            }
+            Change::RemoveLabel(label) => {
+                let len = issue.labels.nodes.len();
+                issue.labels.nodes.retain(|l| l.id != label.id);
+                if issue.labels.nodes.len() == len {
+                    return self.clone();
+                }
+                Change::AddLabel(label.clone())
+            }
+            Change::AddAssignee(actor) => {
+                if issue.assignees.nodes.iter().any(|a| a.id == actor.id) {
+                    return self.clone();
+                }
+                issue.assignees.nodes.push(actor.clone());
+                Change::RemoveAssignee(actor.clone())
+            }
+            Change::RemoveAssignee(actor) => {
+                let len = issue.assignees.nodes.len();
+                issue.assignees.nodes.retain(|a| a.id != actor.id);
+                if issue.assignees.nodes.len() == len {
+                    return self.clone();
+                }
+                Change::AddAssignee(actor.clone())
+            }
+            Change::SetMilestone(milestone) => {
+                Change::SetMilestone(std::mem::replace(&mut issue.milestone, milestone.clone()))
+            }
+        }
+    }
+
+    /// Whether the change would do anything to the issue,
+    /// so bulk changes skip items that are already in the wanted state.
+    pub fn applies(&self, issue: &Issue) -> bool {
+        let labeled = |label: &Label| issue.labels.nodes.iter().any(|l| l.id == label.id);
+        let assigned = |actor: &Actor| issue.assignees.nodes.iter().any(|a| a.id == actor.id);
+        match self {
+            Change::Close(_) => issue.state == IssueState::Open,
+            Change::Reopen => issue.state == IssueState::Closed,
+            Change::AddLabel(label) => !labeled(label),
+            Change::RemoveLabel(label) => labeled(label),
+            Change::AddAssignee(actor) => !assigned(actor),
+            Change::RemoveAssignee(actor) => assigned(actor),
+            Change::SetMilestone(milestone) => {
+                issue.milestone.as_ref().map(|m| &m.id) != milestone.as_ref().map(|m| &m.id)
+            }
+        }
+    }
+
+    /// What the change does to `target`, e.g. "Closed #12" or "Closed 3 items".
+    pub fn describe(&self, target: &str) -> String {
+        match self {
+            Change::Close(Some(CloseReason::NotPlanned)) => {
+                format!("Closed {target} as not planned")
+            }
+            Change::Close(_) => format!("Closed {target}"),
+            Change::Reopen => format!("Reopened {target}"),
+            Change::AddLabel(label) => format!("Added label {} to {target}", label.name),
+            Change::RemoveLabel(label) => format!("Removed label {} from {target}", label.name),
+            Change::AddAssignee(actor) => format!("Assigned {} to {target}", actor.login),
+            Change::RemoveAssignee(actor) => format!("Unassigned {} from {target}", actor.login),
+            Change::SetMilestone(Some(milestone)) => {
+                format!("Set milestone of {target} to {}", milestone.title)
+            }
+            Change::SetMilestone(None) => format!("Removed milestone of {target}"),
+        }
+    }
+
+    /// Make the change on GitHub.
+    pub async fn send(self, github: Github, kind: IssueKind, id: String) -> Result<()> {
+        match self {
+            Change::Close(reason) => close(&github, &kind, &id, reason).await,
+            Change::Reopen => reopen(&github, &kind, &id).await,
+            Change::AddLabel(label) => set_labels(&github, &id, &[&label.id], true).await,
+            Change::RemoveLabel(label) => set_labels(&github, &id, &[&label.id], false).await,
+            Change::AddAssignee(actor) => set_assignees(&github, &id, &[&actor.id], true).await,
+            Change::RemoveAssignee(actor) => set_assignees(&github, &id, &[&actor.id], false).await,
+            Change::SetMilestone(milestone) => {
+                let milestone_id = milestone.as_ref().map(|m| m.id.as_str());
+                set_milestone(&github, &kind, &id, milestone_id).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::Server;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_add_comment() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"variables":{"subjectId":"I_1","body":"hello"}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/add_comment.json").unwrap())
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        assert_eq!(
+            add_comment(&github, "I_1", "hello").await.unwrap(),
+            "https://github.com/octocat/Hello-World/issues/2356#issuecomment-1"
+        );
+        mock.assert_async().await;
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_create_issue() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"repositoryId":"R_1","title":"Crash","labelIds":["LA_1"]}}}"#
+                    .into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/create_issue.json").unwrap())
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let issue = NewIssue {
+            repository_id: "R_1".into(),
+            title: "Crash".into(),
+            body: "It crashed".into(),
+            label_ids: vec!["LA_1".into()],
+            ..Default::default()
+        };
+        let created = create_issue(&github, &issue).await.unwrap();
+        assert_eq!(created.number, 2357);
+        mock.assert_async().await;
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_mutations() {
+        let mut server = Server::new_async().await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        async fn expect(server: &mut Server, body: &str) -> mockito::Mock {
+            server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::PartialJsonString(body.into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"data":{"result":{"clientMutationId":null}}}"#)
+                .create_async()
+                .await
+        }
+
+        let mock = expect(
+            &mut server,
+            r#"{"variables":{"input":{"issueId":"I_1","stateReason":"NOT_PLANNED"}}}"#,
+        )
+        .await;
+        close(
+            &github,
+            &IssueKind::Issue,
+            "I_1",
+            Some(CloseReason::NotPlanned),
+        )
+        .await
+        .unwrap();
+        mock.assert_async().await;
+
+        let mock = expect(
+            &mut server,
+            r#"{"variables":{"input":{"pullRequestId":"PR_1"}}}"#,
+        )
+        .await;
+        reopen(&github, &IssueKind::PullRequest, "PR_1")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+
+        let mock = expect(
+            &mut server,
+            r#"{"variables":{"input":{"labelableId":"I_1","labelIds":["LA_1"]}}}"#,
+        )
+        .await;
+        set_labels(&github, "I_1", &["LA_1"], false).await.unwrap();
+        mock.assert_async().await;
+
+        let mock = expect(
+            &mut server,
+            r#"{"variables":{"input":{"assignableId":"I_1","assigneeIds":["U_1"]}}}"#,
+        )
+        .await;
+        set_assignees(&github, "I_1", &["U_1"], true).await.unwrap();
+        mock.assert_async().await;
+
+        let mock = expect(
+            &mut server,
+            r#"{"variables":{"input":{"id":"I_1","milestoneId":null}}}"#,
+        )
+        .await;
+        set_milestone(&github, &IssueKind::Issue, "I_1", None)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_add_comment_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"errors":[{"message":"Could not resolve to a node"}]}"#)
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let err = add_comment(&github, "I_1", "hello").await.unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Could not resolve to a node"),
+            "{err:#}"
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_close_partial_error() {
+        let mut server = Server::new_async().await;
+        // GitHub returns what data it has alongside the errors
+        let _mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"data":{"closeIssue":null},"errors":[{"type":"FORBIDDEN","path":["closeIssue"],"message":"Resource not accessible by integration"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let err = close(&github, &IssueKind::Issue, "I_1", None)
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Resource not accessible by integration"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn test_change() {
+        let bug = Label {
+            id: "LA_1".into(),
+            name: "bug".into(),
+            ..Default::default()
+        };
+        let mut issue = Issue::default();
+
+        let undo = Change::Close(Some(CloseReason::Completed)).apply(&mut issue);
+        assert_eq!(issue.state, IssueState::Closed);
+        assert_eq!(undo, Change::Reopen);
+        undo.apply(&mut issue);
+        assert_eq!(issue.state, IssueState::Open);
+
+        assert!(Change::AddLabel(bug.clone()).applies(&issue));
+        let undo = Change::AddLabel(bug.clone()).apply(&mut issue);
+        assert_eq!(issue.labels.nodes, vec![bug.clone()]);
+        assert!(!Change::AddLabel(bug.clone()).applies(&issue));
+        assert!(!Change::Reopen.applies(&issue));
+        assert_eq!(
+            Change::AddLabel(bug.clone()).describe("3 items"),
+            "Added label bug to 3 items"
+        );
+        assert_eq!(undo, Change::RemoveLabel(bug.clone()));
+        // adding it again changes nothing, so there is nothing to undo
+        let again = Change::AddLabel(bug.clone()).apply(&mut issue);
+        again.apply(&mut issue);
+        assert_eq!(issue.labels.nodes, vec![bug.clone()]);
+        undo.apply(&mut issue);
+        assert!(issue.labels.nodes.is_empty());
+
+        let milestone = Milestone {
+            id: "MI_1".into(),
+            number: 1,
+            title: "v1.0".into(),
+        };
+        let undo = Change::SetMilestone(Some(milestone.clone())).apply(&mut issue);
+        assert_eq!(issue.milestone, Some(milestone));
+        undo.apply(&mut issue);
+        assert_eq!(issue.milestone, None);
+    }
+}
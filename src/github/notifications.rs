@@ -0,0 +1,330 @@
+use super::{
+    issues::User,
+    search::{Page, Poll},
+    Github,
+};
+use anyhow::{bail, Context, Result};
+use reqwest::{header, Method, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+
+// notifications requested per page
+const PAGE_SIZE: usize = 50;
+
+// how often to poll if GitHub doesn't say
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct NotificationRepository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+/// What a notification is about.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Subject {
+    pub title: String,
+    // the API url of the issue, pull request, release or commit, if it has one
+    pub url: Option<String>,
+    // e.g. "Issue", "PullRequest", "Release" or "CheckSuite"
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// A notification thread.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Notification {
+    pub id: String,
+    pub unread: bool,
+    // why the viewer was notified, e.g. "mention", "review_requested" or "ci_activity"
+    pub reason: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub subject: Subject,
+    pub repository: NotificationRepository,
+}
+
+impl Notification {
+    /// The page on GitHub of what the notification is about.
+    /// Falls back to the repository's releases, discussions or actions when there's no better page.
+    pub fn html_url(&self) -> String {
+        let repo_url = &self.repository.html_url;
+        let prefix = format!("/repos/{}/", self.repository.full_name);
+        let rest = self
+            .subject
+            .url
+            .as_deref()
+            .and_then(|url| Some(&url[url.find(&prefix)? + prefix.len()..]));
+        match rest.and_then(|rest| rest.split_once('/')) {
+            Some(("issues", number)) => format!("{repo_url}/issues/{number}"),
+            Some(("pulls", number)) => format!("{repo_url}/pull/{number}"),
+            Some(("commits", sha)) => format!("{repo_url}/commit/{sha}"),
+            Some(("releases", _)) => format!("{repo_url}/releases"),
+            _ => match self.subject.kind.as_str() {
+                "Discussion" => format!("{repo_url}/discussions"),
+                "CheckSuite" => format!("{repo_url}/actions"),
+                _ => repo_url.clone(),
+            },
+        }
+    }
+}
+
+// The path and parameters of the request for an inbox query, like "is:unread repo:octocat/Hello-World".
+// Without is:unread, read notifications are listed too.
+fn inbox_request(query: &str) -> Result<(String, Vec<(&'static str, &'static str)>)> {
+    let mut path = "/notifications".to_string();
+    let mut params = vec![("all", "true")];
+    for term in query.split_whitespace() {
+        match term.split_once(':') {
+            Some(("is", "unread")) => params.retain(|&(name, _)| name != "all"),
+            Some(("is", "participating")) => params.push(("participating", "true")),
+            Some(("repo", repo)) if repo.contains('/') => {
+                path = format!("/repos/{repo}/notifications")
+            }
+            _ => bail!("Unsupported filter {term}, expected is:unread, is:participating or repo:"),
+        }
+    }
+    Ok((path, params))
+}
+
+/// Fetch page `page` of the notifications matching `query`, or the first page.
+/// The first page says when to poll for changes,
+/// and returns None if it hasn't changed since `last_modified`.
+pub async fn fetch_notifications(
+    github: &Github,
+    query: &str,
+    page: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<Option<Page<Notification>>> {
+    tracing::debug!("fetching notifications for {query} at page {page:?}");
+    let page: u32 = match page {
+        Some(page) => page.parse().with_context(|| format!("Bad page {page}"))?,
+        None => 1,
+    };
+    let (path, params) = inbox_request(query)?;
+    let mut req = github.rest(Method::GET, &path).query(&params).query(&[
+        ("page", page.to_string()),
+        ("per_page", PAGE_SIZE.to_string()),
+    ]);
+    if let Some(last_modified) = last_modified {
+        req = req.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
+    let resp = github
+        .send_raw(req)
+        .await
+        .context("Fetching notifications")?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let poll = Poll {
+        last_modified: header(header::LAST_MODIFIED),
+        interval: header(header::HeaderName::from_static("x-poll-interval"))
+            .and_then(|secs| secs.parse().ok())
+            .map_or(DEFAULT_POLL_INTERVAL, Duration::from_secs),
+    };
+    let response_text = resp.text().await?;
+    let items: Vec<Notification> = serde_json::from_str(&response_text)
+        .with_context(|| format!("Failed to parse JSON response: {response_text}"))?;
+    Ok(Some(Page {
+        next: (items.len() == PAGE_SIZE).then(|| (page + 1).to_string()),
+        items,
+        poll: (page == 1).then_some(poll),
+    }))
+}
+
+/// Mark a notification thread as read.
+pub async fn mark_read(github: &Github, id: &str) -> Result<()> {
+    tracing::debug!("marking notification {id} as read");
+    let req = github.rest(Method::PATCH, &format!("/notifications/threads/{id}"));
+    github
+        .send_text(req)
+        .await
+        .context("Marking notification as read")?;
+    Ok(())
+}
+
+/// Mark a notification thread as done, removing it from the inbox.
+pub async fn mark_done(github: &Github, id: &str) -> Result<()> {
+    tracing::debug!("marking notification {id} as done");
+    let req = github.rest(Method::DELETE, &format!("/notifications/threads/{id}"));
+    github
+        .send_text(req)
+        .await
+        .context("Marking notification as done")?;
+    Ok(())
+}
+
+/// Stop notifying the viewer of a thread until they comment or are mentioned.
+pub async fn unsubscribe(github: &Github, id: &str) -> Result<()> {
+    tracing::debug!("unsubscribing from notification {id}");
+    let req = github.rest(
+        Method::DELETE,
+        &format!("/notifications/threads/{id}/subscription"),
+    );
+    github
+        .send_text(req)
+        .await
+        .context("Unsubscribing from notification")?;
+    Ok(())
+}
+
+/// The issue, pull request or release a notification is about.
+/// Issues and pull requests have a `user`, releases an `author`, `name` and `tag_name`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SubjectDetails {
+    pub title: Option<String>,
+    pub name: Option<String>,
+    pub tag_name: Option<String>,
+    pub state: Option<String>,
+    #[serde(default)]
+    pub merged: bool,
+    pub user: Option<User>,
+    pub author: Option<User>,
+    pub body: Option<String>,
+}
+
+/// Fetch what a notification is about, or None if it has no API url, like CI activity.
+pub async fn fetch_subject(
+    github: &Github,
+    notification: &Notification,
+) -> Result<Option<SubjectDetails>> {
+    let Some(url) = &notification.subject.url else {
+        return Ok(None);
+    };
+    let path = url
+        .find("/repos/")
+        .map(|start| &url[start..])
+        .with_context(|| format!("Unexpected subject url {url}"))?;
+    tracing::debug!("fetching the subject of notification {}", notification.id);
+    github
+        .get(path, &[])
+        .await
+        .with_context(|| format!("Fetching {}", notification.subject.title))
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_inbox_request() {
+        assert_eq!(
+            inbox_request("").unwrap(),
+            ("/notifications".to_string(), vec![("all", "true")])
+        );
+        assert_eq!(
+            inbox_request("is:unread is:participating repo:octocat/Hello-World").unwrap(),
+            (
+                "/repos/octocat/Hello-World/notifications".to_string(),
+                vec![("participating", "true")]
+            )
+        );
+        assert!(inbox_request("label:bug").is_err());
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_notifications() {
+        let mut server = Server::new_async().await;
+        let first = server
+            .mock("GET", "/notifications")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("all".into(), "true".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .match_header("if-modified-since", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("last-modified", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_header("x-poll-interval", "30")
+            .with_body(std::fs::read_to_string("testdata/notifications.json").unwrap())
+            .create_async()
+            .await;
+        let unchanged = server
+            .mock("GET", "/notifications")
+            .match_query(Matcher::Any)
+            .match_header("if-modified-since", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_status(304)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let page = fetch_notifications(&github, "", None, None)
+            .await
+            .unwrap()
+            .unwrap();
+        first.assert_async().await;
+        assert_eq!(page.items.len(), 3);
+        assert_eq!(page.next, None);
+        assert_eq!(
+            page.poll,
+            Some(Poll {
+                last_modified: Some("Thu, 02 May 2024 09:00:00 GMT".to_string()),
+                interval: Duration::from_secs(30),
+            })
+        );
+        let urls: Vec<_> = page.items.iter().map(Notification::html_url).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://github.com/octocat/Hello-World/issues/123",
+                "https://github.com/octocat/Hello-World/pull/124",
+                "https://github.com/octocat/Spoon-Knife/actions",
+            ]
+        );
+
+        let last_modified = page.poll.unwrap().last_modified;
+        let polled = fetch_notifications(&github, "", None, last_modified.as_deref())
+            .await
+            .unwrap();
+        unchanged.assert_async().await;
+        assert_eq!(polled, None);
+
+        let subject = server
+            .mock("GET", "/repos/octocat/Hello-World/pulls/124")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"title":"Fix the greeting","state":"open","merged":false,"user":{"login":"monalisa"},"body":"Says hello properly."}"#)
+            .create_async()
+            .await;
+        let details = fetch_subject(&github, &page.items[1])
+            .await
+            .unwrap()
+            .unwrap();
+        subject.assert_async().await;
+        assert_eq!(details.body.as_deref(), Some("Says hello properly."));
+        assert_eq!(fetch_subject(&github, &page.items[2]).await.unwrap(), None);
+
+        let read = server
+            .mock("PATCH", "/notifications/threads/1")
+            .with_status(205)
+            .create_async()
+            .await;
+        let done = server
+            .mock("DELETE", "/notifications/threads/1")
+            .with_status(204)
+            .create_async()
+            .await;
+        let unsubscribed = server
+            .mock("DELETE", "/notifications/threads/1/subscription")
+            .with_status(204)
+            .create_async()
+            .await;
+        mark_read(&github, "1").await.unwrap();
+        mark_done(&github, "1").await.unwrap();
+        unsubscribe(&github, "1").await.unwrap();
+        read.assert_async().await;
+        done.assert_async().await;
+        unsubscribed.assert_async().await;
+    }
+}
@@ -0,0 +1,107 @@
+use super::{repo::split_repo, Github};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+// GitHub lists at most 3000 files of a pull request, 100 at a time.
+const MAX_FILE_PAGES: usize = 30;
+const FILES_PER_PAGE: usize = 100;
+
+/// A file changed by a pull request.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct PullFile {
+    pub filename: String,
+    // added, removed, modified, renamed, copied, changed or unchanged
+    pub status: String,
+    pub additions: u32,
+    pub deletions: u32,
+    // the unified diff, missing for binary and very large files
+    #[serde(default)]
+    pub patch: Option<String>,
+    #[serde(default)]
+    pub previous_filename: Option<String>,
+}
+
+impl PullFile {
+    /// A one letter summary of the status, as `git status --short` shows it.
+    pub fn status_letter(&self) -> char {
+        match self.status.as_str() {
+            "added" => 'A',
+            "removed" => 'D',
+            "renamed" => 'R',
+            "copied" => 'C',
+            _ => 'M',
+        }
+    }
+}
+
+/// Fetch the files changed by pull request `number` of `repo`, with their patches.
+pub async fn fetch_files(github: &Github, repo: &str, number: u32) -> Result<Vec<PullFile>> {
+    tracing::debug!("fetching files of {repo}#{number}");
+    let (owner, name) = split_repo(repo)?;
+    let path = format!("/repos/{owner}/{name}/pulls/{number}/files");
+    let mut files = Vec::new();
+    for page in 1..=MAX_FILE_PAGES {
+        let page = page.to_string();
+        let per_page = FILES_PER_PAGE.to_string();
+        let batch: Vec<PullFile> = github
+            .get(&path, &[("page", &page), ("per_page", &per_page)])
+            .await
+            .with_context(|| format!("Fetching files of {repo}#{number}"))?;
+        let last = batch.len() < FILES_PER_PAGE;
+        files.extend(batch);
+        if last {
+            break;
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::Server;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_fetch_files() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/repos/octocat/Hello-World/pulls/2648/files")
+            .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/pull_files.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let files = fetch_files(&github, "octocat/Hello-World", 2648)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].filename, "src/main.rs");
+        assert_eq!((files[0].additions, files[0].deletions), (2, 1));
+        assert_eq!(files[1].status_letter(), 'A');
+        assert_eq!(files[1].patch, None);
+
+        let missing = server
+            .mock("GET", "/repos/octocat/Hello-World/pulls/1/files")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create_async()
+            .await;
+        let err = fetch_files(&github, "octocat/Hello-World", 1)
+            .await
+            .unwrap_err();
+        missing.assert_async().await;
+        assert!(
+            format!("{err:#}").contains("404 Not Found: Not Found"),
+            "{err:#}"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use super::{
+    issues::User,
+    mutation::mutate,
+    repo::{split_repo, Nodes},
+    Github,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const COMMENTS_GRAPHQL: &str = include_str!("comments.graphql");
+const ADD_REACTION_GRAPHQL: &str = include_str!("add_reaction.graphql");
+const REMOVE_REACTION_GRAPHQL: &str = include_str!("remove_reaction.graphql");
+
+/// The emoji an issue or comment can be reacted to with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Reaction {
+    ThumbsUp,
+    ThumbsDown,
+    Laugh,
+    Hooray,
+    Confused,
+    Heart,
+    Rocket,
+    Eyes,
+}
+
+impl Reaction {
+    /// In the order GitHub shows them.
+    pub const ALL: [Reaction; 8] = [
+        Reaction::ThumbsUp,
+        Reaction::ThumbsDown,
+        Reaction::Laugh,
+        Reaction::Hooray,
+        Reaction::Confused,
+        Reaction::Heart,
+        Reaction::Rocket,
+        Reaction::Eyes,
+    ];
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Reaction::ThumbsUp => "👍",
+            Reaction::ThumbsDown => "👎",
+            Reaction::Laugh => "😄",
+            Reaction::Hooray => "🎉",
+            Reaction::Confused => "😕",
+            Reaction::Heart => "❤️",
+            Reaction::Rocket => "🚀",
+            Reaction::Eyes => "👀",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Reaction::ThumbsUp => "+1",
+            Reaction::ThumbsDown => "-1",
+            Reaction::Laugh => "laugh",
+            Reaction::Hooray => "hooray",
+            Reaction::Confused => "confused",
+            Reaction::Heart => "heart",
+            Reaction::Rocket => "rocket",
+            Reaction::Eyes => "eyes",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TotalCount {
+    pub total_count: u32,
+}
+
+/// How many reacted to an issue or comment with one emoji, and whether the viewer did.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionGroup {
+    pub content: Reaction,
+    pub viewer_has_reacted: bool,
+    pub reactors: TotalCount,
+}
+
+/// The reactions with any reactors, e.g. "👍 12 🎉 3".
+pub fn summary(groups: &[ReactionGroup]) -> String {
+    groups
+        .iter()
+        .filter(|g| g.reactors.total_count > 0)
+        .map(|g| format!("{} {}", g.content.emoji(), g.reactors.total_count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the viewer has reacted with `reaction`.
+pub fn has_reacted(groups: &[ReactionGroup], reaction: Reaction) -> bool {
+    groups
+        .iter()
+        .any(|g| g.content == reaction && g.viewer_has_reacted)
+}
+
+/// Count the viewer's reaction as added or removed, once GitHub has done so.
+pub fn record(groups: &mut Vec<ReactionGroup>, reaction: Reaction, added: bool) {
+    let index = match groups.iter().position(|g| g.content == reaction) {
+        Some(index) => index,
+        None => {
+            groups.push(ReactionGroup {
+                content: reaction,
+                viewer_has_reacted: false,
+                reactors: TotalCount::default(),
+            });
+            groups.len() - 1
+        }
+    };
+    let group = &mut groups[index];
+    if group.viewer_has_reacted == added {
+        return;
+    }
+    group.viewer_has_reacted = added;
+    group.reactors.total_count = match added {
+        true => group.reactors.total_count + 1,
+        false => group.reactors.total_count.saturating_sub(1),
+    };
+    groups.sort_by_key(|g| Reaction::ALL.iter().position(|&r| r == g.content));
+}
+
+/// A comment on an issue or pull request, outside of any review.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    pub id: String,
+    pub author: Option<User>,
+    pub body: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub reaction_groups: Vec<ReactionGroup>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+struct IssueVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    number: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Comments {
+    comments: Nodes<Comment>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CommentsRepository {
+    issue_or_pull_request: Option<Comments>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct CommentsData {
+    repository: CommentsRepository,
+}
+
+/// Fetch the first 100 comments on issue or pull request `number` of `repo`, with their reactions.
+pub async fn fetch_comments(github: &Github, repo: &str, number: u32) -> Result<Vec<Comment>> {
+    tracing::debug!("fetching comments on {repo}#{number}");
+    let (owner, name) = split_repo(repo)?;
+    let data: CommentsData = github
+        .graphql(
+            COMMENTS_GRAPHQL,
+            IssueVariables {
+                owner,
+                name,
+                number,
+            },
+        )
+        .await
+        .with_context(|| format!("Fetching comments on {repo}#{number}"))?;
+    let issue = data
+        .repository
+        .issue_or_pull_request
+        .with_context(|| format!("{repo}#{number} not found"))?;
+    Ok(issue.comments.nodes)
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ReactionInput<'a> {
+    subject_id: &'a str,
+    content: Reaction,
+}
+
+/// Add or remove the viewer's reaction to an issue, pull request or comment.
+pub async fn react(github: &Github, subject_id: &str, reaction: Reaction, add: bool) -> Result<()> {
+    tracing::debug!("reacting to {subject_id} with {reaction:?}: {add}");
+    let input = ReactionInput {
+        subject_id,
+        content: reaction,
+    };
+    match add {
+        true => mutate(github, ADD_REACTION_GRAPHQL, input)
+            .await
+            .context("Adding reaction"),
+        false => mutate(github, REMOVE_REACTION_GRAPHQL, input)
+            .await
+            .context("Removing reaction"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{Matcher, Server};
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_reactions() {
+        let mut server = Server::new_async().await;
+        let fetch = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"owner":"octocat","name":"Hello-World","number":2356}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/comments.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let comments = fetch_comments(&github, "octocat/Hello-World", 2356)
+            .await
+            .unwrap();
+        fetch.assert_async().await;
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].id, "IC_1");
+        let mut groups = comments[0].reaction_groups.clone();
+        assert_eq!(summary(&groups), "👍 12 🎉 3");
+        assert!(has_reacted(&groups, Reaction::Hooray));
+        assert!(!has_reacted(&groups, Reaction::ThumbsUp));
+
+        record(&mut groups, Reaction::ThumbsUp, true);
+        record(&mut groups, Reaction::Hooray, false);
+        record(&mut groups, Reaction::Eyes, true);
+        assert_eq!(summary(&groups), "👍 13 🎉 2 👀 1");
+        assert!(has_reacted(&groups, Reaction::ThumbsUp));
+        // already counted
+        record(&mut groups, Reaction::Eyes, true);
+        assert_eq!(summary(&groups), "👍 13 🎉 2 👀 1");
+        assert_eq!(summary(&comments[1].reaction_groups), "");
+
+        let add = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"subjectId":"IC_1","content":"THUMBS_UP"}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"data":{"addReaction":{"reaction":{"content":"THUMBS_UP"}}}}"#)
+            .create_async()
+            .await;
+        react(&github, "IC_1", Reaction::ThumbsUp, true)
+            .await
+            .unwrap();
+        add.assert_async().await;
+
+        // a locked conversation can't be reacted to, which GitHub reports next to null data
+        let locked = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"subjectId":"IC_2","content":"EYES"}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"data":{"removeReaction":null},"errors":[{"type":"FORBIDDEN","path":["removeReaction"],"message":"Unable to react to a locked conversation"}]}"#,
+            )
+            .create_async()
+            .await;
+        let err = react(&github, "IC_2", Reaction::Eyes, false)
+            .await
+            .unwrap_err();
+        locked.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Removing reaction: Unable to react to a locked conversation"
+        );
+    }
+}
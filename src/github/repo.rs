@@ -0,0 +1,158 @@
+use super::Github;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const METADATA_GRAPHQL: &str = include_str!("metadata.graphql");
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+struct RepoVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct MetadataData {
+    repository: RepoMetadata,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Nodes<T> {
+    pub nodes: Vec<T>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Label {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Milestone {
+    pub id: String,
+    pub number: u32,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Actor {
+    pub id: String,
+    pub login: String,
+}
+
+/// Values that can be used to search and edit items in a repository.
+/// Only the first 100 of each are fetched.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoMetadata {
+    pub id: String,
+    pub labels: Nodes<Label>,
+    pub milestones: Nodes<Milestone>,
+    pub assignable_users: Nodes<Actor>,
+}
+
+/// Split an "owner/name" repository into its parts.
+pub fn split_repo(repo: &str) -> Result<(&str, &str)> {
+    repo.split_once('/')
+        .filter(|(owner, name)| !owner.is_empty() && !name.is_empty())
+        .with_context(|| format!("Repository '{repo}' is not in the form owner/name"))
+}
+
+pub async fn fetch_metadata(github: &Github, repo: &str) -> Result<RepoMetadata> {
+    tracing::debug!("fetching metadata for {repo}");
+    let (owner, name) = split_repo(repo)?;
+    let data: MetadataData = github
+        .graphql(METADATA_GRAPHQL, RepoVariables { owner, name })
+        .await
+        .with_context(|| format!("Fetching metadata for {repo}"))?;
+    tracing::trace!("fetched metadata: {data:?}");
+    Ok(data.repository)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::Server;
+
+    #[test]
+    fn test_split_repo() {
+        assert_eq!(split_repo("foo/bar").unwrap(), ("foo", "bar"));
+        assert!(split_repo("foo").is_err());
+        assert!(split_repo("/bar").is_err());
+        assert!(split_repo("foo/").is_err());
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_fetch_metadata() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"variables":{"owner":"octocat","name":"Hello-World"}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/metadata.json").unwrap())
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+
+        let metadata = fetch_metadata(&github, "octocat/Hello-World")
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata,
+            RepoMetadata {
+                id: "R_kgDOABCDEF".into(),
+                labels: Nodes {
+                    nodes: vec![
+                        Label {
+                            id: "LA_1".into(),
+                            name: "bug".into(),
+                            description: Some("Something isn't working".into()),
+                        },
+                        Label {
+                            id: "LA_2".into(),
+                            name: "good first issue".into(),
+                            description: None,
+                        },
+                    ]
+                },
+                milestones: Nodes {
+                    nodes: vec![Milestone {
+                        id: "MI_1".into(),
+                        number: 1,
+                        title: "v1.0".into(),
+                    }]
+                },
+                assignable_users: Nodes {
+                    nodes: vec![Actor {
+                        id: "U_1".into(),
+                        login: "octocat".into(),
+                    }]
+                },
+            }
+        );
+        mock.assert_async().await;
+
+        let mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"errors":[{"message":"Could not resolve to a Repository"}]}"#)
+            .create_async()
+            .await;
+        let err = fetch_metadata(&github, "octocat/nope").await.unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Could not resolve to a Repository"),
+            "{err:#}"
+        );
+        mock.assert_async().await;
+    }
+}
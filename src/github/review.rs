@@ -0,0 +1,424 @@
+use super::{
+    issues::User,
+    mutation::{mutate, Input},
+    repo::{split_repo, Nodes},
+    Github,
+};
+use anyhow::{Context, Result};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+const REVIEW_THREADS_GRAPHQL: &str = include_str!("review_threads.graphql");
+const ADD_REVIEW_GRAPHQL: &str = include_str!("add_review.graphql");
+const ADD_REVIEW_THREAD_GRAPHQL: &str = include_str!("add_review_thread.graphql");
+const ADD_REVIEW_THREAD_REPLY_GRAPHQL: &str = include_str!("add_review_thread_reply.graphql");
+const RESOLVE_REVIEW_THREAD_GRAPHQL: &str = include_str!("resolve_review_thread.graphql");
+const UNRESOLVE_REVIEW_THREAD_GRAPHQL: &str = include_str!("unresolve_review_thread.graphql");
+const SUBMIT_REVIEW_GRAPHQL: &str = include_str!("submit_review.graphql");
+
+/// Which side of a diff a line is on: the old version, or the new one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Side {
+    Left,
+    #[default]
+    Right,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ReviewComment {
+    pub author: Option<User>,
+    pub body: String,
+}
+
+/// Review comments on lines of a file, and the replies to them.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    pub id: String,
+    pub is_resolved: bool,
+    pub path: String,
+    // the last line commented on, missing once the diff no longer has it
+    pub line: Option<u32>,
+    pub start_line: Option<u32>,
+    pub diff_side: Side,
+    pub comments: Nodes<ReviewComment>,
+}
+
+/// The review threads of a pull request, and the viewer's review that is yet to be submitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Review {
+    pub pull_request_id: String,
+    pub pending: Option<String>,
+    pub threads: Vec<Thread>,
+}
+
+/// How a submitted review judges the pull request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewEvent {
+    Comment,
+    Approve,
+    RequestChanges,
+}
+
+impl ReviewEvent {
+    pub const ALL: [ReviewEvent; 3] = [
+        ReviewEvent::Comment,
+        ReviewEvent::Approve,
+        ReviewEvent::RequestChanges,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReviewEvent::Comment => "Comment",
+            ReviewEvent::Approve => "Approve",
+            ReviewEvent::RequestChanges => "Request changes",
+        }
+    }
+}
+
+/// Where a new review comment goes: the lines from `start_line` to `line`,
+/// or just `line` if there's no start.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NewThread {
+    pub path: String,
+    pub line: u32,
+    pub side: Side,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_side: Option<Side>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+struct PullRequestVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    number: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Id {
+    id: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestReviews {
+    id: String,
+    reviews: Nodes<Id>,
+    review_threads: Nodes<Thread>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ReviewRepository {
+    pull_request: PullRequestReviews,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct ReviewData {
+    repository: ReviewRepository,
+}
+
+/// Fetch the review threads of pull request `number` of `repo`.
+/// Only the first 100 threads, and 50 comments of each, are fetched.
+pub async fn fetch_review(github: &Github, repo: &str, number: u32) -> Result<Review> {
+    tracing::debug!("fetching review threads of {repo}#{number}");
+    let (owner, name) = split_repo(repo)?;
+    let data: ReviewData = github
+        .graphql(
+            REVIEW_THREADS_GRAPHQL,
+            PullRequestVariables {
+                owner,
+                name,
+                number,
+            },
+        )
+        .await
+        .with_context(|| format!("Fetching review threads of {repo}#{number}"))?;
+    let pull_request = data.repository.pull_request;
+    Ok(Review {
+        pull_request_id: pull_request.id,
+        pending: pull_request.reviews.nodes.into_iter().next().map(|r| r.id),
+        threads: pull_request.review_threads.nodes,
+    })
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddReviewInput<'a> {
+    pull_request_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<ReviewEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddReview {
+    pull_request_review: Id,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddReviewData {
+    add_pull_request_review: AddReview,
+}
+
+/// Start a pending review of a pull request, returning its id.
+pub async fn start_review(github: &Github, pull_request_id: &str) -> Result<String> {
+    tracing::debug!("starting review of {pull_request_id}");
+    let input = AddReviewInput {
+        pull_request_id,
+        ..Default::default()
+    };
+    let data: AddReviewData = github
+        .graphql(ADD_REVIEW_GRAPHQL, Input { input })
+        .await
+        .context("Starting review")?;
+    Ok(data.add_pull_request_review.pull_request_review.id)
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct AddThreadInput<'a> {
+    pull_request_review_id: &'a str,
+    body: &'a str,
+    #[serde(flatten)]
+    thread: &'a NewThread,
+}
+
+/// Comment on lines of a pull request as part of the pending review,
+/// starting one if there isn't one yet.
+pub async fn add_thread(
+    github: &Github,
+    review: &Review,
+    thread: &NewThread,
+    body: &str,
+) -> Result<()> {
+    let review_id = match &review.pending {
+        Some(id) => id.clone(),
+        None => start_review(github, &review.pull_request_id).await?,
+    };
+    tracing::debug!("adding review thread on {}:{}", thread.path, thread.line);
+    let input = AddThreadInput {
+        pull_request_review_id: &review_id,
+        body,
+        thread,
+    };
+    mutate(github, ADD_REVIEW_THREAD_GRAPHQL, input)
+        .await
+        .context("Adding review comment")
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ReplyInput<'a> {
+    pull_request_review_thread_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pull_request_review_id: Option<&'a str>,
+    body: &'a str,
+}
+
+/// Reply to a review thread, as part of the pending review if there is one.
+pub async fn reply(github: &Github, review: &Review, thread_id: &str, body: &str) -> Result<()> {
+    tracing::debug!("replying to {thread_id}");
+    let input = ReplyInput {
+        pull_request_review_thread_id: thread_id,
+        pull_request_review_id: review.pending.as_deref(),
+        body,
+    };
+    mutate(github, ADD_REVIEW_THREAD_REPLY_GRAPHQL, input)
+        .await
+        .context("Replying")
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ThreadInput<'a> {
+    thread_id: &'a str,
+}
+
+/// Resolve (or unresolve) a review thread.
+pub async fn set_resolved(github: &Github, thread_id: &str, resolved: bool) -> Result<()> {
+    tracing::debug!("setting {thread_id} resolved: {resolved}");
+    let mutation = if resolved {
+        RESOLVE_REVIEW_THREAD_GRAPHQL
+    } else {
+        UNRESOLVE_REVIEW_THREAD_GRAPHQL
+    };
+    let context = if resolved { "Resolving" } else { "Unresolving" };
+    mutate(github, mutation, ThreadInput { thread_id })
+        .await
+        .context(context)
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SubmitInput<'a> {
+    pull_request_review_id: &'a str,
+    event: ReviewEvent,
+    body: &'a str,
+}
+
+/// Submit the pending review with its comments,
+/// or review without comments if there's no pending review.
+pub async fn submit(
+    github: &Github,
+    review: &Review,
+    event: ReviewEvent,
+    body: &str,
+) -> Result<()> {
+    tracing::debug!(
+        "submitting review of {} as {event:?}",
+        review.pull_request_id
+    );
+    match &review.pending {
+        Some(id) => {
+            let input = SubmitInput {
+                pull_request_review_id: id,
+                event,
+                body,
+            };
+            mutate(github, SUBMIT_REVIEW_GRAPHQL, input).await
+        }
+        None => {
+            let input = AddReviewInput {
+                pull_request_id: &review.pull_request_id,
+                event: Some(event),
+                body: Some(body),
+            };
+            let _: IgnoredAny = github.graphql(ADD_REVIEW_GRAPHQL, Input { input }).await?;
+            Ok(())
+        }
+    }
+    .context("Submitting review")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{Matcher, Server};
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_review() {
+        let mut server = Server::new_async().await;
+        let fetch = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"owner":"octocat","name":"Hello-World","number":2648}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/review_threads.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let review = fetch_review(&github, "octocat/Hello-World", 2648)
+            .await
+            .unwrap();
+        fetch.assert_async().await;
+        assert_eq!(review.pull_request_id, "PR_1");
+        assert_eq!(review.pending, None);
+        assert_eq!(review.threads.len(), 1);
+        assert_eq!(review.threads[0].path, "src/main.rs");
+        assert_eq!(review.threads[0].line, Some(2));
+        assert_eq!(review.threads[0].diff_side, Side::Left);
+        assert_eq!(review.threads[0].comments.nodes[0].body, "Why?");
+
+        // commenting without a pending review starts one first
+        let start = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestId":"PR_1"}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"data":{"addPullRequestReview":{"pullRequestReview":{"id":"PRR_1"}}}}"#)
+            .create_async()
+            .await;
+        let add = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestReviewId":"PRR_1","body":"nit","path":"src/main.rs","line":3,"side":"RIGHT","startLine":2,"startSide":"RIGHT"}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"data":{"addPullRequestReviewThread":{"thread":{"id":"PRRT_2"}}}}"#)
+            .create_async()
+            .await;
+        let thread = NewThread {
+            path: "src/main.rs".into(),
+            line: 3,
+            side: Side::Right,
+            start_line: Some(2),
+            start_side: Some(Side::Right),
+        };
+        add_thread(&github, &review, &thread, "nit").await.unwrap();
+        start.assert_async().await;
+        add.assert_async().await;
+
+        // a line outside the diff is refused, with null data alongside the error
+        let outside = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestReviewId":"PRR_1","body":"typo","path":"src/main.rs","line":90}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"data":{"addPullRequestReviewThread":null},"errors":[{"type":"UNPROCESSABLE","path":["addPullRequestReviewThread"],"message":"Line could not be resolved"}]}"#)
+            .create_async()
+            .await;
+        let thread = NewThread {
+            path: "src/main.rs".into(),
+            line: 90,
+            side: Side::Right,
+            start_line: None,
+            start_side: None,
+        };
+        let pending = Review {
+            pending: Some("PRR_1".into()),
+            ..review.clone()
+        };
+        let err = add_thread(&github, &pending, &thread, "typo")
+            .await
+            .unwrap_err();
+        outside.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Adding review comment: Line could not be resolved"
+        );
+
+        let submit_mock = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"input":{"pullRequestReviewId":"PRR_1","event":"REQUEST_CHANGES","body":"see comments"}}}"#.into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"errors":[{"message":"Review cannot be empty"}]}"#)
+            .create_async()
+            .await;
+        let pending = Review {
+            pending: Some("PRR_1".into()),
+            ..review
+        };
+        let err = submit(
+            &github,
+            &pending,
+            ReviewEvent::RequestChanges,
+            "see comments",
+        )
+        .await
+        .unwrap_err();
+        submit_mock.assert_async().await;
+        assert_eq!(
+            format!("{err:#}"),
+            "Submitting review: Review cannot be empty"
+        );
+    }
+}
@@ -0,0 +1,498 @@
+use super::{
+    code::{SearchItem, SearchRepository},
+    issues::User,
+    repo::Nodes,
+    Github,
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+const SEARCH_REPOS_GRAPHQL: &str = include_str!("search_repos.graphql");
+const SEARCH_DISCUSSIONS_GRAPHQL: &str = include_str!("search_discussions.graphql");
+
+// results requested per page
+const PAGE_SIZE: u32 = 30;
+const REST_JSON: &str = "application/vnd.github+json";
+
+/// A page of search results, and where the next one starts if there is one.
+/// GraphQL pages start at a cursor, REST ones at a page number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<String>,
+    // when to check whether the results changed, for results that change while they're shown
+    pub poll: Option<Poll>,
+}
+
+/// When GitHub wants the results checked for changes, and what they were last modified at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Poll {
+    pub last_modified: Option<String>,
+    pub interval: std::time::Duration,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+struct SearchVariables<'a> {
+    query: &'a str,
+    count: u32,
+    after: Option<&'a str>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    end_cursor: Option<String>,
+    has_next_page: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SearchBody<T> {
+    nodes: Vec<T>,
+    page_info: PageInfo,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct SearchData<T> {
+    search: SearchBody<T>,
+}
+
+// Run a GraphQL search whose results are all of one type.
+async fn graphql_search<T: serde::de::DeserializeOwned>(
+    github: &Github,
+    graphql: &str,
+    query: &str,
+    after: Option<&str>,
+) -> Result<Page<T>> {
+    let data: SearchData<T> = github
+        .graphql(
+            graphql,
+            SearchVariables {
+                query,
+                count: PAGE_SIZE,
+                after,
+            },
+        )
+        .await?;
+    let info = data.search.page_info;
+    Ok(Page {
+        items: data.search.nodes,
+        next: info.end_cursor.filter(|_| info.has_next_page),
+        poll: None,
+    })
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Language {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Topic {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct RepositoryTopic {
+    pub topic: Topic,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Repo {
+    pub name_with_owner: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub stargazer_count: u32,
+    pub fork_count: u32,
+    pub is_archived: bool,
+    pub is_fork: bool,
+    pub primary_language: Option<Language>,
+    pub repository_topics: Nodes<RepositoryTopic>,
+}
+
+/// Search repositories, starting after the `after` cursor.
+pub async fn search_repos(github: &Github, query: &str, after: Option<&str>) -> Result<Page<Repo>> {
+    tracing::debug!("searching repos for {query} after {after:?}");
+    graphql_search(github, SEARCH_REPOS_GRAPHQL, query, after)
+        .await
+        .context("Searching repositories")
+}
+
+/// The README of `repo`, or None if it has none.
+pub async fn fetch_readme(github: &Github, repo: &str) -> Result<Option<String>> {
+    tracing::debug!("fetching the README of {repo}");
+    let req = github.rest_as(
+        reqwest::Method::GET,
+        &format!("/repos/{repo}/readme"),
+        "application/vnd.github.raw+json",
+    );
+    match github.send_text(req).await {
+        Ok(readme) => Ok(Some(readme)),
+        Err(err) if err.to_string().contains("404 Not Found") => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Fetching the README of {repo}")),
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Category {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct DiscussionComment {
+    pub author: Option<User>,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscussionComments {
+    pub total_count: u32,
+    pub nodes: Vec<DiscussionComment>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Discussion {
+    pub number: u32,
+    pub title: String,
+    pub url: String,
+    pub body: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub upvote_count: u32,
+    pub author: Option<User>,
+    pub category: Category,
+    // the comment marked as answering the question, if any
+    pub answer: Option<DiscussionComment>,
+    // the first 20 comments
+    pub comments: DiscussionComments,
+}
+
+/// Search discussions, starting after the `after` cursor.
+/// The results include their answer and first comments.
+pub async fn search_discussions(
+    github: &Github,
+    query: &str,
+    after: Option<&str>,
+) -> Result<Page<Discussion>> {
+    tracing::debug!("searching discussions for {query} after {after:?}");
+    graphql_search(github, SEARCH_DISCUSSIONS_GRAPHQL, query, after)
+        .await
+        .context("Searching discussions")
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct RestSearch<T> {
+    total_count: u32,
+    items: Vec<T>,
+}
+
+// Get a page of a REST search, numbered from 1, as the media type `accept`.
+// The REST API only returns the first 1000 results.
+async fn rest_search<T: serde::de::DeserializeOwned>(
+    github: &Github,
+    kind: &str,
+    accept: &str,
+    query: &str,
+    page: Option<&str>,
+) -> Result<Page<T>> {
+    let page: u32 = match page {
+        Some(page) => page.parse().with_context(|| format!("Bad page {page}"))?,
+        None => 1,
+    };
+    if query.trim().is_empty() {
+        bail!("a query is required");
+    }
+    let req = github
+        .rest_as(reqwest::Method::GET, &format!("/search/{kind}"), accept)
+        .query(&[
+            ("q", query),
+            ("page", &page.to_string()),
+            ("per_page", &PAGE_SIZE.to_string()),
+        ]);
+    let results: RestSearch<T> = github.send(req).await?;
+    let seen = page * PAGE_SIZE;
+    let more = seen < results.total_count.min(1000) && results.items.len() == PAGE_SIZE as usize;
+    Ok(Page {
+        items: results.items,
+        next: more.then(|| (page + 1).to_string()),
+        poll: None,
+    })
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct GitActor {
+    pub name: String,
+    pub date: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CommitDetails {
+    pub message: String,
+    pub author: GitActor,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct CommitItem {
+    pub sha: String,
+    pub html_url: String,
+    pub commit: CommitDetails,
+    pub repository: SearchRepository,
+    // the GitHub user the commit's author email belongs to, if any
+    pub author: Option<User>,
+}
+
+impl CommitItem {
+    /// The first line of the commit message.
+    pub fn subject(&self) -> &str {
+        self.commit.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Search commits, starting at page `page` of the results.
+pub async fn search_commits(
+    github: &Github,
+    query: &str,
+    page: Option<&str>,
+) -> Result<Page<CommitItem>> {
+    tracing::debug!("searching commits for {query} at page {page:?}");
+    rest_search(github, "commits", REST_JSON, query, page)
+        .await
+        .context("Searching commits")
+}
+
+/// Search code, starting at page `page` of the results.
+/// Each result has the fragments of its file that matched.
+pub async fn search_code(
+    github: &Github,
+    query: &str,
+    page: Option<&str>,
+) -> Result<Page<SearchItem>> {
+    tracing::debug!("searching code for {query} at page {page:?}");
+    rest_search(
+        github,
+        "code",
+        "application/vnd.github.text-match+json",
+        query,
+        page,
+    )
+    .await
+    .context("Searching code")
+}
+
+/// The diff of commit `sha` of `repo`.
+pub async fn fetch_commit_diff(github: &Github, repo: &str, sha: &str) -> Result<String> {
+    tracing::debug!("fetching the diff of {repo}@{sha}");
+    let req = github.rest_as(
+        reqwest::Method::GET,
+        &format!("/repos/{repo}/commits/{sha}"),
+        "application/vnd.github.diff",
+    );
+    github
+        .send_text(req)
+        .await
+        .with_context(|| format!("Fetching the diff of {repo}@{sha}"))
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct UserItem {
+    pub login: String,
+    pub html_url: String,
+    // "User" or "Organization"
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Search users and organizations, starting at page `page` of the results.
+pub async fn search_users(
+    github: &Github,
+    query: &str,
+    page: Option<&str>,
+) -> Result<Page<UserItem>> {
+    tracing::debug!("searching users for {query} at page {page:?}");
+    rest_search(github, "users", REST_JSON, query, page)
+        .await
+        .context("Searching users")
+}
+
+/// The public profile of a user or organization.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Profile {
+    pub login: String,
+    pub name: Option<String>,
+    pub bio: Option<String>,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    pub blog: Option<String>,
+    pub public_repos: u32,
+    #[serde(default)]
+    pub followers: u32,
+    #[serde(default)]
+    pub following: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub async fn fetch_profile(github: &Github, login: &str) -> Result<Profile> {
+    tracing::debug!("fetching the profile of {login}");
+    github
+        .get(&format!("/users/{login}"), &[])
+        .await
+        .with_context(|| format!("Fetching the profile of {login}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::{Matcher, Server};
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_graphql_search() {
+        let mut server = Server::new_async().await;
+        let repos = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"query":"hello","count":30,"after":null}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/search_repos.json").unwrap())
+            .create_async()
+            .await;
+        let discussions = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"variables":{"query":"repo:octocat/Hello-World","after":"Y3Vyc29yOjMw"}}"#
+                    .into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/search_discussions.json").unwrap())
+            .create_async()
+            .await;
+        let readme = server
+            .mock("GET", "/repos/octocat/Hello-World/readme")
+            .match_header("accept", "application/vnd.github.raw+json")
+            .with_status(200)
+            .with_body("# Hello World\n")
+            .create_async()
+            .await;
+        let no_readme = server
+            .mock("GET", "/repos/octocat/Spoon-Knife/readme")
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+
+        let page = search_repos(&github, "hello", None).await.unwrap();
+        repos.assert_async().await;
+        assert_eq!(page.next.as_deref(), Some("Y3Vyc29yOjMw"));
+        assert_eq!(page.items.len(), 2);
+        let repo = &page.items[0];
+        assert_eq!(repo.name_with_owner, "octocat/Hello-World");
+        assert_eq!(repo.stargazer_count, 2600);
+        assert_eq!(repo.primary_language.as_ref().unwrap().name, "Ruby");
+        assert_eq!(repo.repository_topics.nodes[0].topic.name, "example");
+
+        assert_eq!(
+            fetch_readme(&github, "octocat/Hello-World").await.unwrap(),
+            Some("# Hello World\n".into())
+        );
+        readme.assert_async().await;
+        assert_eq!(
+            fetch_readme(&github, "octocat/Spoon-Knife").await.unwrap(),
+            None
+        );
+        no_readme.assert_async().await;
+
+        let page = search_discussions(&github, "repo:octocat/Hello-World", Some("Y3Vyc29yOjMw"))
+            .await
+            .unwrap();
+        discussions.assert_async().await;
+        assert_eq!(page.next, None);
+        let discussion = &page.items[0];
+        assert_eq!(discussion.number, 42);
+        assert_eq!(discussion.category.name, "Q&A");
+        assert_eq!(discussion.answer.as_ref().unwrap().body, "Use a token.");
+        assert_eq!(discussion.comments.total_count, 2);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_rest_search() {
+        let mut server = Server::new_async().await;
+        let commits = server
+            .mock("GET", "/search/commits")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "fix typo".into()),
+                Matcher::UrlEncoded("page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/search_commits.json").unwrap())
+            .create_async()
+            .await;
+        let diff = server
+            .mock(
+                "GET",
+                "/repos/octocat/Hello-World/commits/7fd1a60b01f91b314f59955a4e4d4e80d8edf11d",
+            )
+            .match_header("accept", "application/vnd.github.diff")
+            .with_status(200)
+            .with_body("diff --git a/README b/README\n")
+            .create_async()
+            .await;
+        let users = server
+            .mock("GET", "/search/users")
+            .match_query(Matcher::UrlEncoded("q".into(), "octo".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/search_users.json").unwrap())
+            .create_async()
+            .await;
+        let profile = server
+            .mock("GET", "/users/octocat")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/user.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+
+        let page = search_commits(&github, "fix typo", Some("2"))
+            .await
+            .unwrap();
+        commits.assert_async().await;
+        // 1 of 31 results is left for the third page, but only a full page suggests more
+        assert_eq!(page.next, None);
+        let commit = &page.items[0];
+        assert_eq!(commit.subject(), "Fix typo in README");
+        assert_eq!(commit.repository.full_name, "octocat/Hello-World");
+        assert_eq!(commit.author.as_ref().unwrap().login, "octocat");
+        let text = fetch_commit_diff(&github, "octocat/Hello-World", &commit.sha)
+            .await
+            .unwrap();
+        diff.assert_async().await;
+        assert_eq!(text, "diff --git a/README b/README\n");
+
+        let page = search_users(&github, "octo", None).await.unwrap();
+        users.assert_async().await;
+        assert_eq!(page.items[1].kind, "Organization");
+        let user = fetch_profile(&github, "octocat").await.unwrap();
+        profile.assert_async().await;
+        assert_eq!(user.name.as_deref(), Some("The Octocat"));
+        assert_eq!(user.followers, 3938);
+
+        let err = search_users(&github, " ", None).await.unwrap_err();
+        assert_eq!(format!("{err:#}"), "Searching users: a query is required");
+    }
+}
@@ -0,0 +1,599 @@
+use super::{repo::split_repo, Github};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const TEMPLATES_GRAPHQL: &str = include_str!("templates.graphql");
+
+// Shown for fields left empty, as GitHub does.
+const NO_RESPONSE: &str = "_No response_";
+
+/// A field of an issue form, holding the value entered so far.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    // text shown in the form, but not submitted
+    Markdown(String),
+    Input {
+        label: String,
+        description: String,
+        placeholder: String,
+        value: String,
+        required: bool,
+    },
+    Textarea {
+        label: String,
+        description: String,
+        placeholder: String,
+        value: String,
+        // the language the value is rendered as code in, if any
+        render: Option<String>,
+        required: bool,
+    },
+    Dropdown {
+        label: String,
+        description: String,
+        options: Vec<String>,
+        multiple: bool,
+        selected: Vec<usize>,
+        required: bool,
+    },
+    Checkboxes {
+        label: String,
+        description: String,
+        options: Vec<Checkbox>,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Checkbox {
+    pub label: String,
+    pub required: bool,
+    pub checked: bool,
+}
+
+impl Field {
+    pub fn label(&self) -> &str {
+        match self {
+            Field::Markdown(_) => "",
+            Field::Input { label, .. }
+            | Field::Textarea { label, .. }
+            | Field::Dropdown { label, .. }
+            | Field::Checkboxes { label, .. } => label,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            Field::Markdown(_) => "",
+            Field::Input { description, .. }
+            | Field::Textarea { description, .. }
+            | Field::Dropdown { description, .. }
+            | Field::Checkboxes { description, .. } => description,
+        }
+    }
+
+    // Whether a required field, or a required checkbox, is missing.
+    fn is_missing(&self) -> bool {
+        match self {
+            Field::Markdown(_) => false,
+            Field::Input {
+                value, required, ..
+            }
+            | Field::Textarea {
+                value, required, ..
+            } => *required && value.trim().is_empty(),
+            Field::Dropdown {
+                selected, required, ..
+            } => *required && selected.is_empty(),
+            Field::Checkboxes { options, .. } => options.iter().any(|o| o.required && !o.checked),
+        }
+    }
+
+    // The field's section of the issue body, as GitHub renders submitted forms.
+    fn render(&self) -> Option<String> {
+        let value = match self {
+            Field::Markdown(_) => return None,
+            Field::Input { value, .. } => value.trim().to_string(),
+            Field::Textarea { value, render, .. } => match render {
+                Some(lang) if !value.trim().is_empty() => {
+                    format!("```{lang}\n{}\n```", value.trim_end())
+                }
+                _ => value.trim().to_string(),
+            },
+            Field::Dropdown {
+                options, selected, ..
+            } => selected
+                .iter()
+                .filter_map(|&i| options.get(i))
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+            Field::Checkboxes { options, .. } => options
+                .iter()
+                .map(|o| format!("- [{}] {}", if o.checked { "X" } else { " " }, o.label))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        let value = if value.is_empty() {
+            NO_RESPONSE.to_string()
+        } else {
+            value
+        };
+        Some(format!("### {}\n\n{value}", self.label()))
+    }
+}
+
+/// An issue template, either markdown or an issue form.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub about: String,
+    pub title: String,
+    pub labels: Vec<String>,
+    pub assignees: Vec<String>,
+    pub fields: Vec<Field>,
+    // markdown templates have a single textarea holding the whole body
+    pub form: bool,
+}
+
+impl Template {
+    /// A template for an issue with a free-form body.
+    pub fn blank() -> Self {
+        Self {
+            name: "Blank issue".to_string(),
+            fields: vec![Field::Textarea {
+                label: "Body".to_string(),
+                description: String::new(),
+                placeholder: String::new(),
+                value: String::new(),
+                render: None,
+                required: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Parse a template file from `.github/ISSUE_TEMPLATE`.
+    /// Returns None for files that aren't templates, like `config.yml`.
+    pub fn parse(file_name: &str, text: &str) -> Result<Option<Self>> {
+        let (stem, extension) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+        let template = match extension {
+            "md" => Self::parse_markdown(stem, text),
+            "yml" | "yaml" if stem != "config" => Self::parse_form(text),
+            _ => return Ok(None),
+        };
+        template
+            .map(Some)
+            .with_context(|| format!("Parsing issue template {file_name}"))
+    }
+
+    fn parse_markdown(stem: &str, text: &str) -> Result<Self> {
+        let (front, body) = match text
+            .strip_prefix("---")
+            .and_then(|rest| rest.split_once("\n---"))
+        {
+            Some((front, body)) => (front, body.strip_prefix('\n').unwrap_or(body)),
+            None => ("", text),
+        };
+        let front: FrontMatter = if front.trim().is_empty() {
+            FrontMatter::default()
+        } else {
+            serde_norway::from_str(front)?
+        };
+        let mut template = Self::blank();
+        template.name = front.name.unwrap_or_else(|| stem.to_string());
+        template.about = front.about.unwrap_or_default();
+        template.title = front.title.unwrap_or_default();
+        template.labels = front.labels.into_vec();
+        template.assignees = front.assignees.into_vec();
+        if let Some(Field::Textarea { value, .. }) = template.fields.first_mut() {
+            *value = body.to_string();
+        }
+        Ok(template)
+    }
+
+    fn parse_form(text: &str) -> Result<Self> {
+        let form: FormDef = serde_norway::from_str(text)?;
+        let fields = form
+            .body
+            .into_iter()
+            .map(|field| match field {
+                FieldDef::Markdown { attributes } => Field::Markdown(attributes.value),
+                FieldDef::Input {
+                    attributes,
+                    validations,
+                } => Field::Input {
+                    label: attributes.label,
+                    description: attributes.description,
+                    placeholder: attributes.placeholder,
+                    value: attributes.value,
+                    required: validations.required,
+                },
+                FieldDef::Textarea {
+                    attributes,
+                    validations,
+                } => Field::Textarea {
+                    label: attributes.label,
+                    description: attributes.description,
+                    placeholder: attributes.placeholder,
+                    value: attributes.value,
+                    render: attributes.render,
+                    required: validations.required,
+                },
+                FieldDef::Dropdown {
+                    attributes,
+                    validations,
+                } => Field::Dropdown {
+                    label: attributes.label,
+                    description: attributes.description,
+                    selected: attributes
+                        .default
+                        .filter(|&i| i < attributes.options.len())
+                        .into_iter()
+                        .collect(),
+                    options: attributes.options,
+                    multiple: attributes.multiple,
+                    required: validations.required,
+                },
+                FieldDef::Checkboxes { attributes } => Field::Checkboxes {
+                    label: attributes.label,
+                    description: attributes.description,
+                    options: attributes
+                        .options
+                        .into_iter()
+                        .map(|o| Checkbox {
+                            label: o.label,
+                            required: o.required,
+                            checked: false,
+                        })
+                        .collect(),
+                },
+            })
+            .collect();
+        Ok(Self {
+            name: form.name,
+            about: form.description,
+            title: form.title,
+            labels: form.labels.into_vec(),
+            assignees: form.assignees.into_vec(),
+            fields,
+            form: true,
+        })
+    }
+
+    /// Labels of required fields that haven't been filled in.
+    pub fn missing(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|f| f.is_missing())
+            .map(|f| f.label().to_string())
+            .collect()
+    }
+
+    /// The body of the issue to create.
+    pub fn body(&self) -> String {
+        if !self.form {
+            return match self.fields.first() {
+                Some(Field::Textarea { value, .. }) => value.clone(),
+                _ => String::new(),
+            };
+        }
+        self.fields
+            .iter()
+            .filter_map(Field::render)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// The templates of a repository.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Templates {
+    pub templates: Vec<Template>,
+    // whether issues may also be opened without a template
+    pub blank_issues_enabled: bool,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            templates: Vec::new(),
+            blank_issues_enabled: true,
+        }
+    }
+}
+
+impl Templates {
+    /// The templates to choose from, with the blank issue last if it's allowed.
+    pub fn choices(&self) -> Vec<Template> {
+        let blank = (self.blank_issues_enabled || self.templates.is_empty()).then(Template::blank);
+        self.templates.iter().cloned().chain(blank).collect()
+    }
+
+    /// Find a template by its name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&Template> {
+        self.templates
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// Labels and assignees are lists, or a comma separated string.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum StringList {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl Default for StringList {
+    fn default() -> Self {
+        StringList::List(Vec::new())
+    }
+}
+
+impl StringList {
+    fn into_vec(self) -> Vec<String> {
+        let list = match self {
+            StringList::List(list) => list,
+            StringList::Joined(joined) => joined.split(',').map(str::to_string).collect(),
+        };
+        list.into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct FrontMatter {
+    name: Option<String>,
+    about: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    labels: StringList,
+    #[serde(default)]
+    assignees: StringList,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct FormDef {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    labels: StringList,
+    #[serde(default)]
+    assignees: StringList,
+    body: Vec<FieldDef>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum FieldDef {
+    Markdown {
+        attributes: MarkdownAttributes,
+    },
+    Input {
+        attributes: TextAttributes,
+        #[serde(default)]
+        validations: Validations,
+    },
+    Textarea {
+        attributes: TextAttributes,
+        #[serde(default)]
+        validations: Validations,
+    },
+    Dropdown {
+        attributes: DropdownAttributes,
+        #[serde(default)]
+        validations: Validations,
+    },
+    Checkboxes {
+        attributes: CheckboxesAttributes,
+    },
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct MarkdownAttributes {
+    value: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+struct TextAttributes {
+    label: String,
+    description: String,
+    placeholder: String,
+    value: String,
+    render: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+struct DropdownAttributes {
+    label: String,
+    description: String,
+    options: Vec<String>,
+    multiple: bool,
+    default: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+struct CheckboxesAttributes {
+    label: String,
+    description: String,
+    options: Vec<CheckboxDef>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct CheckboxDef {
+    label: String,
+    #[serde(default)]
+    required: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Validations {
+    #[serde(default)]
+    required: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct ChooserConfig {
+    #[serde(default = "enabled")]
+    blank_issues_enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+struct RepoVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Blob {
+    text: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Entry {
+    name: String,
+    object: Option<Blob>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct Tree {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct TemplatesRepository {
+    object: Option<Tree>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+struct TemplatesData {
+    repository: TemplatesRepository,
+}
+
+/// Fetch the issue templates in `.github/ISSUE_TEMPLATE` on the default branch of `repo`.
+/// Templates that fail to parse are skipped, as GitHub does.
+pub async fn fetch_templates(github: &Github, repo: &str) -> Result<Templates> {
+    tracing::debug!("fetching issue templates for {repo}");
+    let (owner, name) = split_repo(repo)?;
+    let data: TemplatesData = github
+        .graphql(TEMPLATES_GRAPHQL, RepoVariables { owner, name })
+        .await
+        .with_context(|| format!("Fetching issue templates for {repo}"))?;
+    let mut templates = Templates::default();
+    let mut entries = data.repository.object.unwrap_or_default().entries;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    for entry in entries {
+        let Some(text) = entry.object.and_then(|o| o.text) else {
+            continue;
+        };
+        if matches!(entry.name.as_str(), "config.yml" | "config.yaml") {
+            match serde_norway::from_str::<ChooserConfig>(&text) {
+                Ok(config) => templates.blank_issues_enabled = config.blank_issues_enabled,
+                Err(err) => tracing::warn!("Failed to parse {}: {err}", entry.name),
+            }
+            continue;
+        }
+        match Template::parse(&entry.name, &text) {
+            Ok(Some(template)) => templates.templates.push(template),
+            Ok(None) => {}
+            Err(err) => tracing::warn!("{err:#}"),
+        }
+    }
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mockito::Server;
+
+    #[test]
+    fn test_markdown_template() {
+        let text = "---\nname: Bug report\nabout: Something broke\ntitle: '[BUG] '\nlabels: bug, triage\nassignees: ''\n---\n\n**Describe the bug**\n";
+        let template = Template::parse("bug_report.md", text).unwrap().unwrap();
+        assert_eq!(template.name, "Bug report");
+        assert_eq!(template.title, "[BUG] ");
+        assert_eq!(template.labels, vec!["bug", "triage"]);
+        assert!(template.assignees.is_empty());
+        assert!(!template.form);
+        assert_eq!(template.body(), "\n**Describe the bug**\n");
+
+        let template = Template::parse("plain.md", "Hello").unwrap().unwrap();
+        assert_eq!(template.name, "plain");
+        assert_eq!(template.body(), "Hello");
+
+        assert_eq!(Template::parse("config.yml", "").unwrap(), None);
+        assert!(Template::parse("broken.yml", "name: [").is_err());
+    }
+
+    #[test]
+    fn test_form_template() {
+        let text = std::fs::read_to_string("testdata/bug_form.yml").unwrap();
+        let mut template = Template::parse("bug_form.yml", &text).unwrap().unwrap();
+        assert!(template.form);
+        assert_eq!(template.name, "Bug Report");
+        assert_eq!(template.labels, vec!["bug", "triage"]);
+        assert_eq!(template.fields.len(), 6);
+        assert_eq!(
+            template.missing(),
+            vec!["What happened?", "Code of Conduct"]
+        );
+
+        for field in &mut template.fields {
+            match field {
+                Field::Textarea { value, .. } => *value = "It crashed".into(),
+                Field::Checkboxes { options, .. } => options[0].checked = true,
+                _ => {}
+            }
+        }
+        assert!(template.missing().is_empty());
+        assert_eq!(
+            template.body(),
+            "### Version\n\n1.0.0\n\n\
+             ### What happened?\n\nIt crashed\n\n\
+             ### Browsers\n\nFirefox\n\n\
+             ### Logs\n\n```shell\nIt crashed\n```\n\n\
+             ### Code of Conduct\n\n- [X] I agree to follow this project's Code of Conduct"
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_fetch_templates() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/templates.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let templates = fetch_templates(&github, "octocat/Hello-World")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert!(!templates.blank_issues_enabled);
+        let names: Vec<_> = templates.choices().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Bug Report", "Feature request"]);
+        assert!(templates.find("feature REQUEST").is_some());
+    }
+}
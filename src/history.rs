@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+// Maximum number of queries kept for each subcommand and repo.
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+struct Entry {
+    command: String,
+    repo: Option<String>,
+    query: String,
+}
+
+/// Submitted queries, saved one JSON object per line.
+/// Entries are scoped by subcommand and repo, oldest first.
+pub struct History {
+    // None when the history couldn't be opened, so it's only kept in memory
+    path: Option<PathBuf>,
+    command: String,
+    repo: Option<String>,
+    entries: Vec<Entry>,
+}
+
+impl History {
+    /// Open the history in the XDG data directory.
+    /// If it can't be read, the error is logged and queries are only kept in memory.
+    pub fn open(command: &str, repo: Option<&str>) -> Self {
+        let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+        let history = xdg_dirs
+            .place_data_file("history")
+            .map_err(anyhow::Error::from)
+            .and_then(|path| Self::load(path, command, repo));
+        history.unwrap_or_else(|err| {
+            tracing::error!("Keeping the history in memory: {err:#}");
+            Self::in_memory(command, repo)
+        })
+    }
+
+    /// A history that starts empty and is never saved, for queries that aren't worth recalling.
+    pub fn in_memory(command: &str, repo: Option<&str>) -> Self {
+        Self {
+            path: None,
+            command: command.to_string(),
+            repo: repo.map(str::to_string),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl Into<PathBuf>, command: &str, repo: Option<&str>) -> Result<Self> {
+        let path = path.into();
+        let entries = read(&path)?;
+        tracing::debug!("Loaded {} history entries from {path:?}", entries.len());
+        Ok(Self {
+            path: Some(path),
+            command: command.to_string(),
+            repo: repo.map(str::to_string),
+            entries,
+        })
+    }
+
+    fn in_scope(&self, entry: &Entry) -> bool {
+        entry.command == self.command && entry.repo == self.repo
+    }
+
+    /// Queries for the current subcommand and repo, oldest first.
+    pub fn queries(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|e| self.in_scope(e))
+            .map(|e| e.query.clone())
+            .collect()
+    }
+
+    /// Add a query, moving it to the end if it was already present, and save the history.
+    /// Entries saved by other sessions since the history was loaded are kept.
+    pub fn add(&mut self, query: &str) -> Result<()> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(());
+        }
+        if let Some(path) = &self.path {
+            self.entries = read(path)?;
+        }
+        let entry = Entry {
+            command: self.command.clone(),
+            repo: self.repo.clone(),
+            query: query.to_string(),
+        };
+        self.entries.retain(|e| *e != entry);
+        self.entries.push(entry);
+
+        let count = self.entries.iter().filter(|e| self.in_scope(e)).count();
+        let mut excess = count.saturating_sub(MAX_ENTRIES);
+        self.entries.retain(|e| {
+            if excess > 0 && e.command == self.command && e.repo == self.repo {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+
+        self.save()
+    }
+
+    // Write to a temporary file first so a crash can't truncate the history.
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut content = Vec::new();
+        for entry in &self.entries {
+            serde_json::to_writer(&mut content, entry)?;
+            content.push(b'\n');
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .with_context(|| format!("Saving history {}", path.display()))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<Entry>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    tracing::warn!("Skipping malformed history entry '{line}': {err}");
+                    None
+                }
+            })
+            .collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err).with_context(|| format!("Reading history {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = History::load(&path, "issues", Some("foo/bar")).unwrap();
+        assert!(history.queries().is_empty());
+
+        history.add("is:open").unwrap();
+        history.add("label:bug").unwrap();
+        history.add("  ").unwrap();
+        history.add("is:open ").unwrap();
+        assert_eq!(history.queries(), vec!["label:bug", "is:open"]);
+
+        let mut other = History::load(&path, "issues", None).unwrap();
+        assert!(other.queries().is_empty());
+        other.add("author:me").unwrap();
+
+        let history = History::load(&path, "issues", Some("foo/bar")).unwrap();
+        assert_eq!(history.queries(), vec!["label:bug", "is:open"]);
+        let other = History::load(&path, "issues", None).unwrap();
+        assert_eq!(other.queries(), vec!["author:me"]);
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_history_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut other = History::load(&path, "code", None).unwrap();
+        other.add("foo").unwrap();
+
+        let mut history = History::load(&path, "issues", None).unwrap();
+        for i in 0..MAX_ENTRIES + 2 {
+            history.add(&i.to_string()).unwrap();
+        }
+
+        let queries = History::load(&path, "issues", None).unwrap().queries();
+        assert_eq!(queries.len(), MAX_ENTRIES);
+        assert_eq!(queries[0], "2");
+        assert_eq!(queries[MAX_ENTRIES - 1], (MAX_ENTRIES + 1).to_string());
+
+        let other = History::load(&path, "code", None).unwrap();
+        assert_eq!(other.queries(), vec!["foo"]);
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_history_concurrent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        // two sessions open the history before either adds to it
+        let mut first = History::load(&path, "issues", None).unwrap();
+        let mut second = History::load(&path, "issues", None).unwrap();
+        first.add("is:open").unwrap();
+        second.add("label:bug").unwrap();
+        first.add("author:me").unwrap();
+
+        let history = History::load(&path, "issues", None).unwrap();
+        assert_eq!(history.queries(), vec!["is:open", "label:bug", "author:me"]);
+    }
+}
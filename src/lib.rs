@@ -1,6 +1,18 @@
-use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use query::{Expansion, Query, MAX_OPERATORS};
+use serde::Deserialize;
+
+pub mod clipboard;
+pub mod config;
+pub mod git;
 pub mod github;
+pub mod history;
+pub mod open;
+pub mod query;
 pub mod tui;
 
 #[derive(Parser)]
@@ -15,46 +27,492 @@ pub struct Cli {
 pub enum Command {
     /// Search issues.
     Issues(QueryArgs),
+    /// Work with a single issue.
+    Issue {
+        #[command(subcommand)]
+        command: IssueCommand,
+    },
+    /// Search code, opening matches at their line in the browser or in `$EDITOR`.
+    Code(SearchArgs),
+    /// Search repositories.
+    Repos(SearchArgs),
+    /// Search discussions.
+    Discussions(SearchArgs),
+    /// Search commits.
+    Commits(SearchArgs),
+    /// Search users and organizations.
+    Users(SearchArgs),
+    /// Triage notifications, updating as new ones arrive.
+    Notifications(NotificationsArgs),
+    /// Show the issues and pull requests that need you, in sections set in the config file.
+    Dashboard(DashboardArgs),
+}
+
+#[derive(Subcommand)]
+pub enum IssueCommand {
+    /// Create an issue, filling in one of the repository's templates.
+    New(NewIssueArgs),
+    /// Print the url of the issue the current branch was created for.
+    Current,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct NewIssueArgs {
+    /// Repository to create the issue in.
+    /// Defaults to the current repository.
+    #[arg(short, long)]
+    pub repo: Option<String>,
+
+    /// Name of the template to use, instead of choosing one.
+    #[arg(short, long)]
+    pub template: Option<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct SearchArgs {
+    /// Query to search, in GitHub's search syntax.
+    pub query: String,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct NotificationsArgs {
+    /// Filters for the inbox: is:unread, is:participating and repo:OWNER/NAME.
+    /// Lists read notifications too without is:unread.
+    #[arg(default_value = "")]
+    pub query: String,
 }
 
-#[derive(Args, Default)]
+#[derive(Args, Clone, Debug, Default)]
+pub struct DashboardArgs {
+    /// Repository to limit the dashboard to, may be repeated.
+    /// Defaults to all repositories.
+    #[arg(short, long)]
+    pub repo: Vec<String>,
+
+    /// Organization to limit the dashboard to, may be repeated.
+    #[arg(long)]
+    pub org: Vec<String>,
+}
+
+/// A column of the results table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Number,
+    Title,
+    Author,
+    Kind,
+    // whether the checks on a pull request passed
+    Checks,
+}
+
+/// An order for the results, added to the query as a `sort:` qualifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Sort {
+    Created,
+    Updated,
+    Comments,
+    Reactions,
+    /// Most 👍 reactions first.
+    #[value(name = "reactions-+1")]
+    ThumbsUp,
+    Interactions,
+}
+
+impl Sort {
+    /// The value of the `sort:` qualifier, which sorts in descending order.
+    pub fn qualifier(self) -> &'static str {
+        match self {
+            Sort::Created => "created",
+            Sort::Updated => "updated",
+            Sort::Comments => "comments",
+            Sort::Reactions => "reactions",
+            Sort::ThumbsUp => "reactions-+1",
+            Sort::Interactions => "interactions",
+        }
+    }
+}
+
+const DEFAULT_COLUMNS: &[Column] = &[Column::Number, Column::Title];
+
+// GitHub rejects search queries longer than this.
+const MAX_QUERY_LENGTH: usize = 256;
+
+#[derive(Args, Clone, Debug, Default)]
 pub struct QueryArgs {
     /// Query to search.
+    /// Use @name to include a saved search from the config file.
     pub query: String,
 
     /// Maximum number of result pages.
     #[arg(short, long, default_value_t = 5)]
     pub pages: usize,
 
-    /// Repository to search.
+    /// Repository to search, may be repeated.
     /// Defaults to the current repository, or all repos if the current directory is not a git repository.
     /// Pass an empty string to search all repositories.
     #[arg(short, long)]
-    pub repo: Option<String>,
+    pub repo: Vec<String>,
+
+    /// Organization to search, may be repeated.
+    #[arg(long)]
+    pub org: Vec<String>,
+
+    /// User whose repositories to search, may be repeated.
+    #[arg(long)]
+    pub user: Vec<String>,
+
+    /// File listing repositories to search, one per line.
+    /// Blank lines and lines starting with '#' are ignored.
+    #[arg(long)]
+    pub repos_file: Option<PathBuf>,
+
+    /// Columns to show in the results table.
+    #[arg(short, long, value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Order of the results, unless the query has a sort: qualifier.
+    #[arg(short, long)]
+    pub sort: Option<Sort>,
 }
 
 impl QueryArgs {
-    pub fn to_query(&self) -> String {
-        match &self.repo {
-            Some(repo) => format!("repo:{repo} {}", self.query),
-            None => self.query.clone(),
+    pub fn columns(&self) -> &[Column] {
+        if self.columns.is_empty() {
+            DEFAULT_COLUMNS
+        } else {
+            &self.columns
+        }
+    }
+
+    /// Add the repositories listed in `repos_file` to `repo`.
+    pub fn read_repos_file(&mut self) -> Result<()> {
+        let Some(path) = &self.repos_file else {
+            return Ok(());
+        };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading repos file {}", path.display()))?;
+        self.repo.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string),
+        );
+        Ok(())
+    }
+
+    /// Returns true if any repo, org or user was given.
+    pub fn has_scope(&self) -> bool {
+        !(self.repo.is_empty() && self.org.is_empty() && self.user.is_empty())
+    }
+
+    /// The single repository searched, if there is exactly one.
+    pub fn single_repo(&self) -> Option<&str> {
+        match (self.scopes().as_slice(), self.repo.as_slice()) {
+            ([_], [repo]) => Some(repo),
+            _ => None,
+        }
+    }
+
+    /// Identifies the scope in the search history.
+    /// A single repo is identified by its name, anything else by its qualifiers.
+    pub fn history_scope(&self) -> Option<String> {
+        if let Some(repo) = self.single_repo() {
+            return Some(repo.to_string());
         }
+        let scopes = self.scopes();
+        (!scopes.is_empty()).then(|| scopes.join(" "))
+    }
+
+    // Qualifiers for the repos, orgs and users to search.
+    fn scopes(&self) -> Vec<String> {
+        let named = |name: &str, values: &[String]| {
+            values
+                .iter()
+                .filter(|v| !v.is_empty())
+                .map(|v| format!("{name}:{v}"))
+                .collect::<Vec<_>>()
+        };
+        [
+            named("repo", &self.repo),
+            named("org", &self.org),
+            named("user", &self.user),
+        ]
+        .concat()
+    }
+
+    // Scope the query to the repos, orgs and users, unless the query has its own scope,
+    // and expand relative dates.
+    pub fn to_queries(&self) -> Vec<String> {
+        self.expand(chrono::Local::now().date_naive()).queries
+    }
+
+    /// Build the queries to send, with relative dates expanded relative to `today`.
+    /// Alternatives are split across queries to keep each under the limit on operators.
+    /// GitHub ORs repeated scope qualifiers without needing OR operators,
+    /// so the scopes are only split across queries to keep each under the length limit.
+    /// Sorted queries aren't split, since the results of separate queries would each be in order
+    /// but not in order together, so what's over the limits is reported instead.
+    pub fn expand(&self, today: NaiveDate) -> Expanded {
+        let (mut query, errors) = Query::parse(&self.query);
+        if !errors.is_empty() {
+            tracing::warn!("Query has errors: {errors:?}");
+        }
+        let expansions = query.expand_dates(today);
+        if let Some(sort) = self.sort.filter(|_| !query.has_qualifier("sort")) {
+            query.push_qualifier("sort", sort.qualifier());
+        }
+        let sorted = query.has_qualifier("sort");
+        let scopes = self.scopes();
+        let scoped = scopes.is_empty() || query.is_scoped();
+        let alternatives = if sorted {
+            vec![query]
+        } else {
+            query.split_alternatives(MAX_OPERATORS)
+        };
+
+        let mut queries = Vec::new();
+        let mut problems = Vec::new();
+        for mut query in alternatives {
+            if query.operators() > MAX_OPERATORS {
+                tracing::warn!("Query has more than {MAX_OPERATORS} operators: {query}");
+                problems.push(limit_problem(
+                    sorted,
+                    format!("have at most {MAX_OPERATORS} AND, OR and NOT operators"),
+                ));
+            }
+            if scoped {
+                queries.push(query.to_string());
+                continue;
+            }
+            query.group_alternatives();
+            let query = query.to_string();
+            let join = |scopes: &[String]| {
+                let scopes = scopes.join(" ");
+                if query.is_empty() {
+                    scopes
+                } else {
+                    format!("{scopes} {query}")
+                }
+            };
+            let mut chunk: Vec<String> = Vec::new();
+            for scope in &scopes {
+                chunk.push(scope.clone());
+                if !sorted && chunk.len() > 1 && join(&chunk).len() > MAX_QUERY_LENGTH {
+                    let scope = chunk.pop().unwrap_or_default();
+                    queries.push(join(&chunk));
+                    chunk = vec![scope];
+                }
+            }
+            queries.push(join(&chunk));
+        }
+        if let Some(long) = queries.iter().find(|q| q.len() > MAX_QUERY_LENGTH) {
+            tracing::warn!("Query is longer than {MAX_QUERY_LENGTH} characters: {long}");
+            problems.push(limit_problem(
+                sorted,
+                format!("be at most {MAX_QUERY_LENGTH} characters long"),
+            ));
+        }
+        problems.dedup();
+        Expanded {
+            queries,
+            expansions,
+            problems,
+        }
+    }
+}
+
+// Why GitHub may reject a query over one of its limits.
+fn limit_problem(sorted: bool, limit: String) -> String {
+    if sorted {
+        format!("sorted queries aren't split, and can {limit}")
+    } else {
+        format!("queries can {limit}")
     }
 }
 
+/// The queries to send for some arguments.
+#[derive(Debug, Default, PartialEq)]
+pub struct Expanded {
+    pub queries: Vec<String>,
+    /// Each expanded term before and after expansion.
+    pub expansions: Vec<Expansion>,
+    /// Why GitHub may reject the queries, if they couldn't be split to fit its limits.
+    pub problems: Vec<String>,
+}
+
 #[test]
 fn test_to_query() {
     let qa = QueryArgs {
         query: "foo".into(),
-        repo: None,
+        repo: vec![],
         ..Default::default()
     };
-    assert_eq!(qa.to_query(), "foo");
+    assert_eq!(qa.to_queries(), vec!["foo"]);
 
     let qa = QueryArgs {
         query: "foo".into(),
-        repo: Some("bar/baz".into()),
+        repo: vec!["bar/baz".into()],
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["repo:bar/baz foo"]);
+
+    let qa = QueryArgs {
+        query: "foo repo:biz/buz".into(),
+        repo: vec!["bar/baz".into()],
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["foo repo:biz/buz"]);
+
+    let qa = QueryArgs {
+        query: "foo (org:biz OR user:buz)".into(),
+        repo: vec!["bar/baz".into()],
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["foo (org:biz OR user:buz)"]);
+
+    let qa = QueryArgs {
+        query: "foo -repo:biz/buz".into(),
+        repo: vec!["bar/baz".into()],
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["repo:bar/baz foo -repo:biz/buz"]);
+
+    let qa = QueryArgs {
+        query: "foo".into(),
+        repo: vec!["".into()],
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["foo"]);
+}
+
+#[test]
+fn test_expand() {
+    let today = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+    let qa = QueryArgs {
+        query: "foo updated:<2w".into(),
+        repo: vec!["bar/baz".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        qa.expand(today),
+        Expanded {
+            queries: vec!["repo:bar/baz foo updated:<2024-05-17".into()],
+            expansions: vec![("updated:<2w".into(), "updated:<2024-05-17".into())],
+            problems: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_sort() {
+    let qa = QueryArgs {
+        query: "is:open".into(),
+        repo: vec!["bar/baz".into()],
+        sort: Some(Sort::ThumbsUp),
+        ..Default::default()
+    };
+    assert_eq!(
+        qa.to_queries(),
+        vec!["repo:bar/baz sort:reactions-+1 is:open"]
+    );
+
+    let qa = QueryArgs {
+        query: "is:open sort:created-asc".into(),
+        sort: Some(Sort::ThumbsUp),
+        ..Default::default()
+    };
+    assert_eq!(qa.to_queries(), vec!["is:open sort:created-asc"]);
+}
+
+#[test]
+fn test_multiple_scopes() {
+    let qa = QueryArgs {
+        query: "foo OR bar".into(),
+        repo: vec!["a/b".into(), "c/d".into()],
+        org: vec!["e".into()],
+        user: vec!["f".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        qa.to_queries(),
+        vec!["repo:a/b repo:c/d org:e user:f (foo OR bar)"]
+    );
+    assert_eq!(qa.single_repo(), None);
+
+    let qa = QueryArgs {
+        query: "is:open".into(),
+        repo: (0..40).map(|i| format!("owner/repo{i:02}")).collect(),
+        ..Default::default()
+    };
+    let queries = qa.to_queries();
+    assert_eq!(queries.len(), 4);
+    assert!(queries.iter().all(|q| q.len() <= MAX_QUERY_LENGTH));
+    assert!(queries.iter().all(|q| q.ends_with(" is:open")));
+    assert!(queries[0].starts_with("repo:owner/repo00 repo:owner/repo01 "));
+    let repos: Vec<_> = queries
+        .iter()
+        .flat_map(|q| q.split(' ').filter(|t| t.starts_with("repo:")))
+        .collect();
+    assert_eq!(repos.len(), 40);
+
+    let qa = QueryArgs {
+        sort: Some(Sort::Updated),
+        ..qa
+    };
+    let expanded = qa.expand(chrono::Local::now().date_naive());
+    let queries = expanded.queries;
+    assert_eq!(queries.len(), 1);
+    assert!(
+        queries[0].ends_with(" sort:updated is:open"),
+        "{}",
+        queries[0]
+    );
+    assert_eq!(queries[0].matches("repo:").count(), 40);
+    assert_eq!(
+        expanded.problems,
+        vec!["sorted queries aren't split, and can be at most 256 characters long"]
+    );
+
+    // alternatives are searched separately to keep under the limit on operators
+    let qa = QueryArgs {
+        query: "a OR b OR c OR d OR e OR f OR g".into(),
+        repo: vec!["a/b".into(), "c/d".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        qa.to_queries(),
+        vec![
+            "repo:a/b repo:c/d (a OR b OR c OR d OR e OR f)",
+            "repo:a/b repo:c/d g"
+        ]
+    );
+    let qa = QueryArgs {
+        sort: Some(Sort::Updated),
+        ..qa
+    };
+    let expanded = qa.expand(chrono::Local::now().date_naive());
+    assert_eq!(expanded.queries.len(), 1);
+    assert_eq!(
+        expanded.problems,
+        vec!["sorted queries aren't split, and can have at most 5 AND, OR and NOT operators"]
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("repos");
+    std::fs::write(&path, "# mine\nfoo/bar\n\n  baz/qux  \n").unwrap();
+    let mut qa = QueryArgs {
+        repo: vec!["a/b".into()],
+        repos_file: Some(path),
+        ..Default::default()
+    };
+    qa.read_repos_file().unwrap();
+    assert_eq!(qa.repo, vec!["a/b", "foo/bar", "baz/qux"]);
+
+    let qa = QueryArgs {
+        repo: vec!["a/b".into()],
         ..Default::default()
     };
-    assert_eq!(qa.to_query(), "repo:bar/baz foo");
+    assert_eq!(qa.single_repo(), Some("a/b"));
 }
@@ -1,7 +1,12 @@
 use anyhow::Result;
 use clap::Parser as _;
-use hubl::Cli;
-use hubl::{github::Github, QueryArgs};
+use hubl::tui::{
+    notifications::Notifications,
+    picker::{Mode, Picker},
+    searches::{Code, Commits, Discussions, Repos, Users},
+};
+use hubl::{config::Config, github::Github, history::History, QueryArgs};
+use hubl::{Cli, IssueCommand};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _, Layer as _};
 
@@ -16,11 +21,12 @@ fn get_auth_token() -> Result<String> {
 use std::path::Path;
 
 fn set_repo(args: &mut QueryArgs) -> Result<()> {
-    if args.repo.is_some() {
+    if args.has_scope() {
         return Ok(());
     }
 
-    if !Path::new(".git").is_dir() {
+    // .git is a file in worktrees
+    if !Path::new(".git").exists() {
         tracing::debug!("not a git repository");
         return Ok(());
     }
@@ -39,7 +45,7 @@ fn set_repo(args: &mut QueryArgs) -> Result<()> {
     let repo = core::str::from_utf8(&output.stdout)?.trim().to_string();
 
     tracing::debug!("setting repo: {repo}");
-    args.repo = Some(repo);
+    args.repo = vec![repo];
     Ok(())
 }
 
@@ -61,29 +67,108 @@ pub fn initialize_logging() -> Result<()> {
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    initialize_logging()?;
-
-    let cli = Cli::parse();
+// Run the picker for one of the searches other than issues, or for notifications.
+async fn pick<M: Mode>(
+    github: Github,
+    config: Config,
+    query: &str,
+    terminal: &mut ratatui::DefaultTerminal,
+) -> Result<Vec<String>> {
+    let history = History::open(M::NAME, None);
+    let mut picker = Picker::<M>::new(github, config, query, history)?;
+    picker.run(terminal).await?;
+    Ok(picker.output().to_vec())
+}
 
-    let mut terminal = ratatui::init();
+// Run the app for the command in the initialized terminal, returning its output.
+async fn run(
+    command: hubl::Command,
+    github: Github,
+    config: Config,
+    terminal: &mut ratatui::DefaultTerminal,
+) -> Result<Vec<String>> {
     crossterm::execute!(
         std::io::stdout(),
         crossterm::cursor::SetCursorStyle::BlinkingBar
     )?;
-    let github = Github {
-        host: "https://api.github.com".to_string(),
-        token: get_auth_token()?,
-    };
-    let result = match cli.command {
+    match command {
         hubl::Command::Issues(mut cmd) => {
+            cmd.read_repos_file()?;
             set_repo(&mut cmd)?;
-            hubl::tui::issues::App::new(github, cmd)?
-                .run(&mut terminal)
+            let history = History::open("issues", cmd.history_scope().as_deref());
+            let mut app = hubl::tui::issues::App::new(github, config, cmd, history)?;
+            app.run(terminal).await.map(|()| app.output().to_vec())
+        }
+        hubl::Command::Issue {
+            command: IssueCommand::New(new),
+        } => {
+            // the form is shown next to the repo's open issues
+            let mut cmd = QueryArgs {
+                query: "is:issue is:open".to_string(),
+                repo: new.repo.into_iter().collect(),
+                ..Default::default()
+            };
+            set_repo(&mut cmd)?;
+            // the fixed query isn't recorded in the history of searches for issues
+            let history = History::in_memory("issues", cmd.history_scope().as_deref());
+            let mut app = hubl::tui::issues::App::new(github, config, cmd, history)?;
+            app.new_issue_then_exit(new.template);
+            app.run(terminal).await.map(|()| app.output().to_vec())
+        }
+        hubl::Command::Issue {
+            command: IssueCommand::Current,
+        } => unreachable!("handled before starting the terminal"),
+        hubl::Command::Code(args) => pick::<Code>(github, config, &args.query, terminal).await,
+        hubl::Command::Repos(args) => pick::<Repos>(github, config, &args.query, terminal).await,
+        hubl::Command::Discussions(args) => {
+            pick::<Discussions>(github, config, &args.query, terminal).await
+        }
+        hubl::Command::Commits(args) => {
+            pick::<Commits>(github, config, &args.query, terminal).await
+        }
+        hubl::Command::Users(args) => pick::<Users>(github, config, &args.query, terminal).await,
+        hubl::Command::Dashboard(args) => {
+            let mut dashboard = hubl::tui::dashboard::Dashboard::new(github, &config, &args)?;
+            dashboard
+                .run(terminal)
                 .await
+                .map(|()| dashboard.output().to_vec())
         }
+        hubl::Command::Notifications(args) => {
+            pick::<Notifications>(github, config, &args.query, terminal).await
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    initialize_logging()?;
+
+    let cli = Cli::parse();
+    if let hubl::Command::Issue {
+        command: IssueCommand::Current,
+    } = cli.command
+    {
+        let dir = std::env::current_dir()?;
+        let Some(url) = hubl::git::current_issue(&dir)? else {
+            anyhow::bail!("The current branch wasn't created for an issue");
+        };
+        println!("{url}");
+        return Ok(());
+    }
+    let config = Config::open()?;
+
+    let github = Github {
+        host: "https://api.github.com".to_string(),
+        token: get_auth_token()?,
     };
+
+    let mut terminal = ratatui::init();
+    // the terminal is restored whether or not the command succeeded
+    let result = run(cli.command, github, config, &mut terminal).await;
     ratatui::restore();
-    result
+    for line in result? {
+        println!("{line}");
+    }
+    Ok(())
 }
@@ -0,0 +1,197 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+// Split a command from an environment variable like "code --wait" into the program and its args.
+fn split_command(command: &str) -> Option<Command> {
+    let mut words = command.split_whitespace();
+    let mut cmd = Command::new(words.next()?);
+    cmd.args(words);
+    Some(cmd)
+}
+
+/// The command to open `url` with.
+/// `browser` is the value of `$BROWSER`: a colon separated list of commands,
+/// each of which may use `%s` for the url. The first is used.
+/// Without it, the platform's default opener is used.
+pub fn browser_command(browser: Option<&str>, url: &str) -> Command {
+    let browser = browser
+        .and_then(|b| b.split(':').find(|c| !c.trim().is_empty()))
+        .and_then(|b| {
+            if b.contains("%s") {
+                split_command(&b.replace("%s", url))
+            } else {
+                split_command(b).map(|mut cmd| {
+                    cmd.arg(url);
+                    cmd
+                })
+            }
+        });
+    browser.unwrap_or_else(|| {
+        let mut cmd = Command::new(if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        });
+        cmd.arg(url);
+        cmd
+    })
+}
+
+/// Open `url` in the browser, without waiting for it to close.
+pub fn open_url(url: &str) -> Result<()> {
+    let mut cmd = browser_command(std::env::var("BROWSER").ok().as_deref(), url);
+    tracing::debug!("Opening url: {cmd:?}");
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Running {cmd:?}"))?;
+    Ok(())
+}
+
+/// The command to edit `path` at `line`.
+/// `editor` is the value of `$VISUAL` or `$EDITOR`, falling back to vi.
+/// The line is passed as `+N`, which most terminal editors understand.
+pub fn editor_command(editor: Option<&str>, path: &Path, line: Option<usize>) -> Command {
+    let mut cmd = editor
+        .and_then(split_command)
+        .unwrap_or_else(|| Command::new("vi"));
+    if let Some(line) = line {
+        cmd.arg(format!("+{line}"));
+    }
+    cmd.arg(path);
+    cmd
+}
+
+/// The user's preferred editor, from `$VISUAL` or `$EDITOR`.
+pub fn editor() -> Option<String> {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+}
+
+// Returns true if a remote url, like git@github.com:foo/bar.git, points at the repo.
+pub(crate) fn remote_matches(url: &str, repo: &str) -> bool {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    url.rsplit_once([':', '/'])
+        .and_then(|(rest, name)| {
+            let (_, owner) = rest.rsplit_once([':', '/'])?;
+            Some(format!("{owner}/{name}"))
+        })
+        .is_some_and(|r| r.eq_ignore_ascii_case(repo))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Find a local checkout of `repo` (e.g. "foo/bar").
+/// Checkouts in the config take precedence, otherwise the git repository containing `dir`
+/// is used if one of its remotes points at `repo`.
+pub fn find_checkout(config: &Config, repo: &str, dir: &Path) -> Option<PathBuf> {
+    if let Some(path) = config.checkouts.get(repo) {
+        let home = std::env::var_os("HOME");
+        return Some(match (path.strip_prefix("~"), home) {
+            (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+            _ => path.clone(),
+        });
+    }
+    let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let remotes = git(dir, &["remote"])?;
+    remotes
+        .lines()
+        .filter_map(|remote| git(dir, &["remote", "get-url", remote]))
+        .any(|url| remote_matches(&url, repo))
+        .then(|| PathBuf::from(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_browser_command() {
+        let url = "https://github.com/foo/bar/issues/1";
+        assert_eq!(
+            args(&browser_command(Some("firefox"), url)),
+            vec!["firefox", url]
+        );
+        assert_eq!(
+            args(&browser_command(Some("w3m -N %s:lynx"), url)),
+            vec!["w3m", "-N", url]
+        );
+        assert_eq!(args(&browser_command(Some(""), url))[1], url);
+        assert_eq!(args(&browser_command(None, url))[1], url);
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let path = Path::new("src/lib.rs");
+        assert_eq!(
+            args(&editor_command(Some("code --wait"), path, Some(12))),
+            vec!["code", "--wait", "+12", "src/lib.rs"]
+        );
+        assert_eq!(
+            args(&editor_command(None, path, None)),
+            vec!["vi", "src/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_find_checkout() {
+        assert!(remote_matches("git@github.com:foo/bar.git", "foo/bar"));
+        assert!(remote_matches("https://github.com/Foo/Bar/", "foo/bar"));
+        assert!(remote_matches("ssh://git@github.com/foo/bar", "foo/bar"));
+        assert!(!remote_matches("https://github.com/foo/barn", "foo/bar"));
+        assert!(!remote_matches("bar", "foo/bar"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        assert_eq!(find_checkout(&config, "foo/bar", dir.path()), None);
+
+        git(dir.path(), &["init", "-q"]).unwrap();
+        git(
+            dir.path(),
+            &["remote", "add", "origin", "git@github.com:foo/bar.git"],
+        )
+        .unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let found =
+            find_checkout(&config, "foo/bar", dir.path()).map(|p| p.canonicalize().unwrap());
+        assert_eq!(found, Some(root));
+        assert_eq!(find_checkout(&config, "foo/baz", dir.path()), None);
+
+        config
+            .checkouts
+            .insert("foo/baz".into(), PathBuf::from("/src/baz"));
+        assert_eq!(
+            find_checkout(&config, "foo/baz", dir.path()),
+            Some(PathBuf::from("/src/baz"))
+        );
+    }
+}
@@ -0,0 +1,1061 @@
+use std::{fmt, ops::Range};
+
+use chrono::{Days, Months, NaiveDate};
+
+// GitHub rejects queries with more than this many AND, OR and NOT operators.
+pub const MAX_OPERATORS: usize = 5;
+
+// Where the values for a qualifier come from.
+pub enum Values {
+    None,
+    Static(&'static [&'static str]),
+    // an ISO date, or a relative date like `2w` that is expanded before searching
+    Dates,
+    Labels,
+    Milestones,
+    Users,
+}
+
+pub struct Qualifier {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub values: Values,
+}
+
+const fn qualifier(name: &'static str, description: &'static str, values: Values) -> Qualifier {
+    Qualifier {
+        name,
+        description,
+        values,
+    }
+}
+
+/// Qualifiers supported by issue and pull request search.
+pub const QUALIFIERS: &[Qualifier] = &[
+    qualifier(
+        "is",
+        "state, type or visibility",
+        Values::Static(&[
+            "open", "closed", "issue", "pr", "merged", "unmerged", "draft", "locked", "unlocked",
+            "public", "private", "queued",
+        ]),
+    ),
+    qualifier(
+        "state",
+        "open or closed",
+        Values::Static(&["open", "closed"]),
+    ),
+    qualifier("type", "issue or pr", Values::Static(&["issue", "pr"])),
+    qualifier(
+        "in",
+        "where to match the text",
+        Values::Static(&["title", "body", "comments"]),
+    ),
+    qualifier("author", "created by a user", Values::Users),
+    qualifier("assignee", "assigned to a user", Values::Users),
+    qualifier("mentions", "mentions a user", Values::Users),
+    qualifier("commenter", "has a comment from a user", Values::Users),
+    qualifier("involves", "involves a user in any way", Values::Users),
+    qualifier("team", "mentions a team", Values::None),
+    qualifier("label", "has a label", Values::Labels),
+    qualifier("milestone", "in a milestone", Values::Milestones),
+    qualifier("project", "in a project board", Values::None),
+    qualifier(
+        "no",
+        "is missing metadata",
+        Values::Static(&["label", "milestone", "assignee", "project"]),
+    ),
+    qualifier("language", "in repositories using a language", Values::None),
+    qualifier("comments", "number of comments", Values::None),
+    qualifier(
+        "interactions",
+        "number of reactions and comments",
+        Values::None,
+    ),
+    qualifier("reactions", "number of reactions", Values::None),
+    qualifier("created", "creation date", Values::Dates),
+    qualifier("updated", "last update date", Values::Dates),
+    qualifier("closed", "close date", Values::Dates),
+    qualifier("merged", "merge date", Values::Dates),
+    qualifier(
+        "reason",
+        "reason an issue was closed",
+        Values::Static(&["completed", "\"not planned\""]),
+    ),
+    qualifier(
+        "review",
+        "pull request review status",
+        Values::Static(&["none", "required", "approved", "changes_requested"]),
+    ),
+    qualifier(
+        "review-requested",
+        "review requested from a user",
+        Values::Users,
+    ),
+    qualifier("reviewed-by", "reviewed by a user", Values::Users),
+    qualifier(
+        "team-review-requested",
+        "review requested from a team",
+        Values::None,
+    ),
+    qualifier(
+        "status",
+        "commit status",
+        Values::Static(&["pending", "success", "failure"]),
+    ),
+    qualifier("head", "pull request head branch", Values::None),
+    qualifier("base", "pull request base branch", Values::None),
+    qualifier(
+        "linked",
+        "linked to an issue or pr",
+        Values::Static(&["pr", "issue"]),
+    ),
+    qualifier(
+        "archived",
+        "in an archived repository",
+        Values::Static(&["true", "false"]),
+    ),
+    qualifier(
+        "draft",
+        "draft pull request",
+        Values::Static(&["true", "false"]),
+    ),
+    qualifier("repo", "in a repository", Values::None),
+    qualifier("org", "in an organization's repositories", Values::None),
+    qualifier("user", "in a user's repositories", Values::None),
+    qualifier(
+        "sort",
+        "result order",
+        Values::Static(&[
+            "created-desc",
+            "created-asc",
+            "updated-desc",
+            "updated-asc",
+            "comments-desc",
+            "comments-asc",
+            "reactions-desc",
+            "reactions-+1-desc",
+            "interactions-desc",
+        ]),
+    ),
+];
+
+/// A term before and after expanding relative dates.
+pub type Expansion = (String, String);
+
+/// A date relative to today, e.g. `3d`, `2w`, `1m`, `1y`, `today` or `yesterday`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelativeDate {
+    Days(u64),
+    Months(u32),
+}
+
+impl RelativeDate {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "today" => return Some(Self::Days(0)),
+            "yesterday" => return Some(Self::Days(1)),
+            _ => {}
+        }
+        let (n, unit) = s.split_at_checked(s.len().checked_sub(1)?)?;
+        // don't accept signs or other things str::parse allows
+        if n.is_empty() || !n.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let n: u32 = n.parse().ok()?;
+        match unit {
+            "d" => Some(Self::Days(n.into())),
+            "w" => Some(Self::Days(u64::from(n) * 7)),
+            "m" => Some(Self::Months(n)),
+            "y" => Some(Self::Months(n.checked_mul(12)?)),
+            _ => None,
+        }
+    }
+
+    /// The date this many days or months before today.
+    pub fn resolve(self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Days(n) => today.checked_sub_days(Days::new(n)),
+            Self::Months(n) => today.checked_sub_months(Months::new(n)),
+        }
+    }
+}
+
+fn is_date(s: &str) -> bool {
+    s == "*" || s.starts_with(|c: char| c.is_ascii_digit()) || RelativeDate::parse(s).is_some()
+}
+
+// Expand a relative date, leaving anything else unchanged.
+fn expand_date(s: &mut String, today: NaiveDate) {
+    if let Some(date) = RelativeDate::parse(s).and_then(|r| r.resolve(today)) {
+        *s = date.format("%Y-%m-%d").to_string();
+    }
+}
+
+// Qualifiers that restrict which repositories are searched.
+const SCOPES: [&str; 3] = ["repo", "org", "user"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Text(String),
+    Compare(Op, String),
+    // either side may be `*` for an open range
+    Range(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    Word(String),
+    Phrase(String),
+    Qualifier {
+        negated: bool,
+        name: String,
+        value: Value,
+    },
+    And,
+    Or,
+    Not,
+    Group(Vec<Node>),
+}
+
+/// A term and the byte range of the input it was parsed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub term: Term,
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// A parsed issue search query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    pub nodes: Vec<Node>,
+}
+
+fn quote(s: &str) -> String {
+    if s.is_empty() || s.contains(char::is_whitespace) {
+        format!("\"{s}\"")
+    } else {
+        s.to_string()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{}", quote(s)),
+            Value::Compare(op, s) => write!(f, "{}{}", op.as_str(), quote(s)),
+            Value::Range(lo, hi) => write!(f, "{lo}..{hi}"),
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Word(s) => write!(f, "{s}"),
+            Term::Phrase(s) => write!(f, "\"{s}\""),
+            Term::Qualifier {
+                negated,
+                name,
+                value,
+            } => {
+                let neg = if *negated { "-" } else { "" };
+                write!(f, "{neg}{name}:{value}")
+            }
+            Term::And => write!(f, "AND"),
+            Term::Or => write!(f, "OR"),
+            Term::Not => write!(f, "NOT"),
+            Term::Group(nodes) => write!(f, "({})", join(nodes)),
+        }
+    }
+}
+
+fn join(nodes: &[Node]) -> String {
+    nodes
+        .iter()
+        .map(|n| n.term.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", join(&self.nodes))
+    }
+}
+
+// Levenshtein distance, for suggesting the qualifier that was probably meant.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn unknown_qualifier(name: &str) -> String {
+    let closest = QUALIFIERS
+        .iter()
+        .map(|q| (distance(name, q.name), q.name))
+        .min()
+        // short names need a closer match to avoid silly suggestions
+        .filter(|(d, _)| *d <= 2 && *d <= name.len().saturating_sub(1) / 2);
+    match closest {
+        Some((_, suggestion)) => {
+            format!("unknown qualifier '{name}', did you mean '{suggestion}'?")
+        }
+        None => format!("unknown qualifier '{name}'"),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    errors: Vec<Error>,
+    operators: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&mut self, span: Range<usize>, message: impl Into<String>) {
+        self.errors.push(Error {
+            span,
+            message: message.into(),
+        });
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // Read up to the next unquoted whitespace or closing paren.
+    fn read_word(&mut self) -> Range<usize> {
+        let start = self.pos;
+        let mut quoted = false;
+        for (i, c) in self.rest().char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                c if !quoted && (c.is_whitespace() || c == ')') => {
+                    self.pos += i;
+                    return start..self.pos;
+                }
+                _ => {}
+            }
+        }
+        self.pos = self.input.len();
+        if quoted {
+            self.error(start..self.pos, "unterminated quote");
+        }
+        start..self.pos
+    }
+
+    fn parse_phrase(&mut self) -> Node {
+        let start = self.pos;
+        let rest = &self.rest()[1..];
+        let (phrase, end) = match rest.find('"') {
+            Some(i) => (&rest[..i], start + i + 2),
+            None => {
+                self.error(start..self.input.len(), "unterminated quote");
+                (rest, self.input.len())
+            }
+        };
+        self.pos = end;
+        Node {
+            term: Term::Phrase(phrase.to_string()),
+            span: start..end,
+        }
+    }
+
+    fn parse_value(&mut self, span: Range<usize>) -> Value {
+        let raw = &self.input[span.clone()];
+        let unquote = |s: &str| s.trim_matches('"').to_string();
+        for op in [Op::Ge, Op::Le, Op::Gt, Op::Lt] {
+            if let Some(v) = raw.strip_prefix(op.as_str()) {
+                if v.is_empty() {
+                    self.error(span, format!("missing value after '{}'", op.as_str()));
+                }
+                return Value::Compare(op, unquote(v));
+            }
+        }
+        if let Some((lo, hi)) = raw.split_once("..") {
+            if lo.is_empty() || hi.is_empty() {
+                self.error(
+                    span,
+                    "ranges need a value on both sides, use '*' for no limit",
+                );
+            }
+            return Value::Range(lo.to_string(), hi.to_string());
+        }
+        if raw.is_empty() {
+            self.error(span, "missing qualifier value");
+        }
+        Value::Text(unquote(raw))
+    }
+
+    fn parse_word(&mut self) -> Node {
+        let span = self.read_word();
+        let word = &self.input[span.clone()];
+        let term = match word {
+            "AND" => Term::And,
+            "OR" => Term::Or,
+            "NOT" => Term::Not,
+            _ => {
+                let (negated, qualifier) = match word.strip_prefix('-') {
+                    Some(w) => (true, w),
+                    None => (false, word),
+                };
+                match qualifier.split_once(':') {
+                    Some((name, _))
+                        if !name.is_empty()
+                            && name.chars().all(|c| c.is_ascii_alphabetic() || c == '-') =>
+                    {
+                        let name_start = span.start + usize::from(negated);
+                        let name_span = name_start..name_start + name.len();
+                        let value_span = name_span.end + 1..span.end;
+                        let value = self.parse_value(value_span.clone());
+                        self.validate(name, &value, name_span, value_span);
+                        Term::Qualifier {
+                            negated,
+                            name: name.to_string(),
+                            value,
+                        }
+                    }
+                    _ => Term::Word(word.to_string()),
+                }
+            }
+        };
+        if matches!(term, Term::And | Term::Or | Term::Not) {
+            self.operators += 1;
+        }
+        Node { term, span }
+    }
+
+    fn validate(
+        &mut self,
+        name: &str,
+        value: &Value,
+        name_span: Range<usize>,
+        value_span: Range<usize>,
+    ) {
+        let Some(qualifier) = QUALIFIERS.iter().find(|q| q.name == name) else {
+            self.error(name_span, unknown_qualifier(name));
+            return;
+        };
+        if let Values::Dates = qualifier.values {
+            let valid = match value {
+                Value::Text(s) | Value::Compare(_, s) => s.is_empty() || is_date(s),
+                // empty sides are already reported by the parser
+                Value::Range(lo, hi) => [lo, hi].iter().all(|s| s.is_empty() || is_date(s)),
+            };
+            if !valid {
+                self.error(
+                    value_span,
+                    format!("'{name}' expects a date like 2024-05-01, or a relative date like 3d, 2w, 1m, 1y or today"),
+                );
+            }
+            return;
+        }
+        // sort also accepts values without a direction, so it isn't checked
+        if let (Values::Static(values), Value::Text(text), false) =
+            (&qualifier.values, value, name == "sort")
+        {
+            let text = quote(text);
+            if !text.is_empty() && !values.contains(&text.as_str()) {
+                self.error(
+                    value_span,
+                    format!(
+                        "unknown value '{text}' for '{name}', expected one of: {}",
+                        values.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    // Parse terms until the end of input, or a closing paren if `nested`.
+    fn parse_nodes(&mut self, nested: bool) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.rest().chars().next() else {
+                break;
+            };
+            let node = match c {
+                ')' if nested => break,
+                ')' => {
+                    self.error(self.pos..self.pos + 1, "unmatched ')'");
+                    self.pos += 1;
+                    continue;
+                }
+                '(' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    let children = self.parse_nodes(true);
+                    if self.rest().starts_with(')') {
+                        self.pos += 1;
+                    } else {
+                        self.error(start..start + 1, "unmatched '('");
+                    }
+                    Node {
+                        term: Term::Group(children),
+                        span: start..self.pos,
+                    }
+                }
+                '"' => self.parse_phrase(),
+                _ => self.parse_word(),
+            };
+            nodes.push(node);
+        }
+        self.check_operators(&nodes);
+        nodes
+    }
+
+    fn check_operators(&mut self, nodes: &[Node]) {
+        let is_binary = |n: &Node| matches!(n.term, Term::And | Term::Or);
+        let is_operator = |n: &Node| matches!(n.term, Term::And | Term::Or | Term::Not);
+        for (i, node) in nodes.iter().enumerate() {
+            let next = nodes.get(i + 1);
+            let prev = i.checked_sub(1).and_then(|i| nodes.get(i));
+            let missing_right = next.is_none_or(is_binary);
+            let missing_left = prev.is_none_or(is_operator);
+            let message = match node.term {
+                Term::And | Term::Or if missing_left || missing_right => {
+                    format!("{} needs a term on both sides", node.term)
+                }
+                Term::Not if missing_right => "NOT needs a term after it".to_string(),
+                _ => continue,
+            };
+            self.error(node.span.clone(), message);
+        }
+    }
+}
+
+// Count the AND, OR and NOT operators, including those in groups.
+fn operators(nodes: &[Node]) -> usize {
+    nodes
+        .iter()
+        .map(|n| match &n.term {
+            Term::And | Term::Or | Term::Not => 1,
+            Term::Group(nodes) => operators(nodes),
+            _ => 0,
+        })
+        .sum()
+}
+
+// Split terms into terms with at most `max` operators whose results together are those of the
+// terms, by searching alternatives separately.
+fn split_alternatives(nodes: Vec<Node>, max: usize) -> Vec<Vec<Node>> {
+    if operators(&nodes) <= max {
+        return vec![nodes];
+    }
+    if nodes.iter().any(|n| n.term == Term::Or) {
+        // as many alternatives as fit are kept together
+        let mut parts: Vec<Vec<Node>> = Vec::new();
+        for alternative in nodes.split(|n| n.term == Term::Or) {
+            for part in split_alternatives(alternative.to_vec(), max) {
+                match parts.last_mut() {
+                    Some(last) if operators(last) + 1 + operators(&part) <= max => {
+                        last.push(Node {
+                            term: Term::Or,
+                            span: 0..0,
+                        });
+                        last.extend(part);
+                    }
+                    _ => parts.push(part),
+                }
+            }
+        }
+        return parts;
+    }
+    // the group with the most operators is split, unless it's negated
+    let group = nodes
+        .iter()
+        .enumerate()
+        .filter(|(i, n)| {
+            let negated = i.checked_sub(1).is_some_and(|i| nodes[i].term == Term::Not);
+            !negated && matches!(&n.term, Term::Group(g) if g.iter().any(|n| n.term == Term::Or))
+        })
+        .max_by_key(|(_, n)| operators(std::slice::from_ref(*n)))
+        .map(|(i, _)| i);
+    let Some(i) = group else {
+        return vec![nodes];
+    };
+    let Term::Group(group) = nodes[i].term.clone() else {
+        return vec![nodes];
+    };
+    let others = operators(&nodes) - operators(&group);
+    split_alternatives(group, max.saturating_sub(others))
+        .into_iter()
+        .flat_map(|part| {
+            let mut nodes = nodes.clone();
+            nodes[i].term = match <[Node; 1]>::try_from(part) {
+                Ok([node]) => node.term,
+                Err(part) => Term::Group(part),
+            };
+            split_alternatives(nodes, max)
+        })
+        .collect()
+}
+
+impl Query {
+    /// Parse a query, returning it along with any problems found.
+    /// Parsing never fails, so the query can still be sent and highlighted as typed.
+    pub fn parse(input: &str) -> (Self, Vec<Error>) {
+        let mut parser = Parser {
+            input,
+            pos: 0,
+            errors: Vec::new(),
+            operators: 0,
+        };
+        let nodes = parser.parse_nodes(false);
+        if parser.operators > MAX_OPERATORS {
+            parser.error(
+                0..input.len(),
+                format!("queries can have at most {MAX_OPERATORS} AND, OR and NOT operators"),
+            );
+        }
+        parser.errors.sort_by_key(|e| e.span.start);
+        (Self { nodes }, parser.errors)
+    }
+
+    /// Returns true if the query restricts which repositories are searched.
+    pub fn is_scoped(&self) -> bool {
+        fn scoped(nodes: &[Node]) -> bool {
+            nodes.iter().any(|n| match &n.term {
+                Term::Qualifier {
+                    negated: false,
+                    name,
+                    ..
+                } => SCOPES.contains(&name.as_str()),
+                Term::Group(nodes) => scoped(nodes),
+                _ => false,
+            })
+        }
+        scoped(&self.nodes)
+    }
+
+    /// Returns true if the query has a `name` qualifier, negated or not.
+    pub fn has_qualifier(&self, name: &str) -> bool {
+        fn has(nodes: &[Node], name: &str) -> bool {
+            nodes.iter().any(|n| match &n.term {
+                Term::Qualifier { name: found, .. } => found == name,
+                Term::Group(nodes) => has(nodes, name),
+                _ => false,
+            })
+        }
+        has(&self.nodes, name)
+    }
+
+    /// Replace relative dates like `2w` in date qualifiers with ISO dates relative to `today`.
+    /// Returns each changed term before and after expansion.
+    pub fn expand_dates(&mut self, today: NaiveDate) -> Vec<Expansion> {
+        fn expand(nodes: &mut [Node], today: NaiveDate, changes: &mut Vec<Expansion>) {
+            for node in nodes {
+                let before = node.term.to_string();
+                match &mut node.term {
+                    Term::Qualifier { name, value, .. }
+                        if QUALIFIERS
+                            .iter()
+                            .any(|q| q.name == name && matches!(q.values, Values::Dates)) =>
+                    {
+                        match value {
+                            Value::Text(s) | Value::Compare(_, s) => expand_date(s, today),
+                            Value::Range(lo, hi) => {
+                                expand_date(lo, today);
+                                expand_date(hi, today);
+                            }
+                        }
+                    }
+                    Term::Group(nodes) => {
+                        expand(nodes, today, changes);
+                        continue;
+                    }
+                    _ => continue,
+                }
+                let after = node.term.to_string();
+                if before != after {
+                    changes.push((before, after));
+                }
+            }
+        }
+        let mut changes = Vec::new();
+        expand(&mut self.nodes, today, &mut changes);
+        changes
+    }
+
+    /// Replace `@name` words with the saved search `lookup` returns for `name`.
+    /// Saved searches using OR are grouped so they don't change the meaning of the rest of the query.
+    /// Returns the names `lookup` didn't know.
+    pub fn expand_saved(&mut self, mut lookup: impl FnMut(&str) -> Option<Query>) -> Vec<String> {
+        fn expand(
+            nodes: Vec<Node>,
+            lookup: &mut impl FnMut(&str) -> Option<Query>,
+            unknown: &mut Vec<String>,
+        ) -> Vec<Node> {
+            let mut expanded = Vec::with_capacity(nodes.len());
+            for node in nodes {
+                let name = match node.term {
+                    Term::Word(ref word) => word.strip_prefix('@').filter(|n| !n.is_empty()),
+                    Term::Group(nodes) => {
+                        expanded.push(Node {
+                            term: Term::Group(expand(nodes, lookup, unknown)),
+                            span: node.span,
+                        });
+                        continue;
+                    }
+                    _ => None,
+                };
+                let Some(name) = name else {
+                    expanded.push(node);
+                    continue;
+                };
+                let Some(saved) = lookup(name) else {
+                    unknown.push(name.to_string());
+                    expanded.push(node);
+                    continue;
+                };
+                // the saved terms have no place in this input, so they take the span of the name
+                let mut saved: Vec<Node> = saved
+                    .nodes
+                    .into_iter()
+                    .map(|n| Node {
+                        term: n.term,
+                        span: node.span.clone(),
+                    })
+                    .collect();
+                if saved.iter().any(|n| n.term == Term::Or) {
+                    saved = vec![Node {
+                        term: Term::Group(saved),
+                        span: node.span.clone(),
+                    }];
+                }
+                expanded.extend(saved);
+            }
+            expanded
+        }
+        let mut unknown = Vec::new();
+        self.nodes = expand(std::mem::take(&mut self.nodes), &mut lookup, &mut unknown);
+        unknown
+    }
+
+    /// Wrap the query in a group if it has a top-level OR,
+    /// so terms added to it apply to both sides.
+    pub fn group_alternatives(&mut self) {
+        if !self.nodes.iter().any(|n| n.term == Term::Or) {
+            return;
+        }
+        let span = match (self.nodes.first(), self.nodes.last()) {
+            (Some(first), Some(last)) => first.span.start..last.span.end,
+            _ => 0..0,
+        };
+        self.nodes = vec![Node {
+            term: Term::Group(std::mem::take(&mut self.nodes)),
+            span,
+        }];
+    }
+
+    /// The number of AND, OR and NOT operators in the query.
+    pub fn operators(&self) -> usize {
+        operators(&self.nodes)
+    }
+
+    /// Split the query into queries with at most `max` operators each,
+    /// whose results together are those of the query.
+    /// Negated alternatives can't be searched separately, so those queries may have more.
+    pub fn split_alternatives(self, max: usize) -> Vec<Query> {
+        split_alternatives(self.nodes, max)
+            .into_iter()
+            .map(|nodes| Query { nodes })
+            .collect()
+    }
+
+    /// Prepend a qualifier to the query.
+    pub fn push_qualifier(&mut self, name: &str, value: &str) {
+        self.nodes.insert(
+            0,
+            Node {
+                term: Term::Qualifier {
+                    negated: false,
+                    name: name.to_string(),
+                    value: Value::Text(value.to_string()),
+                },
+                span: 0..0,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(term: Term, span: Range<usize>) -> Node {
+        Node { term, span }
+    }
+
+    fn qualifier(negated: bool, name: &str, value: Value) -> Term {
+        Term::Qualifier {
+            negated,
+            name: name.into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let input = r#"crash -label:bug "out of memory" label:"good first issue" comments:>=10 created:2024-01-01..* (is:open OR NOT draft:true)"#;
+        let (query, errors) = Query::parse(input);
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            query.nodes,
+            vec![
+                node(Term::Word("crash".into()), 0..5),
+                node(qualifier(true, "label", Value::Text("bug".into())), 6..16),
+                node(Term::Phrase("out of memory".into()), 17..32),
+                node(
+                    qualifier(false, "label", Value::Text("good first issue".into())),
+                    33..57
+                ),
+                node(
+                    qualifier(false, "comments", Value::Compare(Op::Ge, "10".into())),
+                    58..71
+                ),
+                node(
+                    qualifier(
+                        false,
+                        "created",
+                        Value::Range("2024-01-01".into(), "*".into())
+                    ),
+                    72..93
+                ),
+                node(
+                    Term::Group(vec![
+                        node(qualifier(false, "is", Value::Text("open".into())), 95..102),
+                        node(Term::Or, 103..105),
+                        node(Term::Not, 106..109),
+                        node(
+                            qualifier(false, "draft", Value::Text("true".into())),
+                            110..120
+                        ),
+                    ]),
+                    94..121
+                ),
+            ]
+        );
+        assert_eq!(query.to_string(), input);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let messages = |input| {
+            Query::parse(input)
+                .1
+                .into_iter()
+                .map(|e| (e.span, e.message))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages("lable:bug foo:bar"),
+            vec![
+                (
+                    0..5,
+                    "unknown qualifier 'lable', did you mean 'label'?".into()
+                ),
+                (10..13, "unknown qualifier 'foo'".into()),
+            ]
+        );
+        assert_eq!(
+            messages("is:opne"),
+            vec![(
+                3..7,
+                "unknown value 'opne' for 'is', expected one of: open, closed, issue, pr, merged, unmerged, draft, locked, unlocked, public, private, queued".into()
+            )]
+        );
+        assert_eq!(
+            messages("foo label:\"bar"),
+            vec![(4..14, "unterminated quote".into())]
+        );
+        assert_eq!(
+            messages("foo \"bar"),
+            vec![(4..8, "unterminated quote".into())]
+        );
+        assert_eq!(messages("(foo"), vec![(0..1, "unmatched '('".into())]);
+        assert_eq!(messages("foo)"), vec![(3..4, "unmatched ')'".into())]);
+        assert_eq!(
+            messages("OR foo AND"),
+            vec![
+                (0..2, "OR needs a term on both sides".into()),
+                (7..10, "AND needs a term on both sides".into()),
+            ]
+        );
+        assert_eq!(
+            messages("foo NOT"),
+            vec![(4..7, "NOT needs a term after it".into())]
+        );
+        assert_eq!(
+            messages("label: created:"),
+            vec![
+                (6..6, "missing qualifier value".into()),
+                (15..15, "missing qualifier value".into()),
+            ]
+        );
+        assert_eq!(
+            messages("created:2024.."),
+            vec![(
+                8..14,
+                "ranges need a value on both sides, use '*' for no limit".into()
+            )]
+        );
+        assert_eq!(
+            messages("a OR b OR c OR d OR e OR f OR g"),
+            vec![(
+                0..31,
+                "queries can have at most 5 AND, OR and NOT operators".into()
+            )]
+        );
+        assert_eq!(messages("sort:updated NOT foo"), vec![]);
+    }
+
+    #[test]
+    fn test_relative_date() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let resolve = |s| {
+            RelativeDate::parse(s)
+                .and_then(|r| r.resolve(today))
+                .map(|d| d.to_string())
+        };
+        assert_eq!(resolve("today"), Some("2024-05-31".into()));
+        assert_eq!(resolve("yesterday"), Some("2024-05-30".into()));
+        assert_eq!(resolve("3d"), Some("2024-05-28".into()));
+        assert_eq!(resolve("2w"), Some("2024-05-17".into()));
+        assert_eq!(resolve("1m"), Some("2024-04-30".into()));
+        assert_eq!(resolve("1y"), Some("2023-05-31".into()));
+        assert_eq!(resolve("0d"), Some("2024-05-31".into()));
+        assert_eq!(resolve("d"), None);
+        assert_eq!(resolve("+3d"), None);
+        assert_eq!(resolve("3x"), None);
+        assert_eq!(resolve("2024-05-01"), None);
+    }
+
+    #[test]
+    fn test_expand_dates() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let (mut query, errors) = Query::parse(
+            "foo 2w updated:<2w (created:>3d OR closed:today) merged:1m..yesterday label:1d created:2024-01-01",
+        );
+        assert_eq!(errors, vec![]);
+        assert_eq!(
+            query.expand_dates(today),
+            vec![
+                ("updated:<2w".into(), "updated:<2024-05-17".into()),
+                ("created:>3d".into(), "created:>2024-05-28".into()),
+                ("closed:today".into(), "closed:2024-05-31".into()),
+                (
+                    "merged:1m..yesterday".into(),
+                    "merged:2024-04-30..2024-05-30".into()
+                ),
+            ]
+        );
+        assert_eq!(
+            query.to_string(),
+            "foo 2w updated:<2024-05-17 (created:>2024-05-28 OR closed:2024-05-31) merged:2024-04-30..2024-05-30 label:1d created:2024-01-01"
+        );
+
+        let (_, errors) = Query::parse("created:soon updated:1w..later");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].span, 8..12);
+        assert_eq!(errors[1].span, 21..30);
+    }
+
+    #[test]
+    fn test_expand_saved() {
+        let lookup = |name: &str| match name {
+            "triage" => Some(Query::parse("is:open no:label").0),
+            "either" => Some(Query::parse("label:bug OR label:crash").0),
+            _ => None,
+        };
+        let (mut query, _) = Query::parse("@triage foo (@either bar) @ @nope me@example.com");
+        assert_eq!(query.expand_saved(lookup), vec!["nope".to_string()]);
+        assert_eq!(
+            query.to_string(),
+            "is:open no:label foo ((label:bug OR label:crash) bar) @ @nope me@example.com"
+        );
+        assert_eq!(query.nodes[0].span, 0..7);
+        assert_eq!(query.nodes[1].span, 0..7);
+    }
+
+    #[test]
+    fn test_split_alternatives() {
+        let split = |input: &str| {
+            Query::parse(input)
+                .0
+                .split_alternatives(MAX_OPERATORS)
+                .iter()
+                .map(Query::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(split("a OR b AND c"), vec!["a OR b AND c"]);
+        assert_eq!(
+            split("a OR b OR c OR d OR e OR f OR g"),
+            vec!["a OR b OR c OR d OR e OR f", "g"]
+        );
+        assert_eq!(
+            split("x (a OR b OR c OR d) (e OR f OR g OR h)"),
+            vec![
+                "x (a OR b OR c OR d) (e OR f OR g)",
+                "x (a OR b OR c OR d) h"
+            ]
+        );
+        // the alternatives of a negated group are all excluded by each query
+        let query = "x NOT (a OR b OR c OR d OR e OR f)";
+        assert_eq!(split(query), vec![query]);
+        assert_eq!(Query::parse(query).0.operators(), 6);
+    }
+
+    #[test]
+    fn test_scope() {
+        assert!(!Query::parse("foo").0.is_scoped());
+        assert!(Query::parse("foo repo:a/b").0.is_scoped());
+        assert!(Query::parse("(org:a OR user:b)").0.is_scoped());
+        assert!(!Query::parse("-repo:a/b").0.is_scoped());
+
+        let mut query = Query::parse("foo label:\"a b\"").0;
+        query.push_qualifier("repo", "a/b");
+        assert_eq!(query.to_string(), "repo:a/b foo label:\"a b\"");
+    }
+}
@@ -0,0 +1,115 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::Stylize as _,
+    text::Line,
+    widgets::{Block, Clear, Gauge, Paragraph},
+    Frame,
+};
+
+use super::menu::centered;
+
+// The most failures listed in the panel, the rest are counted.
+const MAX_FAILURES: usize = 8;
+
+/// An operation applied to many items at once, and how far along it is.
+#[derive(Debug)]
+pub struct Batch {
+    // told apart from earlier batches whose results might still arrive
+    pub id: usize,
+    pub description: String,
+    pub total: usize,
+    pub done: usize,
+    // the item that failed, and why
+    pub failures: Vec<(u32, String)>,
+}
+
+impl Batch {
+    pub fn new(id: usize, description: impl Into<String>, total: usize) -> Self {
+        Self {
+            id,
+            description: description.into(),
+            total,
+            done: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Count the result for one item.
+    pub fn record(&mut self, number: u32, result: Result<(), String>) {
+        self.done += 1;
+        if let Err(err) = result {
+            self.failures.push((number, err));
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done >= self.total
+    }
+
+    /// A one line summary, e.g. "Closed 3 items: 2 done, 1 failed".
+    pub fn summary(&self) -> String {
+        let failed = self.failures.len();
+        match failed {
+            0 => format!("{}: {} done", self.description, self.done),
+            _ => format!(
+                "{}: {} done, {failed} failed",
+                self.description,
+                self.done - failed
+            ),
+        }
+    }
+
+    /// Draw the progress, and once finished the failures, over the middle of the screen.
+    pub fn draw(&self, frame: &mut Frame) {
+        let failures = self.failures.len().min(MAX_FAILURES);
+        let more = self.failures.len() - failures;
+        let lines: Vec<Line> = self.failures[..failures]
+            .iter()
+            .map(|(number, err)| Line::from(format!("#{number}: {err}")).red())
+            .chain((more > 0).then(|| Line::from(format!("and {more} more")).dim()))
+            .collect();
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .chain([self.description.len(), 30])
+            .max()
+            .unwrap_or_default()
+            .min(frame.area().width.saturating_sub(4) as usize) as u16
+            + 4;
+        let height = lines.len() as u16 + 3;
+        let area = centered(frame.area(), width, height);
+
+        let mut block = Block::bordered().title(self.description.as_str());
+        if self.is_finished() {
+            block = block.title_bottom("esc: close");
+        }
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let gauge = Gauge::default()
+            .ratio(self.done as f64 / self.total.max(1) as f64)
+            .label(format!("{}/{}", self.done, self.total));
+        let [gauge_area, failures_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+        frame.render_widget(gauge, gauge_area);
+        frame.render_widget(Paragraph::new(lines), failures_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let mut batch = Batch::new(1, "Closed 3 items", 3);
+        batch.record(1, Ok(()));
+        assert!(!batch.is_finished());
+        batch.record(2, Err("Not found".into()));
+        batch.record(3, Ok(()));
+        assert!(batch.is_finished());
+        assert_eq!(batch.failures, vec![(2, "Not found".to_string())]);
+        assert_eq!(batch.summary(), "Closed 3 items: 2 done, 1 failed");
+    }
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use ratatui::{
+    style::Stylize as _,
+    text::{Line, Span},
+};
+
+use crate::github::checks::{self, CheckRun};
+
+// lines shown above the first error of a log, for context
+const ERROR_CONTEXT: usize = 3;
+
+/// Which check run of a pull request is selected, and the log being read.
+#[derive(Debug, Default)]
+pub struct ChecksView {
+    // the pull request the selection belongs to
+    pub url: String,
+    pub selected: usize,
+    pub log: Option<Log>,
+}
+
+impl ChecksView {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The log of a GitHub Actions job.
+#[derive(Debug)]
+pub struct Log {
+    pub job_id: u64,
+    pub name: String,
+    // the cleaned lines once fetched, or why they couldn't be
+    pub lines: Option<Result<Vec<String>, String>>,
+    // the line reporting the first error, if any did
+    pub error: Option<usize>,
+    pub scroll: usize,
+}
+
+impl Log {
+    pub fn new(run: &CheckRun) -> Self {
+        Self {
+            job_id: run.id,
+            name: run.name.clone(),
+            lines: None,
+            error: None,
+            scroll: 0,
+        }
+    }
+
+    /// Show the fetched log, scrolled to its first error.
+    pub fn load(&mut self, log: Result<String, String>) {
+        let lines = log.map(|log| checks::clean_log(&log));
+        if let Ok(lines) = &lines {
+            self.error = checks::first_error(lines);
+            self.scroll = self.error.unwrap_or_default().saturating_sub(ERROR_CONTEXT);
+        }
+        self.lines = Some(lines);
+    }
+
+    /// Scroll by `lines`, keeping the last line in view.
+    pub fn scroll(&mut self, lines: isize) {
+        let len = match &self.lines {
+            Some(Ok(lines)) => lines.len(),
+            _ => 0,
+        };
+        let max = len.saturating_sub(1) as isize;
+        self.scroll = (self.scroll as isize + lines).clamp(0, max) as usize;
+    }
+
+    /// The lines of the log, with the first error highlighted.
+    pub fn text(&self) -> Vec<Line<'static>> {
+        let Some(Ok(lines)) = &self.lines else {
+            return Vec::new();
+        };
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let line = Line::from(line.clone());
+                if Some(index) == self.error {
+                    line.red().bold()
+                } else {
+                    line
+                }
+            })
+            .collect()
+    }
+}
+
+// How a run went: ✓ passed, ✗ failed, • still running, - skipped or neutral.
+fn icon(run: &CheckRun) -> Span<'static> {
+    match run.conclusion.as_deref() {
+        _ if run.is_failed() => Span::raw("✗").red(),
+        Some("success") => Span::raw("✓").green(),
+        Some(_) => Span::raw("-").dim(),
+        None => Span::raw("•").yellow(),
+    }
+}
+
+/// A duration the way CI shows it, e.g. "2m 13s".
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds}s"),
+        _ => format!("{hours}h {minutes}m"),
+    }
+}
+
+/// The check runs of a commit, one per line, with the selected one highlighted.
+pub fn run_list(runs: &[CheckRun], selected: usize, now: DateTime<Utc>) -> Vec<Line<'static>> {
+    let name_width = runs.iter().map(|r| r.name.chars().count()).max();
+    let name_width = name_width.unwrap_or_default();
+    runs.iter()
+        .enumerate()
+        .map(|(index, run)| {
+            let outcome = match &run.conclusion {
+                Some(conclusion) => conclusion.replace('_', " "),
+                None => run.status.replace('_', " "),
+            };
+            let duration = run.duration(now).map(format_duration).unwrap_or_default();
+            let line = Line::from(vec![
+                icon(run),
+                Span::raw(format!(" {:name_width$}  ", run.name)),
+                Span::raw(format!("{outcome:<11} ")),
+                Span::raw(duration).dim(),
+            ]);
+            if index == selected {
+                line.reversed()
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_list() {
+        assert_eq!(format_duration(chrono::Duration::seconds(7)), "7s");
+        assert_eq!(format_duration(chrono::Duration::seconds(133)), "2m 13s");
+        assert_eq!(format_duration(chrono::Duration::seconds(3725)), "1h 2m");
+
+        let runs: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("testdata/check_runs.json").unwrap())
+                .unwrap();
+        let runs: Vec<CheckRun> = serde_json::from_value(runs["check_runs"].clone()).unwrap();
+        let now = "2024-05-01T12:35:00Z".parse().unwrap();
+        let lines: Vec<String> = run_list(&runs, 1, now)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "✗ test      failure     2m 13s",
+                "• coverage  in progress 1m 0s",
+            ]
+        );
+
+        let mut log = Log::new(&runs[0]);
+        let text = (1..=10).map(|i| format!("line {i}\n")).collect::<String>();
+        log.load(Ok(format!("{text}error: oops\nline 12")));
+        assert_eq!((log.error, log.scroll), (Some(10), 7));
+        log.scroll(100);
+        assert_eq!(log.scroll, 11);
+        log.scroll(-100);
+        assert_eq!(log.scroll, 0);
+    }
+}
@@ -0,0 +1,294 @@
+use std::ops::Range;
+
+use ratatui::{
+    layout::Rect,
+    style::{Style, Stylize as _},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::github::repo::RepoMetadata;
+use crate::query::{Values, QUALIFIERS};
+
+const MAX_VISIBLE: u16 = 8;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Suggestion {
+    // replaces the word being completed
+    pub text: String,
+    // shown next to the suggestion
+    pub description: String,
+}
+
+// Quote values containing spaces so they stay a single search term.
+fn quote(value: &str) -> String {
+    if value.contains(' ') && !value.starts_with('"') {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+fn matches(candidate: &str, prefix: &str) -> bool {
+    candidate
+        .trim_start_matches('"')
+        .to_lowercase()
+        .starts_with(&prefix.trim_start_matches('"').to_lowercase())
+}
+
+/// Suggest completions for the word before `cursor`.
+/// Returns the byte range of the word to replace and the suggestions.
+/// Repository values like labels are only suggested once `metadata` is available.
+pub fn complete(
+    pattern: &str,
+    cursor: usize,
+    metadata: Option<&RepoMetadata>,
+) -> (Range<usize>, Vec<Suggestion>) {
+    let start = pattern[..cursor]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &pattern[start..cursor];
+    // keep the negation out of the replaced range
+    let (start, word) = match word.strip_prefix('-') {
+        Some(word) => (start + 1, word),
+        None => (start, word),
+    };
+    if word.is_empty() {
+        return (start..cursor, Vec::new());
+    }
+
+    let Some((name, prefix)) = word.split_once(':') else {
+        let suggestions = QUALIFIERS
+            .iter()
+            .filter(|q| q.name.starts_with(word) && q.name != word)
+            .map(|q| Suggestion {
+                text: format!("{}:", q.name),
+                description: q.description.to_string(),
+            })
+            .collect();
+        return (start..cursor, suggestions);
+    };
+
+    let Some(qualifier) = QUALIFIERS.iter().find(|q| q.name == name) else {
+        return (start..cursor, Vec::new());
+    };
+
+    let values: Vec<(String, String)> = match (&qualifier.values, metadata) {
+        (Values::None, _) => Vec::new(),
+        (Values::Static(values), _) => values
+            .iter()
+            .map(|v| (v.to_string(), String::new()))
+            .collect(),
+        (Values::Dates, _) => [
+            (">1d", "in the last day"),
+            (">1w", "in the last week"),
+            (">1m", "in the last month"),
+            ("<1y", "over a year ago"),
+            ("today", ""),
+            ("yesterday", ""),
+        ]
+        .iter()
+        .map(|(v, d)| (v.to_string(), d.to_string()))
+        .collect(),
+        (Values::Labels, Some(metadata)) => metadata
+            .labels
+            .nodes
+            .iter()
+            .map(|l| (quote(&l.name), l.description.clone().unwrap_or_default()))
+            .collect(),
+        (Values::Milestones, Some(metadata)) => metadata
+            .milestones
+            .nodes
+            .iter()
+            .map(|m| (quote(&m.title), String::new()))
+            .collect(),
+        (Values::Users, metadata) => std::iter::once(("@me".to_string(), "you".to_string()))
+            .chain(
+                metadata
+                    .iter()
+                    .flat_map(|m| &m.assignable_users.nodes)
+                    .map(|u| (u.login.clone(), String::new())),
+            )
+            .collect(),
+        (Values::Labels | Values::Milestones, None) => Vec::new(),
+    };
+
+    let suggestions = values
+        .into_iter()
+        .filter(|(value, _)| matches(value, prefix) && value != prefix)
+        .map(|(value, description)| Suggestion {
+            text: format!("{name}:{value} "),
+            description,
+        })
+        .collect();
+    (start..cursor, suggestions)
+}
+
+/// A popup listing completions for the query being edited.
+#[derive(Default)]
+pub struct Completion {
+    range: Range<usize>,
+    suggestions: Vec<Suggestion>,
+    state: ListState,
+}
+
+impl Completion {
+    pub fn update(&mut self, pattern: &str, cursor: usize, metadata: Option<&RepoMetadata>) {
+        let (range, suggestions) = complete(pattern, cursor, metadata);
+        if suggestions != self.suggestions {
+            self.state.select(Some(0));
+        }
+        self.range = range;
+        self.suggestions = suggestions;
+    }
+
+    pub fn clear(&mut self) {
+        self.suggestions.clear();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.suggestions.is_empty()
+    }
+
+    pub fn select_next(&mut self) {
+        let last = self.suggestions.len().saturating_sub(1);
+        self.state.select(Some(
+            (self.state.selected().unwrap_or_default() + 1).min(last),
+        ));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.state.select_previous();
+    }
+
+    /// The range to replace and the text to replace it with.
+    pub fn selected(&self) -> Option<(Range<usize>, &str)> {
+        let suggestion = self.suggestions.get(self.state.selected()?)?;
+        Some((self.range.clone(), suggestion.text.as_str()))
+    }
+
+    /// Draw the popup below the given column of the input line.
+    pub fn draw(&mut self, frame: &mut Frame, input: Rect, pattern: &str) {
+        if !self.is_visible() {
+            return;
+        }
+        let x = input.x + pattern[..self.range.start].width() as u16;
+        let width = self
+            .suggestions
+            .iter()
+            .map(|s| s.text.width() + s.description.width() + 2)
+            .max()
+            .unwrap_or_default() as u16
+            + 2;
+        let height = self.suggestions.len().min(MAX_VISIBLE as usize) as u16 + 2;
+        let area = Rect::new(x, input.y + input.height, width, height).intersection(frame.area());
+
+        let list = List::new(self.suggestions.iter().map(|s| {
+            Line::from(vec![
+                Span::raw(s.text.as_str()),
+                Span::raw("  "),
+                Span::raw(s.description.as_str()).dim(),
+            ])
+        }))
+        .block(Block::bordered())
+        .highlight_style(Style::new().reversed());
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::repo::{Actor, Label, Milestone, Nodes};
+
+    fn texts(pattern: &str, metadata: Option<&RepoMetadata>) -> (Range<usize>, Vec<String>) {
+        let (range, suggestions) = complete(pattern, pattern.len(), metadata);
+        (range, suggestions.into_iter().map(|s| s.text).collect())
+    }
+
+    #[test]
+    fn test_complete_qualifier() {
+        assert_eq!(texts("", None), (0..0, vec![]));
+        assert_eq!(texts("foo ", None), (4..4, vec![]));
+        assert_eq!(
+            texts("foo la", None),
+            (4..6, vec!["label:".into(), "language:".into()])
+        );
+        assert_eq!(texts("-mil", None), (1..4, vec!["milestone:".into()]));
+        assert_eq!(texts("xyz", None), (0..3, vec![]));
+        // after an ideographic space, which is more than one byte
+        assert_eq!(
+            texts("バグ\u{3000}mil", None),
+            (9..12, vec!["milestone:".into()])
+        );
+    }
+
+    #[test]
+    fn test_complete_value() {
+        assert_eq!(texts("is:o", None), (0..4, vec!["is:open ".into()]));
+        assert_eq!(texts("is:open", None), (0..7, vec![]));
+        assert_eq!(texts("lable:", None), (0..6, vec![]));
+        assert_eq!(texts("label:", None), (0..6, vec![]));
+        assert_eq!(texts("author:", None), (0..7, vec!["author:@me ".into()]));
+        assert_eq!(
+            texts("updated:t", None),
+            (0..9, vec!["updated:today ".into()])
+        );
+
+        let metadata = RepoMetadata {
+            labels: Nodes {
+                nodes: vec![
+                    Label {
+                        name: "bug".into(),
+                        ..Default::default()
+                    },
+                    Label {
+                        name: "Good first issue".into(),
+                        ..Default::default()
+                    },
+                ],
+            },
+            milestones: Nodes {
+                nodes: vec![Milestone {
+                    title: "v1.0".into(),
+                    ..Default::default()
+                }],
+            },
+            assignable_users: Nodes {
+                nodes: vec![Actor {
+                    login: "octocat".into(),
+                    ..Default::default()
+                }],
+            },
+            ..Default::default()
+        };
+        let metadata = Some(&metadata);
+        assert_eq!(
+            texts("is:open label:", metadata),
+            (
+                8..14,
+                vec!["label:bug ".into(), "label:\"Good first issue\" ".into()]
+            )
+        );
+        assert_eq!(
+            texts("label:\"go", metadata),
+            (0..9, vec!["label:\"Good first issue\" ".into()])
+        );
+        assert_eq!(
+            texts("milestone:v", metadata),
+            (0..11, vec!["milestone:v1.0 ".into()])
+        );
+        assert_eq!(
+            texts("-assignee:", metadata),
+            (
+                1..10,
+                vec!["assignee:@me ".into(), "assignee:octocat ".into()]
+            )
+        );
+    }
+}
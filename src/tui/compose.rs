@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::github::{
+    issues::Issue,
+    merge::MergeMethod,
+    review::{NewThread, ReviewEvent},
+};
+
+/// The directory drafts are kept in until they are posted.
+pub fn drafts_dir() -> Result<PathBuf> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    Ok(xdg_dirs.create_data_directory("drafts")?)
+}
+
+/// An issue or pull request a comment is posted on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subject {
+    pub id: String,
+    pub number: u32,
+}
+
+/// What a comment is posted as.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Draft {
+    // a comment on each subject
+    #[default]
+    Comment,
+    // a review comment on lines of a pull request
+    Thread(NewThread),
+    Reply {
+        thread_id: String,
+    },
+    // the summary of a review
+    Review(ReviewEvent),
+    // the message of the commit merging a pull request, now or once it can be
+    Merge {
+        method: MergeMethod,
+        auto: bool,
+    },
+}
+
+/// A comment being written on one or more issues.
+/// The comment is edited in a draft file, which is only removed once it is posted or discarded,
+/// so nothing is lost if the editor or posting fails.
+#[derive(Debug)]
+pub struct Composer {
+    pub subjects: Vec<Subject>,
+    pub draft: Draft,
+    pub path: PathBuf,
+    pub body: String,
+    // bumped each time the body is reloaded, so a stale preview isn't shown
+    pub revision: usize,
+    pub posting: bool,
+}
+
+impl Composer {
+    /// Start a comment on `issues`, continuing any draft left from before.
+    pub fn new(dir: &Path, issues: &[&Issue]) -> Self {
+        let first = issues.first().copied().cloned().unwrap_or_default();
+        let repo = first.repo().unwrap_or_default().replace('/', "_");
+        let name = match issues.len() {
+            0 | 1 => format!("{repo}_{}.md", first.number),
+            n => format!("{repo}_{}_and_{}_more.md", first.number, n - 1),
+        };
+        Self {
+            subjects: issues
+                .iter()
+                .map(|i| Subject {
+                    id: i.id.clone(),
+                    number: i.number,
+                })
+                .collect(),
+            draft: Draft::Comment,
+            path: dir.join(name),
+            body: String::new(),
+            revision: 0,
+            posting: false,
+        }
+    }
+
+    /// Start a comment that is part of a review of `pull_request`, or its merge commit message.
+    pub fn review(dir: &Path, pull_request: &Issue, draft: Draft) -> Self {
+        let mut composer = Self::new(dir, &[pull_request]);
+        let stem = composer.path.with_extension("");
+        let suffix = match &draft {
+            Draft::Comment => String::new(),
+            Draft::Thread(thread) => {
+                format!("_{}_{}", thread.path.replace(['/', '.'], "_"), thread.line)
+            }
+            Draft::Reply { thread_id } => format!("_reply_{thread_id}"),
+            Draft::Review(_) => "_review".to_string(),
+            Draft::Merge { .. } => "_merge".to_string(),
+        };
+        composer.path = PathBuf::from(format!("{}{suffix}.md", stem.display()));
+        composer.draft = draft;
+        composer
+    }
+
+    /// Whether there's no need to write anything before posting.
+    /// Approvals, and reviews made of comments, can be submitted without a summary.
+    pub fn may_be_empty(&self) -> bool {
+        matches!(self.draft, Draft::Review(_))
+    }
+
+    /// Read the draft after editing.
+    /// Returns false if there's nothing to post.
+    pub fn load(&mut self) -> Result<bool> {
+        self.body = match std::fs::read_to_string(&self.path) {
+            Ok(body) => body,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Reading draft {}", self.path.display()))
+            }
+        };
+        self.revision += 1;
+        Ok(!self.body.trim().is_empty())
+    }
+
+    /// Remove the draft, once posted or when abandoned.
+    pub fn discard(&self) {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => tracing::warn!("Failed to remove draft {:?}: {err}", self.path),
+        }
+    }
+
+    /// What the comment is on, e.g. "#7" or "#7 and 2 more".
+    pub fn target(&self) -> String {
+        match self.subjects.as_slice() {
+            [] => String::new(),
+            [subject] => format!("#{}", subject.number),
+            [subject, rest @ ..] => format!("#{} and {} more", subject.number, rest.len()),
+        }
+    }
+
+    /// What posting the draft does, e.g. "Comment on #7" or "Approve #7".
+    pub fn title(&self) -> String {
+        let target = self.target();
+        match &self.draft {
+            Draft::Comment => format!("Comment on {target}"),
+            Draft::Thread(thread) => match thread.start_line {
+                Some(start) => format!("Review comment on {}:{start}-{}", thread.path, thread.line),
+                None => format!("Review comment on {}:{}", thread.path, thread.line),
+            },
+            Draft::Reply { .. } => format!("Reply on {target}"),
+            Draft::Review(ReviewEvent::Comment) => format!("Review {target}"),
+            Draft::Review(ReviewEvent::Approve) => format!("Approve {target}"),
+            Draft::Review(ReviewEvent::RequestChanges) => {
+                format!("Request changes on {target}")
+            }
+            Draft::Merge {
+                method,
+                auto: false,
+            } => format!("{} {target}", method.name()),
+            Draft::Merge { method, auto: true } => {
+                format!("Auto-merge {target} ({})", method.name().to_lowercase())
+            }
+        }
+    }
+
+    /// Identifies the rendered body in the preview cache.
+    pub fn preview_id(&self) -> String {
+        format!("draft:{}:{}", self.path.display(), self.revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composer() {
+        let dir = tempfile::tempdir().unwrap();
+        let issue = Issue {
+            id: "I_1".into(),
+            number: 7,
+            url: "https://github.com/foo/bar/issues/7".into(),
+            ..Default::default()
+        };
+        let mut composer = Composer::new(dir.path(), &[&issue]);
+        assert_eq!(composer.path, dir.path().join("foo_bar_7.md"));
+        assert_eq!(composer.target(), "#7");
+        assert!(!composer.load().unwrap());
+
+        std::fs::write(&composer.path, "  \n").unwrap();
+        assert!(!composer.load().unwrap());
+
+        std::fs::write(&composer.path, "LGTM\n").unwrap();
+        assert!(composer.load().unwrap());
+        assert_eq!(composer.body, "LGTM\n");
+        assert_eq!(composer.revision, 3);
+
+        composer.discard();
+        assert!(!composer.path.exists());
+        composer.discard();
+
+        let other = Issue {
+            id: "I_2".into(),
+            number: 9,
+            ..issue.clone()
+        };
+        let composer = Composer::new(dir.path(), &[&issue, &other]);
+        assert_eq!(composer.path, dir.path().join("foo_bar_7_and_1_more.md"));
+        assert_eq!(composer.target(), "#7 and 1 more");
+        assert_eq!(composer.subjects[1].id, "I_2");
+
+        let thread = NewThread {
+            path: "src/main.rs".into(),
+            line: 12,
+            start_line: Some(10),
+            ..Default::default()
+        };
+        let composer = Composer::review(dir.path(), &issue, Draft::Thread(thread));
+        assert_eq!(
+            composer.path,
+            dir.path().join("foo_bar_7_src_main_rs_12.md")
+        );
+        assert_eq!(composer.title(), "Review comment on src/main.rs:10-12");
+        assert!(!composer.may_be_empty());
+        let composer = Composer::review(dir.path(), &issue, Draft::Review(ReviewEvent::Approve));
+        assert_eq!(composer.path, dir.path().join("foo_bar_7_review.md"));
+        assert_eq!(composer.title(), "Approve #7");
+        assert!(composer.may_be_empty());
+        let draft = Draft::Merge {
+            method: MergeMethod::Squash,
+            auto: true,
+        };
+        let composer = Composer::review(dir.path(), &issue, draft);
+        assert_eq!(composer.path, dir.path().join("foo_bar_7_merge.md"));
+        assert_eq!(composer.title(), "Auto-merge #7 (squash and merge)");
+    }
+}
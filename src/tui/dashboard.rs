@@ -0,0 +1,589 @@
+use super::issues::{cell, conversation, header, SPINNER, TICK_RATE};
+use super::preview::{self, HighlightCache, Highlighter};
+use crate::config::Config;
+use crate::github::{
+    issues::{self, Issue, RateBudget},
+    Github,
+};
+use crate::{clipboard, open, Column, DashboardArgs, QueryArgs};
+use anyhow::{Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::{Stream, StreamExt as _};
+use ratatui::{
+    layout::{Constraint, Layout, Rect, Size},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{collections::HashSet, sync::Arc};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::Interval,
+};
+
+// issues requested at a time for each section
+const PAGE_SIZE: u32 = 25;
+// pages fetched for each section, after which its count is shown as e.g. "100+"
+const MAX_PAGES: usize = 4;
+
+/// Everything that can change the state of the dashboard.
+#[derive(Debug)]
+pub enum Message {
+    Key(KeyEvent),
+    Resize(Size),
+    Tick,
+    // more results for section `section`
+    Results {
+        section: usize,
+        issues: Vec<Issue>,
+    },
+    // the search for a section ended, with more results left if it stopped at MAX_PAGES,
+    // or with why it failed
+    Done {
+        section: usize,
+        more: bool,
+        error: Option<String>,
+    },
+    Highlighted((String, u16)),
+    // text was copied to the clipboard
+    Copied(String, Result<()>),
+}
+
+// A query's results under a heading that can be collapsed.
+struct Section {
+    name: String,
+    queries: Vec<String>,
+    issues: Vec<Issue>,
+    seen: HashSet<String>,
+    loading: bool,
+    more: bool,
+    // why the search failed, shown in the heading
+    error: Option<String>,
+    collapsed: bool,
+}
+
+// A line of the list: a section's heading, or one of its issues.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Entry {
+    Section(usize),
+    Issue(usize, usize),
+}
+
+/// Runs several searches at once and lists their results in collapsible sections.
+pub struct Dashboard {
+    github: Github,
+    sections: Vec<Section>,
+    // shared by the sections' searches
+    budget: RateBudget,
+    list_state: ListState,
+    exit: bool,
+    dirty: bool,
+    area: Rect,
+    highlighter: Arc<Highlighter>,
+    previews: HighlightCache<(String, u16), Text<'static>>, // (url, width)->preview
+    tasks_tx: UnboundedSender<Message>,
+    tasks_rx: UnboundedReceiver<Message>,
+    // the result of the last action, shown in the status line until the next key
+    notice: Option<Line<'static>>,
+    spinner: usize,
+    output: Vec<String>,
+}
+
+impl Dashboard {
+    pub fn new(github: Github, config: &Config, args: &DashboardArgs) -> Result<Self> {
+        let sections = config
+            .dashboard()
+            .into_iter()
+            .map(|section| {
+                let query = QueryArgs {
+                    query: section.query,
+                    repo: args.repo.clone(),
+                    org: args.org.clone(),
+                    ..Default::default()
+                };
+                let resolved = config.resolve(&query);
+                Section {
+                    name: section.name,
+                    queries: resolved.to_queries(),
+                    issues: Vec::new(),
+                    seen: HashSet::new(),
+                    loading: true,
+                    more: false,
+                    error: None,
+                    collapsed: false,
+                }
+            })
+            .collect();
+        let (tasks_tx, tasks_rx) = mpsc::unbounded_channel();
+        let dashboard = Self {
+            github,
+            sections,
+            budget: RateBudget::default(),
+            list_state: ListState::default().with_selected(Some(0)),
+            exit: false,
+            dirty: true,
+            area: Rect::default(),
+            highlighter: Arc::new(Highlighter::default()),
+            previews: HighlightCache::default(),
+            tasks_tx,
+            tasks_rx,
+            notice: None,
+            spinner: 0,
+            output: Vec::new(),
+        };
+        for section in 0..dashboard.sections.len() {
+            dashboard.search(section);
+        }
+        Ok(dashboard)
+    }
+
+    pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let mut events = EventStream::new();
+        let mut ticks = tokio::time::interval(TICK_RATE);
+        self.update(Message::Resize(terminal.size()?));
+        while !self.exit {
+            if self.dirty {
+                terminal.draw(|frame| self.view(frame))?;
+                self.dirty = false;
+            }
+            let msg = self.next_message(&mut events, &mut ticks).await?;
+            self.update(msg);
+        }
+        Ok(())
+    }
+
+    /// Lines to print after the terminal is restored, such as the url of the picked issue.
+    pub fn output(&self) -> &[String] {
+        &self.output
+    }
+
+    // Search for a section's issues a page at a time, up to MAX_PAGES.
+    fn search(&self, section: usize) {
+        let (req_tx, req_rx) = mpsc::channel(1);
+        let (resp_tx, mut resp_rx) = mpsc::channel(1);
+        let search = issues::search_issues(
+            self.github.clone(),
+            self.sections[section].queries.clone(),
+            self.budget.clone(),
+            req_rx,
+            resp_tx,
+        );
+        let tx = self.tasks_tx.clone();
+        tokio::spawn(async move {
+            for _ in 0..MAX_PAGES {
+                // the search ends once it runs out of results or fails
+                let issues = match req_tx.send(PAGE_SIZE).await {
+                    Ok(()) => resp_rx.recv().await,
+                    Err(_) => None,
+                };
+                let Some(issues) = issues else {
+                    let result = search.await.context("Searching").and_then(|r| r);
+                    let _ = tx.send(Message::Done {
+                        section,
+                        more: false,
+                        error: result.err().map(|err| format!("{err:#}")),
+                    });
+                    return;
+                };
+                let _ = tx.send(Message::Results { section, issues });
+            }
+            let _ = tx.send(Message::Done {
+                section,
+                more: true,
+                error: None,
+            });
+        });
+    }
+
+    /// Wait for the next thing that might change the state of the dashboard.
+    async fn next_message(
+        &mut self,
+        events: &mut (impl Stream<Item = std::io::Result<Event>> + Unpin),
+        ticks: &mut Interval,
+    ) -> Result<Message> {
+        loop {
+            let msg = tokio::select! {
+                event = events.next() => match event.context("Event stream closed")?? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Message::Key(key),
+                    Event::Resize(width, height) => Message::Resize(Size::new(width, height)),
+                    _ => continue,
+                },
+                _ = ticks.tick() => Message::Tick,
+                Some(key) = self.previews.recv() => Message::Highlighted(key),
+                Some(msg) = self.tasks_rx.recv() => msg,
+            };
+            return Ok(msg);
+        }
+    }
+
+    /// Apply a message to the dashboard state.
+    pub fn update(&mut self, msg: Message) {
+        match msg {
+            Message::Key(key_event) => {
+                self.notice = None;
+                self.handle_key_event(key_event);
+                self.dirty = true;
+            }
+            Message::Resize(size) => {
+                self.area = Rect::new(0, 0, size.width, size.height);
+                self.dirty = true;
+            }
+            Message::Tick => {
+                self.spinner = (self.spinner + 1) % SPINNER.len();
+                self.dirty |= self.sections.iter().any(|s| s.loading);
+            }
+            Message::Results { section, issues } => {
+                // the lines below the new issues move down, along with the selected one
+                let selected = self.selected_entry();
+                let section = &mut self.sections[section];
+                let new = issues
+                    .into_iter()
+                    .filter(|i| section.seen.insert(i.url.clone()));
+                section.issues.extend(new);
+                self.select(selected);
+                self.dirty = true;
+            }
+            Message::Done {
+                section,
+                more,
+                error,
+            } => {
+                let section = &mut self.sections[section];
+                section.loading = false;
+                section.more = more;
+                section.error = error;
+                self.dirty = true;
+            }
+            Message::Highlighted(key) => {
+                self.dirty |= self.preview_key().is_some_and(|k| k == key);
+            }
+            Message::Copied(url, result) => {
+                self.notice = Some(match result {
+                    Ok(()) => Line::from(format!("Copied {url}")).dim(),
+                    Err(err) => {
+                        tracing::error!("Failed to copy {url}: {err:?}");
+                        Line::from(format!("Failed to copy: {err}")).red()
+                    }
+                });
+                self.dirty = true;
+            }
+        }
+        self.request_preview();
+    }
+
+    // The lines of the list, skipping the issues of collapsed sections.
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for (index, section) in self.sections.iter().enumerate() {
+            entries.push(Entry::Section(index));
+            if !section.collapsed {
+                entries.extend((0..section.issues.len()).map(|i| Entry::Issue(index, i)));
+            }
+        }
+        entries
+    }
+
+    fn selected_entry(&self) -> Option<Entry> {
+        self.entries().get(self.list_state.selected()?).copied()
+    }
+
+    fn selected_issue(&self) -> Option<&Issue> {
+        match self.selected_entry()? {
+            Entry::Issue(section, index) => self.sections[section].issues.get(index),
+            Entry::Section(_) => None,
+        }
+    }
+
+    // Select the line of `entry`, wherever the lines before it have moved it.
+    fn select(&mut self, entry: Option<Entry>) {
+        let index = entry.and_then(|entry| self.entries().iter().position(|&e| e == entry));
+        if index.is_some() {
+            self.list_state.select(index);
+        }
+    }
+
+    // Collapse or expand a section, keeping its heading selected.
+    fn toggle(&mut self, section: usize) {
+        self.sections[section].collapsed ^= true;
+        self.select(Some(Entry::Section(section)));
+    }
+
+    fn select_next(&mut self) {
+        let last = self.entries().len().saturating_sub(1);
+        let next = self.list_state.selected().map_or(0, |i| (i + 1).min(last));
+        self.list_state.select(Some(next));
+    }
+
+    fn preview_area(&self) -> Rect {
+        let [_, preview] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(self.area);
+        preview
+    }
+
+    fn preview_key(&self) -> Option<(String, u16)> {
+        let width = Block::bordered().inner(self.preview_area()).width;
+        Some((self.selected_issue()?.url.clone(), width))
+    }
+
+    // Highlight the selected issue's body.
+    fn request_preview(&mut self) {
+        let Some(key) = self.preview_key() else {
+            return;
+        };
+        if self.previews.contains(&key) {
+            return;
+        }
+        let Some(issue) = self.selected_issue().cloned() else {
+            return;
+        };
+        let (highlighter, width) = (self.highlighter.clone(), key.1);
+        self.previews.spawn(key, move || {
+            let mut text = Text::from(vec![header(&issue), Line::default()]);
+            text.extend(highlighter.markdown(&conversation(&issue, None))?);
+            Ok(preview::wrap(text, width))
+        });
+    }
+
+    /// Render the dashboard state.
+    pub fn view(&mut self, frame: &mut Frame) {
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(50); 2]).areas(frame.area());
+        let lines = self.entries().into_iter().map(|entry| match entry {
+            Entry::Section(index) => self.heading(index),
+            Entry::Issue(section, index) => issue_line(&self.sections[section].issues[index]),
+        });
+        let status = self
+            .notice
+            .clone()
+            .unwrap_or_else(|| Line::from("space: collapse · p: print · o: open · y: copy").dim());
+        let list = List::new(lines)
+            .block(Block::bordered().title("Dashboard").title_bottom(status))
+            .highlight_style(Style::new().bold().reversed())
+            .highlight_symbol(">");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let Some(issue) = self.selected_issue() else {
+            frame.render_widget(Block::bordered(), preview_area);
+            return;
+        };
+        let block = Block::bordered().title(format!("#{} {}", issue.number, issue.title));
+        let preview = match self.preview_key().and_then(|key| self.previews.get(&key)) {
+            Some(text) => Paragraph::new(text.clone()),
+            None => Paragraph::new(Line::from("Loading…").dim()),
+        };
+        frame.render_widget(preview.block(block), preview_area);
+    }
+
+    // A section's name with its count, e.g. "▾ Assigned to me (12)", and why its search failed.
+    fn heading(&self, index: usize) -> Line<'static> {
+        let section = &self.sections[index];
+        let arrow = if section.collapsed { "▸" } else { "▾" };
+        let count = match (section.loading, section.more) {
+            (true, _) => format!("{} {}", SPINNER[self.spinner], section.issues.len()),
+            (false, true) => format!("{}+", section.issues.len()),
+            (false, false) => section.issues.len().to_string(),
+        };
+        let error = section
+            .error
+            .as_ref()
+            .map(|err| Span::raw(format!(" {err}")).red());
+        Line::from_iter(
+            [
+                Span::raw(format!("{arrow} {} ", section.name)).bold(),
+                Span::raw(format!("({count})")).dim(),
+            ]
+            .into_iter()
+            .chain(error),
+        )
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let url = self.selected_issue().map(|i| i.url.clone());
+        match key_event.code {
+            KeyCode::Esc => self.exit = true,
+            KeyCode::Char('c') if ctrl => self.exit = true,
+            KeyCode::Char('k') => self.list_state.select_previous(),
+            KeyCode::Char('j') => self.select_next(),
+            // Enter picks issues and collapses or expands sections
+            KeyCode::Char(' ') | KeyCode::Enter
+                if let Some(Entry::Section(section)) = self.selected_entry() =>
+            {
+                self.toggle(section)
+            }
+            KeyCode::Char('p') | KeyCode::Enter if url.is_some() => {
+                self.output.extend(url);
+                self.exit = true;
+            }
+            KeyCode::Char('y') => {
+                let Some(url) = url else {
+                    return;
+                };
+                let copying = clipboard::copy(&url);
+                let tx = self.tasks_tx.clone();
+                tokio::spawn(async move {
+                    let result = match copying {
+                        Ok(copying) => copying.await,
+                        Err(err) => Err(err),
+                    };
+                    let _ = tx.send(Message::Copied(url, result));
+                });
+            }
+            KeyCode::Char('o') => {
+                let Some(url) = url else {
+                    return;
+                };
+                if let Err(err) = open::open_url(&url) {
+                    tracing::error!("Failed to open {url}: {err:?}");
+                    self.notice = Some(Line::from(format!("Failed to open {url}: {err}")).red());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// An issue under its section's heading, e.g. "  ✓ octocat/Hello-World#12 Fix the greeting".
+fn issue_line(issue: &Issue) -> Line<'static> {
+    let repo = issue.repo().unwrap_or_default();
+    Line::from(vec![
+        Span::raw("  "),
+        cell(issue, Column::Checks),
+        Span::raw(" "),
+        Span::raw(format!("{repo}#{} ", issue.number)).dim(),
+        cell(issue, Column::Title),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DashboardSection;
+    use mockito::{Matcher, Server};
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::time::Duration;
+
+    fn render(dashboard: &mut Dashboard) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| dashboard.view(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(80)
+            .map(|line| line.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_dashboard() {
+        let mut server = Server::new_async().await;
+        let mine = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::Regex("assignee:@me".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let empty = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::Regex("review-requested:@me".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"data":{"search":{"nodes":[],"issueCount":0,"pageInfo":{"endCursor":"","hasNextPage":false}},
+                "rateLimit":{"cost":1,"limit":5000,"remaining":4991,"resetAt":"2025-06-26T23:06:12Z","used":9}}}"#,
+            )
+            .create_async()
+            .await;
+        let broken = server
+            .mock("POST", "/graphql")
+            .match_body(Matcher::Regex("label:broken".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"errors":[{"message":"bad query"}]}"#)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let config = Config {
+            dashboard: vec![
+                DashboardSection {
+                    name: "Mine".into(),
+                    query: "is:open assignee:@me".into(),
+                },
+                DashboardSection {
+                    name: "Reviews".into(),
+                    query: "is:open review-requested:@me".into(),
+                },
+                DashboardSection {
+                    name: "Broken".into(),
+                    query: "label:broken".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        let mut dashboard = Dashboard::new(github, &config, &DashboardArgs::default()).unwrap();
+        dashboard.update(Message::Resize(Size::new(80, 12)));
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while dashboard.sections.iter().any(|s| s.loading) {
+            let msg = dashboard
+                .next_message(&mut events, &mut ticks)
+                .await
+                .unwrap();
+            dashboard.update(msg);
+        }
+        mine.assert_async().await;
+        empty.assert_async().await;
+        broken.assert_async().await;
+        let screen = render(&mut dashboard);
+        assert!(screen.contains("▾ Mine (2)"), "{screen}");
+        assert!(screen.contains("Hello-World#2356 Terraform"), "{screen}");
+        assert!(screen.contains("▾ Reviews (0)"), "{screen}");
+        assert!(
+            screen.contains("▾ Broken (0) Issue search failed"),
+            "{screen}"
+        );
+
+        // results arriving above the selection don't move it
+        for _ in 0..3 {
+            dashboard.update(Message::Key(KeyCode::Char('j').into()));
+        }
+        assert_eq!(dashboard.selected_entry(), Some(Entry::Section(1)));
+        let mut issue = dashboard.sections[0].issues[0].clone();
+        issue.url.push_str("#more");
+        dashboard.update(Message::Results {
+            section: 0,
+            issues: vec![issue],
+        });
+        assert_eq!(dashboard.selected_entry(), Some(Entry::Section(1)));
+        for _ in 0..4 {
+            dashboard.update(Message::Key(KeyCode::Char('k').into()));
+        }
+
+        dashboard.update(Message::Key(KeyCode::Char(' ').into()));
+        assert_eq!(
+            dashboard.entries(),
+            [Entry::Section(0), Entry::Section(1), Entry::Section(2)]
+        );
+        let screen = render(&mut dashboard);
+        assert!(screen.contains("▸ Mine (3)"), "{screen}");
+        assert!(!screen.contains("Terraform"), "{screen}");
+
+        for key in [KeyCode::Enter, KeyCode::Char('j'), KeyCode::Char('j')] {
+            dashboard.update(Message::Key(key.into()));
+        }
+        assert_eq!(dashboard.selected_entry(), Some(Entry::Issue(0, 1)));
+        dashboard.update(Message::Key(KeyCode::Char('p').into()));
+        assert_eq!(
+            dashboard.output(),
+            ["https://github.com/octocat/Hello-World/pull/2648"]
+        );
+    }
+}
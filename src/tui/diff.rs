@@ -0,0 +1,500 @@
+use ratatui::{
+    style::{Style, Stylize as _},
+    text::{Line, Span},
+};
+
+use super::preview::{fit, HighlightedDiff};
+use crate::github::{
+    pulls::PullFile,
+    review::{NewThread, Side, Thread},
+};
+
+/// One line of a unified diff hunk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffLine<'a> {
+    // "@@ -1,4 +1,5 @@ fn main() {"
+    Hunk(&'a str),
+    Context { old: u32, new: u32, text: &'a str },
+    Removed { old: u32, text: &'a str },
+    Added { new: u32, text: &'a str },
+    // "\ No newline at end of file" and anything else that isn't part of the content
+    Note(&'a str),
+}
+
+/// Parse a patch as GitHub returns it: hunks without the file headers.
+pub fn parse(patch: &str) -> Vec<DiffLine<'_>> {
+    let (mut old, mut new) = (0, 0);
+    patch
+        .lines()
+        .map(|line| match line.split_at_checked(1) {
+            Some(("@", _)) => {
+                if let Some((o, n)) = hunk_start(line) {
+                    (old, new) = (o, n);
+                }
+                DiffLine::Hunk(line)
+            }
+            Some((" ", text)) => {
+                old += 1;
+                new += 1;
+                DiffLine::Context {
+                    old: old - 1,
+                    new: new - 1,
+                    text,
+                }
+            }
+            Some(("-", text)) => {
+                old += 1;
+                DiffLine::Removed { old: old - 1, text }
+            }
+            Some(("+", text)) => {
+                new += 1;
+                DiffLine::Added { new: new - 1, text }
+            }
+            // an empty context line, when trailing whitespace was stripped
+            None => {
+                old += 1;
+                new += 1;
+                DiffLine::Context {
+                    old: old - 1,
+                    new: new - 1,
+                    text: "",
+                }
+            }
+            Some(_) => DiffLine::Note(line),
+        })
+        .collect()
+}
+
+// The first old and new line numbers of a hunk header, e.g. (1, 1) for "@@ -1,4 +1,5 @@".
+fn hunk_start(header: &str) -> Option<(u32, u32)> {
+    let mut ranges = header.strip_prefix("@@ ")?.split(' ');
+    let start = |range: Option<&str>, sign| -> Option<u32> {
+        let range = range?.strip_prefix(sign)?;
+        range.split(',').next()?.parse().ok()
+    };
+    Some((start(ranges.next(), '-')?, start(ranges.next(), '+')?))
+}
+
+/// A row of a side-by-side diff, as indices of the lines it shows.
+/// Removed lines are paired with the lines added in their place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitRow {
+    Full(usize),
+    Pair(Option<usize>, Option<usize>),
+}
+
+pub fn split(lines: &[DiffLine<'_>]) -> Vec<SplitRow> {
+    let is_removed = |i: &usize| matches!(lines[*i], DiffLine::Removed { .. });
+    let is_added = |i: &usize| matches!(lines[*i], DiffLine::Added { .. });
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        match lines[i] {
+            DiffLine::Removed { .. } | DiffLine::Added { .. } => {
+                let removed: Vec<_> = (i..lines.len()).take_while(is_removed).collect();
+                let added: Vec<_> = (i + removed.len()..lines.len())
+                    .take_while(is_added)
+                    .collect();
+                i += removed.len() + added.len();
+                for row in 0..removed.len().max(added.len()) {
+                    rows.push(SplitRow::Pair(
+                        removed.get(row).copied(),
+                        added.get(row).copied(),
+                    ));
+                }
+            }
+            DiffLine::Context { .. } => {
+                rows.push(SplitRow::Pair(Some(i), Some(i)));
+                i += 1;
+            }
+            _ => {
+                rows.push(SplitRow::Full(i));
+                i += 1;
+            }
+        }
+    }
+    rows
+}
+
+/// The side and line number a line of a diff is commented on by,
+/// the new side unless the line was removed.
+pub fn position(line: DiffLine) -> Option<(Side, u32)> {
+    match line {
+        DiffLine::Context { new, .. } | DiffLine::Added { new, .. } => Some((Side::Right, new)),
+        DiffLine::Removed { old, .. } => Some((Side::Left, old)),
+        DiffLine::Hunk(_) | DiffLine::Note(_) => None,
+    }
+}
+
+/// Find the index of the line a review thread is on.
+pub fn find_line(lines: &[DiffLine], side: Side, number: u32) -> Option<usize> {
+    lines.iter().position(|&line| match (line, side) {
+        (DiffLine::Context { old, .. } | DiffLine::Removed { old, .. }, Side::Left) => {
+            old == number
+        }
+        (DiffLine::Context { new, .. } | DiffLine::Added { new, .. }, Side::Right) => new == number,
+        _ => false,
+    })
+}
+
+/// Where a comment on the lines from `start` to `end` of a file goes.
+/// Returns None if either end isn't a line that can be commented on.
+pub fn new_thread(path: &str, lines: &[DiffLine], start: usize, end: usize) -> Option<NewThread> {
+    let (start, end) = (start.min(end), start.max(end));
+    let (side, line) = position(*lines.get(end)?)?;
+    let (start_side, start_line) = position(*lines.get(start)?)?;
+    let range = start != end;
+    Some(NewThread {
+        path: path.to_string(),
+        line,
+        side,
+        start_line: range.then_some(start_line),
+        start_side: range.then_some(start_side),
+    })
+}
+
+/// Which file of a pull request's diff is shown, and where in it the cursor is.
+#[derive(Debug, Default)]
+pub struct DiffView {
+    // the pull request the position belongs to
+    pub url: String,
+    pub file: usize,
+    // the first line shown, counting the threads between the rows of the diff
+    pub scroll: u16,
+    // whether keys move the cursor rather than the selection of the list
+    pub focused: bool,
+    // the row of the diff the cursor is on
+    pub cursor: usize,
+    // where a range of rows being selected for a comment started
+    pub anchor: Option<usize>,
+}
+
+impl DiffView {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn select_file(&mut self, file: usize) {
+        if file != self.file {
+            self.file = file;
+            self.scroll = 0;
+            self.cursor = 0;
+            self.anchor = None;
+        }
+    }
+
+    // The rows from the anchor to the cursor, or just the cursor's.
+    fn selection(&self) -> std::ops::RangeInclusive<usize> {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        anchor.min(self.cursor)..=anchor.max(self.cursor)
+    }
+
+    /// The first and last lines of the patch selected, for a new comment.
+    pub fn selected_lines(&self, diff: &HighlightedDiff) -> Option<(usize, usize)> {
+        let selection = self.selection();
+        let start = *diff.rows.get(*selection.start())?.last()?;
+        let end = *diff.rows.get(*selection.end())?.last()?;
+        Some((start, end))
+    }
+}
+
+/// Lay out a highlighted diff for display, with the cursor and selection shown if focused,
+/// and each review thread after the row its last line is on.
+/// Returns the lines, and the index of each row of the diff in them.
+pub fn layout(
+    diff: &HighlightedDiff,
+    lines: &[DiffLine],
+    threads: &[&Thread],
+    view: &DiffView,
+    width: u16,
+) -> (Vec<Line<'static>>, Vec<usize>) {
+    let anchored: Vec<(usize, &Thread)> = threads
+        .iter()
+        .filter_map(|&t| Some((find_line(lines, t.diff_side, t.line?)?, t)))
+        .collect();
+    let selection = view.selection();
+    let mut out = Vec::new();
+    let mut positions = Vec::new();
+    for (row, line) in diff.text.lines.iter().enumerate() {
+        positions.push(out.len());
+        let mut line = line.clone();
+        if view.focused && selection.contains(&row) {
+            if let Some(gutter) = line.spans.first_mut() {
+                gutter.style = gutter.style.reversed();
+            }
+        }
+        out.push(line);
+
+        let shown = diff.rows.get(row).map(Vec::as_slice).unwrap_or_default();
+        let next = diff
+            .rows
+            .get(row + 1)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (_, thread) in anchored
+            .iter()
+            .filter(|(i, _)| shown.contains(i) && !next.contains(i))
+        {
+            out.extend(thread_lines(thread, width));
+        }
+    }
+    (out, positions)
+}
+
+/// The threads on the given rows of a diff.
+pub fn threads_on<'a>(
+    diff: &HighlightedDiff,
+    lines: &[DiffLine],
+    threads: &[&'a Thread],
+    row: usize,
+) -> Vec<&'a Thread> {
+    let shown = diff.rows.get(row).map(Vec::as_slice).unwrap_or_default();
+    threads
+        .iter()
+        .filter(|t| {
+            t.line
+                .and_then(|line| find_line(lines, t.diff_side, line))
+                .is_some_and(|i| shown.contains(&i))
+        })
+        .copied()
+        .collect()
+}
+
+// A thread's comments in a box drawn under the line they're on, or just the first if resolved.
+fn thread_lines(thread: &Thread, width: u16) -> Vec<Line<'static>> {
+    let width = width as usize;
+    let border = |resolved: bool| {
+        let border = Span::raw(" ┃ ");
+        if resolved {
+            border.dim()
+        } else {
+            border.yellow()
+        }
+    };
+    let comments = &thread.comments.nodes;
+    let mut out = Vec::new();
+    for comment in comments {
+        let author = comment
+            .author
+            .as_ref()
+            .map(|a| a.login.as_str())
+            .unwrap_or("ghost");
+        let mut body = comment.body.lines();
+        let first = body.next().unwrap_or_default();
+        if thread.is_resolved {
+            let more = match comments.len() {
+                1 => String::new(),
+                n => format!(" ({} more)", n - 1),
+            };
+            let line = Line::from(vec![
+                border(true),
+                Span::raw(format!("✓ {author}: {first}{more}")).dim(),
+            ]);
+            out.push(fit(line, width, None));
+            break;
+        }
+        let line = Line::from(vec![
+            border(false),
+            Span::raw(format!("{author}: ")).bold(),
+            Span::raw(first.to_string()),
+        ]);
+        out.push(fit(line, width, None));
+        for text in body {
+            let line = Line::from(vec![border(false), Span::raw(format!("  {text}"))]);
+            out.push(fit(line, width, None));
+        }
+    }
+    out
+}
+
+/// The list of changed files, e.g. "M src/main.rs +2 -1", with the shown file highlighted.
+pub fn file_list(files: &[PullFile], selected: usize) -> Vec<Line<'static>> {
+    files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let name = match &file.previous_filename {
+                Some(previous) => format!("{previous} → {}", file.filename),
+                None => file.filename.clone(),
+            };
+            let line = Line::from(vec![
+                Span::raw(format!("{} ", file.status_letter())),
+                Span::raw(name),
+                Span::raw(format!(" +{}", file.additions)).green(),
+                Span::raw(format!(" -{}", file.deletions)).red(),
+            ]);
+            if i == selected {
+                line.style(Style::new().reversed())
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let patch = "@@ -1,4 +1,5 @@ fn main()\n fn main() {\n-    a();\n+    b();\n+    c();\n\n }\n\\ No newline at end of file";
+        let lines = parse(patch);
+        assert_eq!(
+            lines,
+            vec![
+                DiffLine::Hunk("@@ -1,4 +1,5 @@ fn main()"),
+                DiffLine::Context {
+                    old: 1,
+                    new: 1,
+                    text: "fn main() {"
+                },
+                DiffLine::Removed {
+                    old: 2,
+                    text: "    a();"
+                },
+                DiffLine::Added {
+                    new: 2,
+                    text: "    b();"
+                },
+                DiffLine::Added {
+                    new: 3,
+                    text: "    c();"
+                },
+                DiffLine::Context {
+                    old: 3,
+                    new: 4,
+                    text: ""
+                },
+                DiffLine::Context {
+                    old: 4,
+                    new: 5,
+                    text: "}"
+                },
+                DiffLine::Note("\\ No newline at end of file"),
+            ]
+        );
+
+        let rows = split(&lines);
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[0], SplitRow::Full(0));
+        assert_eq!(rows[2], SplitRow::Pair(Some(2), Some(3)));
+        assert_eq!(rows[3], SplitRow::Pair(None, Some(4)));
+        assert_eq!(rows[4], SplitRow::Pair(Some(5), Some(5)));
+
+        assert_eq!(find_line(&lines, Side::Left, 2), Some(2));
+        assert_eq!(find_line(&lines, Side::Right, 4), Some(5));
+        assert_eq!(find_line(&lines, Side::Left, 9), None);
+        assert_eq!(
+            new_thread("main.rs", &lines, 4, 2),
+            Some(NewThread {
+                path: "main.rs".into(),
+                line: 3,
+                side: Side::Right,
+                start_line: Some(2),
+                start_side: Some(Side::Left),
+            })
+        );
+        assert_eq!(new_thread("main.rs", &lines, 0, 1), None);
+
+        assert_eq!(hunk_start("@@ -10 +12,0 @@"), Some((10, 12)));
+        assert_eq!(hunk_start("@@ nonsense"), None);
+    }
+
+    #[test]
+    fn test_layout() {
+        use crate::github::{issues::User, repo::Nodes, review::ReviewComment};
+
+        let patch = "@@ -1,2 +1,2 @@\n-a\n+b\n c";
+        let lines = parse(patch);
+        let diff = HighlightedDiff {
+            text: ["@@", "-a", "+b", " c"]
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            rows: vec![vec![0], vec![1], vec![2], vec![3]],
+        };
+        let comment = |login: &str, body: &str| ReviewComment {
+            author: Some(User {
+                login: login.into(),
+            }),
+            body: body.into(),
+        };
+        let open = Thread {
+            id: "T_1".into(),
+            path: "main.rs".into(),
+            line: Some(1),
+            diff_side: Side::Left,
+            comments: Nodes {
+                nodes: vec![
+                    comment("monalisa", "Why?"),
+                    comment("octocat", "Because\nreasons"),
+                ],
+            },
+            ..Default::default()
+        };
+        let resolved = Thread {
+            id: "T_2".into(),
+            is_resolved: true,
+            line: Some(1),
+            diff_side: Side::Right,
+            comments: Nodes {
+                nodes: vec![comment("monalisa", "Typo"), comment("octocat", "Fixed")],
+            },
+            ..open.clone()
+        };
+        let outdated = Thread {
+            line: None,
+            ..open.clone()
+        };
+        let threads = [&open, &resolved, &outdated];
+        let mut view = DiffView {
+            focused: true,
+            cursor: 2,
+            anchor: Some(1),
+            ..Default::default()
+        };
+        let (out, positions) = layout(&diff, &lines, &threads, &view, 30);
+        let plain: Vec<String> = out
+            .iter()
+            .map(|l| {
+                l.spans
+                    .iter()
+                    .map(|s| s.content.as_ref())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            plain,
+            vec![
+                "@@",
+                "-a",
+                " ┃ monalisa: Why?",
+                " ┃ octocat: Because",
+                " ┃   reasons",
+                "+b",
+                " ┃ ✓ monalisa: Typo (1 more)",
+                " c",
+            ]
+        );
+        assert_eq!(positions, vec![0, 1, 5, 7]);
+        assert!(out[1].spans[0]
+            .style
+            .add_modifier
+            .contains(ratatui::style::Modifier::REVERSED));
+        assert!(!out[7].spans[0]
+            .style
+            .add_modifier
+            .contains(ratatui::style::Modifier::REVERSED));
+
+        assert_eq!(view.selected_lines(&diff), Some((1, 2)));
+        assert_eq!(threads_on(&diff, &lines, &threads, 2), vec![&resolved]);
+        view.select_file(1);
+        assert_eq!((view.cursor, view.anchor), (0, None));
+    }
+}
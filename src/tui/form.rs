@@ -0,0 +1,615 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Style, Stylize as _},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use super::input::LineInput;
+use super::menu::{Menu, MenuEvent, MenuItem};
+use crate::github::issues::Issue;
+use crate::github::mutation::NewIssue;
+use crate::github::repo::RepoMetadata;
+use crate::github::template::{Field, Template};
+
+// Lines of markdown and textarea values shown in the form.
+const MAX_VALUE_LINES: usize = 3;
+
+/// The result of passing a key to a form.
+#[derive(Debug, PartialEq)]
+pub enum FormEvent {
+    Pending,
+    // the focused textarea should be edited in $EDITOR
+    Edit,
+    // the form is filled in
+    Submit,
+    Cancelled,
+}
+
+/// What can be focused in a form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Focus {
+    Title,
+    Field(usize),
+    Checkbox(usize, usize),
+}
+
+/// A new issue being filled in from a template.
+pub struct Form {
+    pub repo: String,
+    pub template: Template,
+    pub title: String,
+    focus: usize,
+    // the title or input field being edited
+    input: Option<LineInput>,
+    // the options of the dropdown being edited
+    menu: Option<Menu<usize>>,
+    // the textarea being edited in $EDITOR, and the file it's edited in
+    editing: Option<(usize, PathBuf)>,
+    pub duplicates: Vec<Issue>,
+    // the title duplicates were last searched for
+    pub checked_title: Option<String>,
+    // what is being waited for, such as the issue being created
+    pub busy: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Form {
+    pub fn new(repo: impl Into<String>, template: Template) -> Self {
+        Self {
+            repo: repo.into(),
+            title: template.title.clone(),
+            template,
+            focus: 0,
+            input: None,
+            menu: None,
+            editing: None,
+            duplicates: Vec::new(),
+            checked_title: None,
+            busy: None,
+            error: None,
+        }
+    }
+
+    fn focusables(&self) -> Vec<Focus> {
+        let fields = self
+            .template
+            .fields
+            .iter()
+            .enumerate()
+            .flat_map(|(i, field)| match field {
+                Field::Markdown(_) => Vec::new(),
+                Field::Checkboxes { options, .. } => {
+                    (0..options.len()).map(|j| Focus::Checkbox(i, j)).collect()
+                }
+                _ => vec![Focus::Field(i)],
+            });
+        std::iter::once(Focus::Title).chain(fields).collect()
+    }
+
+    fn focused(&self) -> Focus {
+        self.focusables()
+            .get(self.focus)
+            .copied()
+            .unwrap_or(Focus::Title)
+    }
+
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> FormEvent {
+        if let Some(menu) = &mut self.menu {
+            match menu.handle_key_event(key_event) {
+                MenuEvent::Pending => {}
+                MenuEvent::Cancelled => self.menu = None,
+                MenuEvent::Chosen(option) => {
+                    let index = menu.selected();
+                    self.menu = None;
+                    if self.choose(option) {
+                        // keep toggling options until the menu is closed
+                        self.open_menu();
+                        if let Some(menu) = &mut self.menu {
+                            menu.select(index);
+                        }
+                    }
+                }
+            }
+            return FormEvent::Pending;
+        }
+
+        if let Some(input) = &mut self.input {
+            match key_event.code {
+                KeyCode::Enter => {
+                    let value = input.pattern().to_string();
+                    self.input = None;
+                    match self.focused() {
+                        Focus::Title => self.title = value,
+                        Focus::Field(i) => {
+                            if let Some(Field::Input { value: v, .. }) =
+                                self.template.fields.get_mut(i)
+                            {
+                                *v = value;
+                            }
+                        }
+                        Focus::Checkbox(..) => {}
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                _ => input.handle_key_event(key_event),
+            }
+            return FormEvent::Pending;
+        }
+
+        if self.busy.is_some() {
+            return FormEvent::Pending;
+        }
+        self.error = None;
+
+        let last = self.focusables().len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down | KeyCode::Tab => {
+                self.focus = (self.focus + 1).min(last)
+            }
+            KeyCode::Char('k') | KeyCode::Up | KeyCode::BackTab => {
+                self.focus = self.focus.saturating_sub(1)
+            }
+            KeyCode::Enter | KeyCode::Char('e' | ' ') => return self.edit(),
+            // Ctrl-s too, as in most editors
+            KeyCode::Char('s') => return self.submit(),
+            KeyCode::Esc | KeyCode::Char('q') => return FormEvent::Cancelled,
+            _ => {}
+        }
+        FormEvent::Pending
+    }
+
+    // Start editing the focused item.
+    fn edit(&mut self) -> FormEvent {
+        match self.focused() {
+            Focus::Title => self.input = Some(LineInput::new(self.title.as_str())),
+            Focus::Field(i) => match &self.template.fields[i] {
+                Field::Input { value, .. } => self.input = Some(LineInput::new(value.as_str())),
+                Field::Textarea { .. } => return FormEvent::Edit,
+                Field::Dropdown { .. } => self.open_menu(),
+                Field::Markdown(_) | Field::Checkboxes { .. } => {}
+            },
+            Focus::Checkbox(i, j) => {
+                if let Some(Field::Checkboxes { options, .. }) = self.template.fields.get_mut(i) {
+                    options[j].checked = !options[j].checked;
+                }
+            }
+        }
+        FormEvent::Pending
+    }
+
+    fn open_menu(&mut self) {
+        let Focus::Field(i) = self.focused() else {
+            return;
+        };
+        let Some(Field::Dropdown {
+            label,
+            options,
+            selected,
+            ..
+        }) = self.template.fields.get(i)
+        else {
+            return;
+        };
+        let items = options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| MenuItem {
+                label: format!(
+                    "{}{option}",
+                    if selected.contains(&index) {
+                        "✓ "
+                    } else {
+                        "  "
+                    }
+                ),
+                description: String::new(),
+                value: index,
+            })
+            .collect();
+        self.menu = Some(Menu::new(label.as_str(), items));
+    }
+
+    // Select an option of the focused dropdown.
+    // Returns true if more options can be chosen.
+    fn choose(&mut self, option: usize) -> bool {
+        let Focus::Field(i) = self.focused() else {
+            return false;
+        };
+        let Some(Field::Dropdown {
+            multiple, selected, ..
+        }) = self.template.fields.get_mut(i)
+        else {
+            return false;
+        };
+        if !*multiple {
+            *selected = vec![option];
+            return false;
+        }
+        if let Some(pos) = selected.iter().position(|&s| s == option) {
+            selected.remove(pos);
+        } else {
+            selected.push(option);
+            selected.sort();
+        }
+        true
+    }
+
+    fn submit(&mut self) -> FormEvent {
+        let mut missing = self.template.missing();
+        if self.title.trim().is_empty() {
+            missing.insert(0, "Title".to_string());
+        }
+        if !missing.is_empty() {
+            self.error = Some(format!("Required: {}", missing.join(", ")));
+            return FormEvent::Pending;
+        }
+        FormEvent::Submit
+    }
+
+    /// Write the focused textarea to a file in `dir` to edit it.
+    pub fn start_edit(&mut self, dir: &Path) -> Result<PathBuf> {
+        let Focus::Field(i) = self.focused() else {
+            anyhow::bail!("Not editing a text area");
+        };
+        let Some(Field::Textarea { value, .. }) = self.template.fields.get(i) else {
+            anyhow::bail!("Not editing a text area");
+        };
+        let path = dir.join(format!("{}_new_{i}.md", self.repo.replace('/', "_")));
+        std::fs::write(&path, value)
+            .with_context(|| format!("Writing draft {}", path.display()))?;
+        self.editing = Some((i, path.clone()));
+        Ok(path)
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Read the textarea back once the editor exits.
+    pub fn finish_edit(&mut self, status: Result<ExitStatus>) -> Result<()> {
+        let Some((i, path)) = self.editing.take() else {
+            return Ok(());
+        };
+        let status = status?;
+        anyhow::ensure!(status.success(), "Editor exited with {status}");
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading draft {}", path.display()))?;
+        if let Some(Field::Textarea { value, .. }) = self.template.fields.get_mut(i) {
+            *value = text;
+        }
+        if let Err(err) = std::fs::remove_file(&path) {
+            tracing::warn!("Failed to remove draft {path:?}: {err}");
+        }
+        Ok(())
+    }
+
+    /// The search for open and closed issues with a similar title, if there is a title.
+    /// A prefix from the template, like "[Bug]: ", is left out since every issue from it has it.
+    pub fn duplicate_query(&self) -> Option<String> {
+        let prefix = self.template.title.trim();
+        let title = match self.title.trim().strip_prefix(prefix) {
+            Some(rest) if !prefix.is_empty() => rest,
+            _ => &self.title,
+        };
+        duplicate_query(&self.repo, title)
+    }
+
+    /// The issue to create, with the template's labels and assignees looked up in `metadata`.
+    /// Names that aren't found are left out.
+    pub fn new_issue(&self, metadata: &RepoMetadata) -> NewIssue {
+        let label_ids = self
+            .template
+            .labels
+            .iter()
+            .filter_map(|name| {
+                let label = metadata.labels.nodes.iter().find(|l| &l.name == name);
+                if label.is_none() {
+                    tracing::warn!("Label {name} not found in {}", self.repo);
+                }
+                label.map(|l| l.id.clone())
+            })
+            .collect();
+        let assignee_ids = self
+            .template
+            .assignees
+            .iter()
+            .filter_map(|login| {
+                let actor = metadata
+                    .assignable_users
+                    .nodes
+                    .iter()
+                    .find(|a| a.login.eq_ignore_ascii_case(login));
+                if actor.is_none() {
+                    tracing::warn!("{login} can't be assigned in {}", self.repo);
+                }
+                actor.map(|a| a.id.clone())
+            })
+            .collect();
+        NewIssue {
+            repository_id: metadata.id.clone(),
+            title: self.title.trim().to_string(),
+            body: self.template.body(),
+            label_ids,
+            assignee_ids,
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut focus_line;
+        let focused = self.focused();
+        let highlight = |line: Line<'static>, focus: Focus| {
+            if focus == focused {
+                line.patch_style(Style::new().reversed())
+            } else {
+                line
+            }
+        };
+        let value_line = |value: &str, placeholder: &str| {
+            if value.is_empty() {
+                Line::from(format!("  {placeholder}")).dim()
+            } else {
+                Line::from(format!("  {value}"))
+            }
+        };
+
+        lines.push(Line::from("Title").bold());
+        focus_line = lines.len();
+        lines.push(highlight(value_line(&self.title, ""), Focus::Title));
+
+        for (i, field) in self.template.fields.iter().enumerate() {
+            lines.push(Line::default());
+            if let Field::Markdown(text) = field {
+                lines.extend(
+                    text.lines()
+                        .take(MAX_VALUE_LINES)
+                        .map(|l| Line::from(l.to_string()).dim()),
+                );
+                continue;
+            }
+            let required = match field {
+                Field::Input { required, .. }
+                | Field::Textarea { required, .. }
+                | Field::Dropdown { required, .. } => *required,
+                _ => false,
+            };
+            let mut label = vec![Span::from(field.label().to_string()).bold()];
+            if required {
+                label.push(Span::from(" *").red());
+            }
+            lines.push(Line::from(label));
+            if !field.description().is_empty() {
+                lines.push(Line::from(field.description().to_string()).dim());
+            }
+            match field {
+                Field::Input {
+                    value, placeholder, ..
+                } => {
+                    if focused == Focus::Field(i) {
+                        focus_line = lines.len();
+                    }
+                    lines.push(highlight(value_line(value, placeholder), Focus::Field(i)));
+                }
+                Field::Textarea {
+                    value, placeholder, ..
+                } => {
+                    if focused == Focus::Field(i) {
+                        focus_line = lines.len();
+                    }
+                    let mut value_lines = value.lines();
+                    let first = value_lines.next().unwrap_or_default();
+                    lines.push(highlight(value_line(first, placeholder), Focus::Field(i)));
+                    lines.extend(
+                        value_lines
+                            .take(MAX_VALUE_LINES - 1)
+                            .map(|l| Line::from(format!("  {l}"))),
+                    );
+                }
+                Field::Dropdown {
+                    options, selected, ..
+                } => {
+                    if focused == Focus::Field(i) {
+                        focus_line = lines.len();
+                    }
+                    let chosen = selected
+                        .iter()
+                        .filter_map(|&s| options.get(s))
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let line = if chosen.is_empty() {
+                        Line::from("  ▾ Select…").dim()
+                    } else {
+                        Line::from(format!("  ▾ {chosen}"))
+                    };
+                    lines.push(highlight(line, Focus::Field(i)));
+                }
+                Field::Checkboxes { options, .. } => {
+                    for (j, option) in options.iter().enumerate() {
+                        if focused == Focus::Checkbox(i, j) {
+                            focus_line = lines.len();
+                        }
+                        let check = if option.checked { "[x]" } else { "[ ]" };
+                        let mut line = vec![Span::from(format!("  {check} {}", option.label))];
+                        if option.required {
+                            line.push(Span::from(" *").red());
+                        }
+                        lines.push(highlight(Line::from(line), Focus::Checkbox(i, j)));
+                    }
+                }
+                Field::Markdown(_) => {}
+            }
+        }
+
+        if !self.duplicates.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from("Possible duplicates").yellow().bold());
+            lines.extend(self.duplicates.iter().map(|issue| {
+                Line::from(vec![
+                    Span::from(format!("  #{} ", issue.number)).dim(),
+                    Span::from(issue.title.clone()),
+                ])
+            }));
+        }
+
+        let mut block = Block::bordered().title(format!(
+            "New issue in {} · {}",
+            self.repo, self.template.name
+        ));
+        block = match (&self.busy, &self.error) {
+            (Some(busy), _) => block.title_bottom(Line::from(busy.as_str()).dim()),
+            (None, Some(error)) => block.title_bottom(Line::from(error.as_str()).red()),
+            (None, None) if !self.duplicates.is_empty() => {
+                block.title_bottom("s: create anyway  esc: cancel")
+            }
+            (None, None) => block.title_bottom("enter: edit  s: create  esc: cancel"),
+        };
+        let inner = block.inner(area);
+        let scroll = (focus_line + 3).saturating_sub(inner.height as usize);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .scroll((scroll as u16, 0)),
+            area,
+        );
+
+        if let Some(input) = &self.input {
+            let y = inner.y + (focus_line - scroll) as u16;
+            let line = Rect::new(inner.x, y, inner.width, 1).intersection(inner);
+            frame.render_widget(
+                Paragraph::new(format!("  {}", input.pattern())).reversed(),
+                line,
+            );
+            frame.set_cursor_position(Position::new(inner.x + 2 + input.cursor_pos(), y));
+        }
+        if let Some(menu) = &mut self.menu {
+            menu.draw(frame);
+        }
+    }
+}
+
+/// Search `repo` for issues whose title has the words of `title`.
+/// Words that are too short to narrow the search are left out.
+pub fn duplicate_query(repo: &str, title: &str) -> Option<String> {
+    let words: Vec<&str> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("repo:{repo} is:issue in:title {}", words.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn render(form: &mut Form) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(60, 30)).unwrap();
+        terminal
+            .draw(|frame| form.draw(frame, frame.area()))
+            .unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(60)
+            .map(|line| line.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn key(form: &mut Form, code: KeyCode) -> FormEvent {
+        form.handle_key_event(code.into())
+    }
+
+    #[test]
+    fn test_form() {
+        let text = std::fs::read_to_string("testdata/bug_form.yml").unwrap();
+        let template = Template::parse("bug_form.yml", &text).unwrap().unwrap();
+        let mut form = Form::new("octocat/Hello-World", template);
+        assert_eq!(form.title, "[Bug]: ");
+        let screen = render(&mut form);
+        assert!(
+            screen.contains("New issue in octocat/Hello-World · Bug"),
+            "{screen}"
+        );
+        assert!(screen.contains("What happened? *"), "{screen}");
+        assert!(screen.contains("▾ Firefox"), "{screen}");
+
+        assert_eq!(key(&mut form, KeyCode::Char('s')), FormEvent::Pending);
+        assert_eq!(
+            form.error.as_deref(),
+            Some("Required: What happened?, Code of Conduct")
+        );
+
+        // edit the title inline
+        key(&mut form, KeyCode::Enter);
+        for c in "crash".chars() {
+            key(&mut form, KeyCode::Char(c));
+        }
+        key(&mut form, KeyCode::Enter);
+        assert_eq!(form.title, "[Bug]: crash");
+
+        // the textarea is edited in a file
+        key(&mut form, KeyCode::Char('j'));
+        key(&mut form, KeyCode::Char('j'));
+        assert_eq!(key(&mut form, KeyCode::Enter), FormEvent::Edit);
+        let dir = tempfile::tempdir().unwrap();
+        let path = form.start_edit(dir.path()).unwrap();
+        std::fs::write(&path, "It crashed").unwrap();
+        form.finish_edit(Ok(ExitStatus::default())).unwrap();
+        assert!(!path.exists());
+
+        // pick another browser too
+        key(&mut form, KeyCode::Char('j'));
+        key(&mut form, KeyCode::Enter);
+        key(&mut form, KeyCode::Char('j'));
+        key(&mut form, KeyCode::Enter);
+        assert!(render(&mut form).contains("✓ Chrome"));
+        key(&mut form, KeyCode::Esc);
+        assert!(render(&mut form).contains("▾ Firefox, Chrome"));
+
+        key(&mut form, KeyCode::Char('j'));
+        key(&mut form, KeyCode::Char('j'));
+        key(&mut form, KeyCode::Char(' '));
+        assert!(render(&mut form).contains("[x] I agree"));
+        assert_eq!(key(&mut form, KeyCode::Char('s')), FormEvent::Submit);
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("testdata/metadata.json").unwrap())
+                .unwrap();
+        let metadata: RepoMetadata =
+            serde_json::from_value(metadata["data"]["repository"].clone()).unwrap();
+        let issue = form.new_issue(&metadata);
+        assert_eq!(issue.title, "[Bug]: crash");
+        assert_eq!(
+            form.duplicate_query().as_deref(),
+            Some("repo:octocat/Hello-World is:issue in:title crash")
+        );
+        assert_eq!(issue.label_ids.len(), 1);
+        assert!(issue.body.contains("### What happened?\n\nIt crashed"));
+        assert!(issue.body.contains("Firefox, Chrome"));
+
+        assert_eq!(key(&mut form, KeyCode::Esc), FormEvent::Cancelled);
+    }
+
+    #[test]
+    fn test_duplicate_query() {
+        assert_eq!(
+            duplicate_query("foo/bar", "[Bug]: App crashes on start-up"),
+            Some("repo:foo/bar is:issue in:title Bug App crashes start".into())
+        );
+        assert_eq!(duplicate_query("foo/bar", "Is it on?"), None);
+    }
+}
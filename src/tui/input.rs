@@ -1,81 +1,491 @@
+use std::ops::Range;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use nucleo::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo::{Config, Matcher, Utf32Str};
 use ratatui::layout::Rect;
 use ratatui::{
     layout::Position,
+    style::{Style, Stylize as _},
+    text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthStr as _;
+
+const KILL_RING_SIZE: usize = 16;
+const UNDO_SIZE: usize = 128;
+
+// The kind of the last edit, used to merge consecutive edits of the same kind
+// into a single kill ring entry or undo step, like readline does.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Edit {
+    #[default]
+    None,
+    Insert,
+    Kill,
+    Yank,
+}
+
+pub type Highlighter = fn(&str) -> Vec<(Range<usize>, Style)>;
 
+/// A single line text input with readline-style editing.
+/// The cursor is a byte index into the pattern that always sits on a grapheme cluster boundary.
 #[derive(Default)]
 pub struct LineInput {
     pattern: String,
     cursor_pos: usize,
+    kill_ring: Vec<String>,
+    // the range of the last yanked text and the kill ring index it came from, for Alt-y
+    last_yank: Option<(Range<usize>, usize)>,
+    undo: Vec<(String, usize)>,
+    last_edit: Edit,
+    // previously submitted patterns, oldest first
+    history: Vec<String>,
+    // the history entry shown while browsing with Up/Down, and the pattern from before browsing
+    history_pos: Option<usize>,
+    draft: String,
+    search: Option<ReverseSearch>,
+    highlighter: Option<Highlighter>,
+}
+
+// State of an incremental Ctrl-r search through the history.
+#[derive(Default)]
+struct ReverseSearch {
+    query: String,
+    // indices into the history, best match first
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl ReverseSearch {
+    fn update(&mut self, history: &[String]) {
+        self.selected = 0;
+        if self.query.is_empty() {
+            self.matches = (0..history.len()).rev().collect();
+            return;
+        }
+        let pattern = Pattern::parse(&self.query, CaseMatching::Smart, Normalization::Smart);
+        let mut matcher = Matcher::new(Config::DEFAULT);
+        let mut buf = Vec::new();
+        let mut scored = history
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let score = pattern.score(Utf32Str::new(entry, &mut buf), &mut matcher)?;
+                Some((score, i))
+            })
+            .collect::<Vec<_>>();
+        // best score first, more recent entries win ties
+        scored.sort_by(|a, b| b.cmp(a));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+    }
+
+    fn current<'a>(&self, history: &'a [String]) -> Option<&'a str> {
+        let idx = self.matches.get(self.selected)?;
+        Some(history[*idx].as_str())
+    }
 }
 
 impl LineInput {
-    pub fn handle_key_event(&mut self, key_event: KeyEvent) {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        Self {
+            cursor_pos: pattern.len(),
+            pattern,
+            ..Default::default()
+        }
+    }
+
+    /// Set the previously submitted patterns, oldest first.
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = history;
+        self.history_pos = None;
+    }
+
+    /// Record a submitted pattern, moving it to the end if it was already present.
+    pub fn push_history(&mut self, pattern: &str) {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return;
+        }
+        self.history.retain(|p| p != pattern);
+        self.history.push(pattern.to_string());
+        self.history_pos = None;
+    }
+
+    /// True while a Ctrl-r history search is active.
+    /// The search consumes keys like Enter and Esc that would otherwise go to the app.
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    fn set_pattern(&mut self, pattern: &str) {
+        if pattern == self.pattern {
+            return;
+        }
+        self.save_undo();
+        self.pattern = pattern.to_string();
+        self.cursor_pos = self.pattern.len();
+    }
+
+    // Show the previous (older) history entry, saving the current pattern when browsing starts.
+    fn history_prev(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => return,
+            Some(pos) => pos - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.pattern.clone();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+        self.set_pattern(&self.history[pos].clone());
+    }
+
+    // Show the next (newer) history entry, restoring the saved pattern at the end.
+    fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.set_pattern(&self.history[pos + 1].clone());
+        } else {
+            self.history_pos = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_pattern(&draft);
+        }
+    }
+
+    fn handle_search_key(&mut self, key_event: KeyEvent) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
         match key_event.code {
-            KeyCode::Left => {
-                tracing::debug!("Moving cursor left");
-                self.cursor_pos = self.cursor_pos.saturating_sub(1);
+            KeyCode::Char('r') if ctrl => {
+                search.selected = (search.selected + 1).min(search.matches.len().saturating_sub(1));
+            }
+            KeyCode::Char('s') if ctrl => search.selected = search.selected.saturating_sub(1),
+            KeyCode::Esc => self.search = None,
+            KeyCode::Char('g') if ctrl => self.search = None,
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.update(&self.history);
             }
-            KeyCode::Right => {
-                tracing::debug!("Moving cursor right");
-                self.cursor_pos = (self.cursor_pos + 1).min(self.pattern.len());
+            KeyCode::Char(c) if (key_event.modifiers & !KeyModifiers::SHIFT).is_empty() => {
+                search.query.push(c);
+                search.update(&self.history);
             }
-            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                if self.cursor_pos == 0 {
-                    return;
-                };
-                tracing::debug!(
-                    "Deleting word from '{}' at {}",
-                    self.pattern,
-                    self.cursor_pos
-                );
-                let (s, rest) = self.pattern.split_at(self.cursor_pos);
-                if let Some(idx) = s.trim_end().rfind(char::is_whitespace) {
-                    self.cursor_pos = idx + 1;
-                    self.pattern = s[0..=idx].to_owned() + rest;
-                    tracing::debug!("Truncated pattern to {}", self.pattern);
-                } else {
-                    self.pattern = rest.into();
-                    self.cursor_pos = 0;
-                    tracing::debug!("Cleared pattern");
+            _ => {
+                // like readline, any other key accepts the match and is then handled as usual
+                let accepted = search.current(&self.history).map(str::to_string);
+                tracing::debug!("Accepted history search: {accepted:?}");
+                self.search = None;
+                if let Some(pattern) = accepted {
+                    self.set_pattern(&pattern);
+                }
+                if key_event.code != KeyCode::Enter {
+                    self.handle_key_event(key_event);
                 }
             }
-            KeyCode::Backspace => {
-                if self.cursor_pos == 0 {
-                    return;
-                };
-                self.cursor_pos -= 1;
-                let c = self.pattern.remove(self.cursor_pos);
-                tracing::debug!("Removed '{c}' from pattern, new pattern: {}", self.pattern);
+        }
+    }
+
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if self.search.is_some() {
+            self.handle_search_key(key_event);
+            return;
+        }
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        let edit = self.last_edit;
+        self.last_edit = Edit::None;
+        match key_event.code {
+            KeyCode::Left => self.cursor_pos = self.prev_boundary(),
+            KeyCode::Char('b') if ctrl => self.cursor_pos = self.prev_boundary(),
+            KeyCode::Right => self.cursor_pos = self.next_boundary(),
+            KeyCode::Char('f') if ctrl => self.cursor_pos = self.next_boundary(),
+            KeyCode::Home => self.cursor_pos = 0,
+            KeyCode::Char('a') if ctrl => self.cursor_pos = 0,
+            KeyCode::End => self.cursor_pos = self.pattern.len(),
+            KeyCode::Char('e') if ctrl => self.cursor_pos = self.pattern.len(),
+            KeyCode::Up => self.history_prev(),
+            KeyCode::Char('p') if ctrl => self.history_prev(),
+            KeyCode::Down => self.history_next(),
+            KeyCode::Char('n') if ctrl => self.history_next(),
+            KeyCode::Char('r') if ctrl => {
+                let mut search = ReverseSearch::default();
+                search.update(&self.history);
+                self.search = Some(search);
+            }
+            KeyCode::Char('b') if alt => self.cursor_pos = self.prev_word(),
+            KeyCode::Char('f') if alt => self.cursor_pos = self.next_word(),
+            KeyCode::Char('w') if ctrl => {
+                // unlike Alt-Backspace, Ctrl-w deletes back to the previous whitespace
+                let s = &self.pattern[..self.cursor_pos];
+                let start = s
+                    .trim_end()
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| c.is_whitespace())
+                    .map_or(0, |(idx, c)| idx + c.len_utf8());
+                self.kill(start..self.cursor_pos, edit);
             }
+            KeyCode::Backspace if alt => self.kill(self.prev_word()..self.cursor_pos, edit),
+            KeyCode::Char('d') if alt => self.kill(self.cursor_pos..self.next_word(), edit),
+            KeyCode::Char('u') if ctrl => self.kill(0..self.cursor_pos, edit),
+            KeyCode::Char('k') if ctrl => self.kill(self.cursor_pos..self.pattern.len(), edit),
+            KeyCode::Char('y') if ctrl => self.yank(),
+            KeyCode::Char('y') if alt && edit == Edit::Yank => self.yank_pop(),
+            KeyCode::Char('z' | '_' | '7') if ctrl => self.undo(),
+            KeyCode::Backspace => self.delete(self.prev_boundary()..self.cursor_pos),
+            KeyCode::Char('h') if ctrl => self.delete(self.prev_boundary()..self.cursor_pos),
+            KeyCode::Delete => self.delete(self.cursor_pos..self.next_boundary()),
+            KeyCode::Char('d') if ctrl => self.delete(self.cursor_pos..self.next_boundary()),
             KeyCode::Char(c) if (key_event.modifiers & !KeyModifiers::SHIFT).is_empty() => {
+                if edit != Edit::Insert {
+                    self.save_undo();
+                }
                 self.pattern.insert(self.cursor_pos, c);
-                self.cursor_pos += 1;
+                self.cursor_pos += c.len_utf8();
+                // combining characters may have merged with the previous grapheme
+                self.cursor_pos = self.next_boundary_from(self.prev_boundary());
+                self.last_edit = Edit::Insert;
                 tracing::debug!("Updated filter pattern: {}", self.pattern);
             }
-            _ => {}
+            _ => {
+                // keys we don't handle shouldn't break up an edit group
+                self.last_edit = edit;
+            }
+        }
+        tracing::trace!("pattern='{}', cursor={}", self.pattern, self.cursor_pos);
+    }
+
+    // Byte index of the grapheme boundary before the cursor.
+    fn prev_boundary(&self) -> usize {
+        self.pattern[..self.cursor_pos]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    // Byte index of the grapheme boundary after the cursor.
+    fn next_boundary(&self) -> usize {
+        self.next_boundary_from(self.cursor_pos)
+    }
+
+    fn next_boundary_from(&self, pos: usize) -> usize {
+        self.pattern[pos..]
+            .graphemes(true)
+            .next()
+            .map_or(pos, |g| pos + g.len())
+    }
+
+    // Byte index of the start of the word before the cursor.
+    // Like readline, words are runs of alphanumeric characters.
+    fn prev_word(&self) -> usize {
+        let mut graphemes = self.pattern[..self.cursor_pos].grapheme_indices(true).rev();
+        let mut pos = 0;
+        for (i, g) in graphemes.by_ref() {
+            if is_word(g) {
+                pos = i;
+                break;
+            }
+        }
+        for (i, g) in graphemes {
+            if !is_word(g) {
+                break;
+            }
+            pos = i;
+        }
+        pos
+    }
+
+    // Byte index of the end of the word after the cursor.
+    fn next_word(&self) -> usize {
+        let start = self.cursor_pos;
+        self.pattern[start..]
+            .grapheme_indices(true)
+            .skip_while(|(_, g)| !is_word(g))
+            .find(|(_, g)| !is_word(g))
+            .map_or(self.pattern.len(), |(i, _)| start + i)
+    }
+
+    fn save_undo(&mut self) {
+        if self.undo.len() == UNDO_SIZE {
+            self.undo.remove(0);
+        }
+        self.undo.push((self.pattern.clone(), self.cursor_pos));
+    }
+
+    fn undo(&mut self) {
+        if let Some((pattern, cursor_pos)) = self.undo.pop() {
+            tracing::debug!("Undo to '{pattern}'");
+            self.pattern = pattern;
+            self.cursor_pos = cursor_pos;
+        }
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.save_undo();
+        self.cursor_pos = range.start;
+        let removed = self.pattern.drain(range).collect::<String>();
+        tracing::debug!(
+            "Removed '{removed}' from pattern, new pattern: {}",
+            self.pattern
+        );
+    }
+
+    // Delete the range and save it to the kill ring.
+    // Consecutive kills are merged into one entry.
+    fn kill(&mut self, range: Range<usize>, last_edit: Edit) {
+        if range.is_empty() {
+            self.last_edit = last_edit;
+            return;
+        }
+        let text = self.pattern[range.clone()].to_string();
+        let backward = range.end == self.cursor_pos;
+        self.delete(range);
+        match self.kill_ring.last_mut() {
+            Some(last) if last_edit == Edit::Kill && backward => last.insert_str(0, &text),
+            Some(last) if last_edit == Edit::Kill => last.push_str(&text),
+            _ => {
+                if self.kill_ring.len() == KILL_RING_SIZE {
+                    self.kill_ring.remove(0);
+                }
+                self.kill_ring.push(text);
+            }
+        }
+        self.last_edit = Edit::Kill;
+        tracing::debug!("Kill ring: {:?}", self.kill_ring);
+    }
+
+    fn insert_yank(&mut self, idx: usize) {
+        let text = &self.kill_ring[idx];
+        let start = self.cursor_pos;
+        self.pattern.insert_str(start, text);
+        self.cursor_pos += text.len();
+        self.last_yank = Some((start..self.cursor_pos, idx));
+        self.last_edit = Edit::Yank;
+    }
+
+    fn yank(&mut self) {
+        if self.kill_ring.is_empty() {
+            return;
         }
+        self.save_undo();
+        self.insert_yank(self.kill_ring.len() - 1);
+    }
+
+    // Replace the text that was just yanked with the previous kill ring entry.
+    fn yank_pop(&mut self) {
+        let Some((range, idx)) = self.last_yank.take() else {
+            return;
+        };
+        self.pattern.replace_range(range.clone(), "");
+        self.cursor_pos = range.start;
+        let idx = idx.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        self.insert_yank(idx);
+    }
+
+    /// Replace a byte range of the pattern, leaving the cursor after the replacement.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        self.save_undo();
+        self.pattern.replace_range(range.clone(), text);
+        self.cursor_pos = range.start + text.len();
+        self.last_edit = Edit::None;
     }
 
+    /// Byte index of the cursor.
+    pub fn cursor_index(&self) -> usize {
+        self.cursor_pos
+    }
+
+    // Display column of the cursor.
     pub fn cursor_pos(&self) -> u16 {
-        self.cursor_pos as u16
+        self.pattern[..self.cursor_pos].width() as u16
     }
 
     pub fn pattern(&self) -> &str {
         &self.pattern
     }
 
+    /// Set a function that returns styles for byte ranges of the pattern.
+    /// Later styles take precedence over earlier ones.
+    pub fn with_highlighter(mut self, highlighter: Highlighter) -> Self {
+        self.highlighter = Some(highlighter);
+        self
+    }
+
+    // The visible part of the pattern, and the cursor column within it.
+    // Scrolls horizontally to keep the cursor in view.
+    fn visible(&self, width: u16) -> (Line<'static>, u16) {
+        let styles = self
+            .highlighter
+            .map(|h| h(&self.pattern))
+            .unwrap_or_default();
+        let style_at = |idx: usize| {
+            styles
+                .iter()
+                .rev()
+                .find(|(range, _)| range.contains(&idx))
+                .map(|(_, style)| *style)
+                .unwrap_or_default()
+        };
+
+        // leave room for the cursor even in an empty area
+        let width = width.max(1);
+        let cursor = self.cursor_pos();
+        let scroll = (cursor + 1).saturating_sub(width);
+        let mut col = 0;
+        let mut line = Line::default();
+        let mut push = |s: &str, style: Style| match line.spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push_str(s),
+            _ => line.push_span(Span::styled(s.to_string(), style)),
+        };
+        for (i, g) in self.pattern.grapheme_indices(true) {
+            let start = col;
+            col += g.width() as u16;
+            if start >= scroll && col <= scroll + width {
+                push(g, style_at(i));
+            } else if start < scroll && col > scroll {
+                // a wide character cut off by the left edge
+                push(&" ".repeat((col - scroll) as usize), Style::default());
+            }
+        }
+        (line, cursor - scroll)
+    }
+
     pub fn draw(&self, frame: &mut Frame, area: Rect) {
-        let input =
-            Paragraph::new(self.pattern.as_str()).block(Block::new().borders(Borders::BOTTOM));
+        if let Some(search) = &self.search {
+            let prompt = format!("(reverse-i-search)`{}': ", search.query);
+            let cursor = prompt.width() as u16 - 3;
+            let found = search.current(&self.history).unwrap_or_default();
+            let line = Line::from(vec![Span::raw(prompt).dim(), Span::raw(found)]);
+            let input = Paragraph::new(line).block(Block::new().borders(Borders::BOTTOM));
+            frame.render_widget(input, area);
+            frame.set_cursor_position(Position::new(area.x + cursor, area.y));
+            return;
+        }
+
+        let (visible, cursor) = self.visible(area.width);
+        let input = Paragraph::new(visible).block(Block::new().borders(Borders::BOTTOM));
         frame.render_widget(input, area);
-        frame.set_cursor_position(Position::new(area.x + self.cursor_pos as u16, area.y));
+        frame.set_cursor_position(Position::new(area.x + cursor, area.y));
     }
 }
 
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,6 +557,19 @@ mod tests {
         app.handle_key_event(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
         assert_eq!(app.pattern, "");
         assert_eq!(app.cursor_pos, 0);
+
+        // a full-width space, as typed with an IME
+        input(&mut app, "label:バグ\u{3000}検索");
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(app.pattern, "label:バグ\u{3000}");
+        assert_eq!(app.cursor_pos, app.pattern.len());
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(app.pattern, "");
+
+        input(&mut app, "abc");
+        let (line, cursor) = app.visible(0);
+        assert_eq!(line.to_string(), "");
+        assert_eq!(cursor, 0);
     }
 
     #[test]
@@ -208,4 +631,248 @@ mod tests {
         assert_eq!(app.pattern, " ghi");
         assert_eq!(app.cursor_pos, 0);
     }
+
+    fn key(app: &mut LineInput, code: KeyCode, modifiers: KeyModifiers) {
+        app.handle_key_event(KeyEvent::new(code, modifiers));
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_unicode() {
+        let mut app = LineInput::default();
+
+        input(&mut app, "aé日本");
+        assert_eq!(app.pattern, "aé日本");
+        assert_eq!(app.cursor_pos, "aé日本".len());
+        assert_eq!(app.cursor_pos(), 6);
+
+        app.handle_key_event(KeyCode::Left.into());
+        assert_eq!(app.cursor_pos, "aé日".len());
+        assert_eq!(app.cursor_pos(), 4);
+
+        app.handle_key_event(KeyCode::Backspace.into());
+        assert_eq!(app.pattern, "aé本");
+        assert_eq!(app.cursor_pos(), 2);
+
+        app.handle_key_event(KeyCode::Backspace.into());
+        assert_eq!(app.pattern, "a本");
+        assert_eq!(app.cursor_pos(), 1);
+
+        // combining acute accent joins the previous character into one grapheme
+        input(&mut app, "e\u{301}");
+        assert_eq!(app.pattern, "ae\u{301}本");
+        app.handle_key_event(KeyCode::Left.into());
+        assert_eq!(app.cursor_pos, 1);
+        app.handle_key_event(KeyCode::Delete.into());
+        assert_eq!(app.pattern, "a本");
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_line_motion() {
+        let mut app = LineInput::new("abc def");
+        assert_eq!(app.cursor_pos, 7);
+
+        app.handle_key_event(KeyCode::Home.into());
+        assert_eq!(app.cursor_pos, 0);
+        app.handle_key_event(KeyCode::End.into());
+        assert_eq!(app.cursor_pos, 7);
+        key(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        assert_eq!(app.cursor_pos, 0);
+        key(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        assert_eq!(app.cursor_pos, 7);
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_word_motion() {
+        let mut app = LineInput::new("is:open label:bug");
+
+        key(&mut app, KeyCode::Char('b'), KeyModifiers::ALT);
+        assert_eq!(app.cursor_pos, 14);
+        key(&mut app, KeyCode::Char('b'), KeyModifiers::ALT);
+        assert_eq!(app.cursor_pos, 8);
+        key(&mut app, KeyCode::Char('b'), KeyModifiers::ALT);
+        assert_eq!(app.cursor_pos, 3);
+        key(&mut app, KeyCode::Char('f'), KeyModifiers::ALT);
+        assert_eq!(app.cursor_pos, 7);
+
+        key(&mut app, KeyCode::Char('d'), KeyModifiers::ALT);
+        assert_eq!(app.pattern, "is:open:bug");
+        assert_eq!(app.cursor_pos, 7);
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_kill_yank() {
+        let mut app = LineInput::new("abc def ghi");
+
+        for _ in 0..4 {
+            app.handle_key_event(KeyCode::Left.into());
+        }
+        key(&mut app, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc def");
+        key(&mut app, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "");
+        assert_eq!(app.cursor_pos, 0);
+
+        // consecutive kills are joined
+        key(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc def ghi");
+        assert_eq!(app.cursor_pos, 11);
+
+        key(&mut app, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc def ");
+        app.handle_key_event(KeyCode::Home.into());
+        input(&mut app, "x");
+        key(&mut app, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "x");
+
+        key(&mut app, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "xabc def ");
+        key(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(app.pattern, "xghi");
+        key(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(app.pattern, "xabc def ghi");
+        key(&mut app, KeyCode::Char('y'), KeyModifiers::ALT);
+        assert_eq!(app.pattern, "xabc def ");
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_undo() {
+        let mut app = LineInput::default();
+
+        input(&mut app, "abc ");
+        input(&mut app, "def");
+        key(&mut app, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc ");
+        app.handle_key_event(KeyCode::Backspace.into());
+        assert_eq!(app.pattern, "abc");
+
+        key(&mut app, KeyCode::Char('_'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc ");
+        key(&mut app, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "abc def");
+        assert_eq!(app.cursor_pos, 7);
+        key(&mut app, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "");
+        key(&mut app, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(app.pattern, "");
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_scroll() {
+        let mut app = LineInput::new("abcdef");
+        assert_eq!(app.visible(10), ("abcdef".into(), 6));
+        assert_eq!(app.visible(4), ("def".into(), 3));
+
+        app.handle_key_event(KeyCode::Home.into());
+        assert_eq!(app.visible(4), ("abcd".into(), 0));
+
+        let app = LineInput::new("a日本語");
+        assert_eq!(app.visible(4), (" 語".into(), 3));
+        assert_eq!(app.visible(5), ("本語".into(), 4));
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_highlight() {
+        let app = LineInput::new("abcdef")
+            .with_highlighter(|_| vec![(1..4, Style::new().red()), (3..4, Style::new().blue())]);
+        assert_eq!(
+            app.visible(10).0,
+            Line::from(vec![
+                Span::raw("a"),
+                Span::raw("bc").red(),
+                Span::raw("d").blue(),
+                Span::raw("ef"),
+            ])
+        );
+        assert_eq!(
+            app.visible(4).0,
+            Line::from(vec![Span::raw("d").blue(), Span::raw("ef")])
+        );
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_history() {
+        let mut app = LineInput::new("draft");
+        app.set_history(vec!["one".into(), "two".into()]);
+
+        app.handle_key_event(KeyCode::Up.into());
+        assert_eq!(app.pattern, "two");
+        assert_eq!(app.cursor_pos, 3);
+        app.handle_key_event(KeyCode::Up.into());
+        assert_eq!(app.pattern, "one");
+        app.handle_key_event(KeyCode::Up.into());
+        assert_eq!(app.pattern, "one");
+
+        app.handle_key_event(KeyCode::Down.into());
+        assert_eq!(app.pattern, "two");
+        app.handle_key_event(KeyCode::Down.into());
+        assert_eq!(app.pattern, "draft");
+        app.handle_key_event(KeyCode::Down.into());
+        assert_eq!(app.pattern, "draft");
+
+        app.push_history("one");
+        app.handle_key_event(KeyCode::Up.into());
+        assert_eq!(app.pattern, "one");
+        app.handle_key_event(KeyCode::Up.into());
+        assert_eq!(app.pattern, "two");
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_reverse_search() {
+        let mut app = LineInput::new("draft");
+        app.set_history(vec![
+            "label:bug".into(),
+            "is:open label:enhancement".into(),
+            "is:closed".into(),
+        ]);
+
+        key(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert!(app.is_searching());
+        input(&mut app, "label");
+        // equally good matches are sorted newest first
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(
+            search.current(&app.history),
+            Some("is:open label:enhancement")
+        );
+        assert_eq!(search.matches.len(), 2);
+
+        key(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(search.current(&app.history), Some("label:bug"));
+        key(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        key(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(
+            search.current(&app.history),
+            Some("is:open label:enhancement")
+        );
+
+        app.handle_key_event(KeyCode::Enter.into());
+        assert!(!app.is_searching());
+        assert_eq!(app.pattern, "is:open label:enhancement");
+
+        // other keys accept the match and then apply
+        key(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        input(&mut app, "clo");
+        app.handle_key_event(KeyCode::Left.into());
+        assert!(!app.is_searching());
+        assert_eq!(app.pattern, "is:closed");
+        assert_eq!(app.cursor_pos, 8);
+
+        // escape cancels
+        key(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        input(&mut app, "bug");
+        app.handle_key_event(KeyCode::Esc.into());
+        assert!(!app.is_searching());
+        assert_eq!(app.pattern, "is:closed");
+    }
 }
@@ -0,0 +1,607 @@
+use super::{Action, App};
+use crate::github::issues::{Issue, IssueKind, IssueState};
+use crate::github::mutation::{Change, CloseReason};
+use crate::tui::bulk::Batch;
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+
+/// Menus for editing the metadata of an issue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Picker {
+    Labels,
+    Assignees,
+    Milestone,
+}
+
+impl Picker {
+    // The picker a change to labels or assignees was chosen from, which stays open
+    // to toggle more of them.
+    pub(super) fn chosen_from(action: &Action) -> Option<Self> {
+        match action {
+            Action::Change(Change::AddLabel(_) | Change::RemoveLabel(_)) => Some(Self::Labels),
+            Action::Change(Change::AddAssignee(_) | Change::RemoveAssignee(_)) => {
+                Some(Self::Assignees)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Results of changes to one or many issues.
+#[derive(Debug)]
+pub enum Message {
+    // a change to an issue was sent, and is undone if it failed
+    Changed {
+        // set when the change is one of many made at once
+        batch: Option<usize>,
+        id: String,
+        undo: Change,
+        description: String,
+        result: Result<()>,
+    },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Bulk(msg)
+    }
+}
+
+impl App {
+    // Open a picker again at the item chosen from it, unless the change is shown as a batch.
+    pub(super) fn reopen_picker(&mut self, picker: Picker, index: Option<usize>) {
+        if self.batch.is_some() {
+            return;
+        }
+        self.open_picker(picker);
+        if let Some(menu) = &mut self.menu {
+            menu.select(index);
+        }
+    }
+
+    pub(super) fn handle_batch_key(&mut self, key_event: KeyEvent) {
+        let Some(batch) = &self.batch else {
+            return;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('c') if ctrl => self.exit = true,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') if batch.is_finished() => {
+                self.notice = Some(Line::from(batch.summary()).red());
+                self.batch = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn update_bulk(&mut self, msg: Message) {
+        match msg {
+            Message::Changed {
+                batch,
+                id,
+                undo,
+                description,
+                result,
+            } => self.changed(batch, id, undo, description, result),
+        }
+        self.dirty = true;
+    }
+
+    pub(super) fn is_marked(&self, index: usize) -> bool {
+        let in_range = match (self.visual, self.table_state.selected()) {
+            (Some(anchor), Some(selected)) => {
+                (anchor.min(selected)..=anchor.max(selected)).contains(&index)
+            }
+            _ => false,
+        };
+        in_range
+            || self
+                .issues
+                .get(index)
+                .is_some_and(|i| self.marked.contains(&i.id))
+    }
+
+    pub(super) fn marked_indices(&self) -> Vec<usize> {
+        (0..self.issues.len())
+            .filter(|&i| self.is_marked(i))
+            .collect()
+    }
+
+    // The items an operation applies to: the marked ones, or else the selected one.
+    pub(super) fn targets(&self) -> Vec<usize> {
+        let marked = self.marked_indices();
+        if marked.is_empty() {
+            self.table_state
+                .selected()
+                .filter(|&i| i < self.issues.len())
+                .into_iter()
+                .collect()
+        } else {
+            marked
+        }
+    }
+
+    // How an operation refers to its targets, e.g. "#12" or "3 items".
+    fn describe_targets(&self, targets: &[usize]) -> String {
+        match targets {
+            [index] => format!("#{}", self.issues[*index].number),
+            _ => format!("{} items", targets.len()),
+        }
+    }
+
+    // Mark the rows of the visual range, ending it.
+    pub(super) fn commit_visual(&mut self) {
+        if self.visual.is_none() {
+            return;
+        }
+        let marked = self.marked_indices();
+        self.marked
+            .extend(marked.into_iter().map(|i| self.issues[i].id.clone()));
+        self.visual = None;
+    }
+
+    pub(super) fn toggle_mark(&mut self) {
+        let Some(id) = self.selected_issue().map(|i| i.id.clone()) else {
+            return;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        self.select_next();
+    }
+
+    // Mark every loaded item, or unmark them all if they already are.
+    pub(super) fn toggle_all(&mut self) {
+        self.visual = None;
+        if self.issues.iter().all(|i| self.marked.contains(&i.id)) {
+            self.marked.clear();
+        } else {
+            self.marked.extend(self.issues.iter().map(|i| i.id.clone()));
+        }
+    }
+
+    // Count the result of an operation on one of many items.
+    // Items that succeeded are unmarked, so the failed ones can be retried.
+    pub(super) fn record(
+        &mut self,
+        batch: usize,
+        id: &str,
+        number: u32,
+        result: Result<(), String>,
+    ) {
+        let Some(current) = self.batch.as_mut().filter(|b| b.id == batch) else {
+            return;
+        };
+        if result.is_ok() {
+            self.marked.remove(id);
+        }
+        current.record(number, result);
+        if !current.is_finished() {
+            return;
+        }
+        let (failed, summary) = (!current.failures.is_empty(), current.summary());
+        tracing::debug!("{summary}");
+        if let Some(composer) = self.composer.take() {
+            if failed {
+                self.notice =
+                    Some(Line::from(format!("Draft kept in {}", composer.path.display())).dim());
+            } else {
+                composer.discard();
+            }
+        }
+        if !failed {
+            self.notice = Some(Line::from(summary).dim());
+            self.batch = None;
+        }
+    }
+
+    // Close or reopen the marked or selected issues.
+    pub(super) fn open_state_menu(&mut self) {
+        let targets = self.targets();
+        let item = |label: &str, change| MenuItem {
+            label: label.to_string(),
+            description: String::new(),
+            value: Action::Change(change),
+        };
+        let issue = match targets.as_slice() {
+            [] => return,
+            [index] => &self.issues[*index],
+            _ => {
+                // pull requests ignore the reason
+                let items = vec![
+                    item(
+                        "Close as completed",
+                        Change::Close(Some(CloseReason::Completed)),
+                    ),
+                    item(
+                        "Close as not planned",
+                        Change::Close(Some(CloseReason::NotPlanned)),
+                    ),
+                    item("Reopen", Change::Reopen),
+                ];
+                self.menu = Some(Menu::new(self.describe_targets(&targets), items));
+                return;
+            }
+        };
+        let items = match (issue.state, &issue.typename) {
+            (IssueState::Open, IssueKind::Issue) => vec![
+                item(
+                    "Close as completed",
+                    Change::Close(Some(CloseReason::Completed)),
+                ),
+                item(
+                    "Close as not planned",
+                    Change::Close(Some(CloseReason::NotPlanned)),
+                ),
+            ],
+            (IssueState::Open, IssueKind::PullRequest) => {
+                vec![item("Close pull request", Change::Close(None))]
+            }
+            (IssueState::Closed, _) => vec![item("Reopen", Change::Reopen)],
+            (IssueState::Merged, _) => return,
+        };
+        self.menu = Some(Menu::new(format!("#{}", issue.number), items));
+    }
+
+    // Open a picker for the marked or selected issues, once their repo's metadata is available.
+    // Something is shown as set if it is set on all of them.
+    pub(super) fn open_picker(&mut self, picker: Picker) {
+        let targets = self.targets();
+        let issues: Vec<&Issue> = targets.iter().map(|&i| &self.issues[i]).collect();
+        let mut repos: Vec<String> = issues.iter().filter_map(|i| i.repo()).collect();
+        repos.sort();
+        repos.dedup();
+        let repo = match repos.as_slice() {
+            [] => return,
+            [repo] => repo.clone(),
+            _ => {
+                self.notice =
+                    Some(Line::from("The marked items are in more than one repository").red());
+                return;
+            }
+        };
+        let Some(metadata) = self.metadata.get(&repo).cloned() else {
+            self.pending_picker = Some((picker, repo.clone()));
+            self.notice = Some(Line::from(format!("Fetching {repo}…")).dim());
+            self.request_metadata(&repo);
+            return;
+        };
+        let check = |set: bool| if set { "✓ " } else { "  " };
+        let (title, items) = match picker {
+            Picker::Labels => (
+                "Labels",
+                metadata
+                    .labels
+                    .nodes
+                    .iter()
+                    .map(|label| {
+                        let set = issues
+                            .iter()
+                            .all(|i| i.labels.nodes.iter().any(|l| l.id == label.id));
+                        MenuItem {
+                            label: format!("{}{}", check(set), label.name),
+                            description: label.description.clone().unwrap_or_default(),
+                            value: Action::Change(if set {
+                                Change::RemoveLabel(label.clone())
+                            } else {
+                                Change::AddLabel(label.clone())
+                            }),
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Picker::Assignees => (
+                "Assignees",
+                metadata
+                    .assignable_users
+                    .nodes
+                    .iter()
+                    .map(|actor| {
+                        let set = issues
+                            .iter()
+                            .all(|i| i.assignees.nodes.iter().any(|a| a.id == actor.id));
+                        MenuItem {
+                            label: format!("{}{}", check(set), actor.login),
+                            description: String::new(),
+                            value: Action::Change(if set {
+                                Change::RemoveAssignee(actor.clone())
+                            } else {
+                                Change::AddAssignee(actor.clone())
+                            }),
+                        }
+                    })
+                    .collect(),
+            ),
+            Picker::Milestone => (
+                "Milestone",
+                metadata
+                    .milestones
+                    .nodes
+                    .iter()
+                    .map(|milestone| {
+                        let set = issues
+                            .iter()
+                            .all(|i| i.milestone.as_ref().is_some_and(|m| m.id == milestone.id));
+                        MenuItem {
+                            label: format!("{}{}", check(set), milestone.title),
+                            description: String::new(),
+                            value: Action::Change(Change::SetMilestone(
+                                (!set).then(|| milestone.clone()),
+                            )),
+                        }
+                    })
+                    .collect(),
+            ),
+        };
+        if items.is_empty() {
+            self.notice = Some(Line::from(format!("{repo} has no {}", title.to_lowercase())).dim());
+            return;
+        }
+        let title = format!("{title} of {}", self.describe_targets(&targets));
+        self.menu = Some(Menu::new(title, items));
+    }
+
+    // Apply a change to the marked or selected issues now, and send it to GitHub.
+    // Changes to many items skip those it wouldn't change, and show their progress.
+    pub(super) fn change(&mut self, change: Change) {
+        let mut targets = self.targets();
+        let batch = (targets.len() > 1).then(|| {
+            targets.retain(|&i| change.applies(&self.issues[i]));
+            self.batches += 1;
+            self.batches
+        });
+        if let Some(batch) = batch {
+            if targets.is_empty() {
+                self.notice = Some(Line::from("Nothing to change").dim());
+                return;
+            }
+            let description = change.describe(&self.describe_targets(&targets));
+            self.batch = Some(Batch::new(batch, description, targets.len()));
+        }
+        for index in targets {
+            let issue = &mut self.issues[index];
+            let undo = change.apply(issue);
+            let description = change.describe(&format!("#{}", issue.number));
+            let (id, kind) = (issue.id.clone(), issue.typename.clone());
+            let github = self.github.clone();
+            let change = change.clone();
+            if batch.is_none() {
+                self.notice = Some(Line::from(format!("{description}…")).dim());
+            }
+            self.spawn_limited(async move {
+                let result = change.send(github, kind, id.clone()).await;
+                Message::Changed {
+                    batch,
+                    id,
+                    undo,
+                    description,
+                    result,
+                }
+            });
+        }
+    }
+
+    fn changed(
+        &mut self,
+        batch: Option<usize>,
+        id: String,
+        undo: Change,
+        description: String,
+        result: Result<()>,
+    ) {
+        let issue = self.issues.iter_mut().find(|i| i.id == id);
+        let number = issue.as_ref().map(|i| i.number).unwrap_or_default();
+        if let Err(err) = &result {
+            tracing::error!("{description} failed: {err:?}");
+            if let Some(issue) = issue {
+                undo.apply(issue);
+            }
+        }
+        match (batch, result) {
+            (Some(batch), result) => {
+                let result = result.map_err(|err| format!("{err:#}"));
+                self.record(batch, &id, number, result);
+            }
+            (None, Ok(())) => self.notice = Some(Line::from(description).dim()),
+            (None, Err(err)) => {
+                self.notice = Some(Line::from(format!("{description} failed: {err:#}")).red());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::repo::RepoMetadata;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::process::ExitStatus;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_triage() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        assert!(render(&mut app).contains("open · bug"));
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("testdata/metadata.json").unwrap())
+                .unwrap();
+        let metadata: RepoMetadata =
+            serde_json::from_value(metadata["data"]["repository"].clone()).unwrap();
+        app.metadata
+            .insert("octocat/Hello-World".into(), Arc::new(metadata));
+
+        // the label picker stays open, showing the label was added
+        app.update(Message::Key(KeyCode::Char('l').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("✓ bug"), "{screen}");
+        assert!(screen.contains("  good first issue"), "{screen}");
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(render(&mut app).contains("✓ good first issue"));
+        assert_eq!(app.issues[0].labels.nodes.len(), 2);
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.menu.is_none());
+        loop {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            let changed = matches!(msg, Message::Bulk(super::Message::Changed { .. }));
+            if changed {
+                assert!(matches!(
+                    msg,
+                    Message::Bulk(super::Message::Changed { result: Ok(()), .. })
+                ));
+            }
+            app.update(msg).unwrap();
+            if changed {
+                break;
+            }
+        }
+        assert_eq!(app.issues[0].labels.nodes.len(), 2);
+
+        app.update(Message::Key(KeyCode::Char('x').into())).unwrap();
+        assert!(render(&mut app).contains("Close as not planned"));
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert_eq!(app.issues[0].state, IssueState::Closed);
+
+        // a failed change is undone
+        let id = app.issues[0].id.clone();
+        app.update(Message::Bulk(super::Message::Changed {
+            batch: None,
+            id,
+            undo: Change::Reopen,
+            description: "Closed #2356".into(),
+            result: Err(anyhow::anyhow!("Resource not accessible by integration")),
+        }))
+        .unwrap();
+        assert_eq!(app.issues[0].state, IssueState::Open);
+        let screen = render(&mut app);
+        assert!(screen.contains("Closed #2356 failed"), "{screen}");
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_bulk() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let ctrl_a = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL);
+
+        app.update(Message::Key(KeyCode::Char(' ').into())).unwrap();
+        assert_eq!(app.table_state.selected(), Some(1));
+        assert_eq!(app.targets(), vec![0]);
+        assert!(render(&mut app).contains("1 marked"));
+        app.update(Message::Key(ctrl_a)).unwrap();
+        assert_eq!(app.targets(), vec![0, 1]);
+        app.update(Message::Key(ctrl_a)).unwrap();
+        assert_eq!(app.targets(), vec![1]);
+
+        // a visual range from the second row up to the first
+        app.update(Message::Key(KeyCode::Char('v').into())).unwrap();
+        app.update(Message::Key(KeyCode::Char('k').into())).unwrap();
+        assert_eq!(app.targets(), vec![0, 1]);
+        app.update(Message::Key(KeyCode::Char('x').into())).unwrap();
+        assert!(app.visual.is_none());
+        assert_eq!(app.marked.len(), 2);
+        assert!(render(&mut app).contains("Reopen"));
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(app.issues.iter().all(|i| i.state == IssueState::Closed));
+        assert!(render(&mut app).contains("Closed 2 items"));
+        while app.batch.is_some() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        assert!(app.marked.is_empty());
+        let screen = render(&mut app);
+        assert!(screen.contains("Closed 2 items: 2 done"), "{screen}");
+
+        // the mock server can't post comments, so every item fails and stays marked
+        let drafts = tempfile::tempdir().unwrap();
+        app.drafts_dir = Some(drafts.path().to_path_buf());
+        app.update(Message::Key(ctrl_a)).unwrap();
+        app.update(Message::Key(KeyCode::Char('c').into())).unwrap();
+        assert!(app.command.take().is_some());
+        let draft = drafts.path().join("octocat_Hello-World_2356_and_1_more.md");
+        std::fs::write(&draft, "Duplicate").unwrap();
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        assert!(render(&mut app).contains("Comment on #2356 and 1 more"));
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        while !app.batch.as_ref().is_some_and(Batch::is_finished) {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("#2356: "), "{screen}");
+        assert!(screen.contains("#2648: "), "{screen}");
+        assert!(screen.contains("esc: close"), "{screen}");
+        assert!(draft.exists());
+        assert!(app.composer.is_none());
+        assert_eq!(app.marked.len(), 2);
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.batch.is_none());
+        assert!(render(&mut app).contains("0 done, 2 failed"));
+
+        app.update(Message::Key(KeyCode::Char('p').into())).unwrap();
+        assert!(app.exit);
+        assert_eq!(app.output().len(), 2);
+        assert!(
+            app.output()[1].ends_with("/pull/2648"),
+            "{:?}",
+            app.output()
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use super::App;
+use crate::git::{self, PullHead};
+use crate::github::issues::IssueKind;
+use crate::open;
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::{Context, Result};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use std::path::PathBuf;
+
+/// Results of checking out pull requests and branches.
+#[derive(Debug)]
+pub enum Message {
+    // a pull request or a branch for an issue was checked out, described by the result
+    CheckedOut(Result<String>),
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Checkout(msg)
+    }
+}
+
+/// Ways of checking out the selected item.
+#[derive(Clone, Debug)]
+pub(super) enum Action {
+    Checkout { stash: bool },
+    Branch { worktree: bool },
+}
+
+impl From<Action> for super::Action {
+    fn from(action: Action) -> Self {
+        Self::Checkout(action)
+    }
+}
+
+impl App {
+    pub(super) fn update_checkout(&mut self, msg: Message) {
+        match msg {
+            Message::CheckedOut(result) => {
+                self.notice = Some(match result {
+                    Ok(description) => Line::from(description).dim(),
+                    Err(err) => {
+                        tracing::error!("Failed to check out: {err:?}");
+                        Line::from(format!("{err:#}")).red()
+                    }
+                });
+            }
+        }
+        self.dirty = true;
+    }
+
+    pub(super) fn apply_checkout(&mut self, action: Action) {
+        match action {
+            Action::Checkout { stash } => self.checkout(stash),
+            Action::Branch { worktree } => self.branch(worktree),
+        }
+    }
+
+    // Check out the selected pull request in the local clone of its repo.
+    // Uncommitted changes are refused, unless the user chooses to stash them.
+    pub(super) fn checkout(&mut self, stash: bool) {
+        let Some(issue) = self
+            .selected_issue()
+            .filter(|i| i.typename == IssueKind::PullRequest)
+        else {
+            return;
+        };
+        let Some(repo) = issue.repo() else {
+            return;
+        };
+        let head = PullHead {
+            repo: repo.clone(),
+            number: issue.number,
+            url: issue.url.clone(),
+            branch: issue.head_ref_name.clone(),
+            head_url: issue.head_repository.as_ref().map(|r| r.url.clone()),
+        };
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let Some(dir) = open::find_checkout(&self.config, &repo, &cwd) else {
+            self.notice = Some(Line::from(format!("No local clone of {repo}")).red());
+            return;
+        };
+        match git::is_dirty(&dir) {
+            Ok(true) if !stash => {
+                let item = MenuItem {
+                    label: "Stash them and check out".to_string(),
+                    description: format!("as {}", head.local_branch()),
+                    value: Action::Checkout { stash: true }.into(),
+                };
+                let title = format!("Uncommitted changes in {}", dir.display());
+                self.menu = Some(Menu::new(title, vec![item]));
+                return;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                self.notice = Some(Line::from(format!("{err:#}")).red());
+                return;
+            }
+        }
+        self.notice = Some(Line::from(format!("Checking out #{}…", head.number)).dim());
+        self.spawn(async move {
+            let number = head.number;
+            // git may take a while to fetch
+            let result =
+                tokio::task::spawn_blocking(move || git::checkout_pull(&dir, &head, stash))
+                    .await
+                    .context("Checking out")
+                    .and_then(|result| result)
+                    .map(|branch| format!("Checked out #{number} as {branch}"));
+            Message::CheckedOut(result)
+        });
+    }
+
+    // The local clone of the selected issue's repo, the branch for it, and where a worktree for
+    // the branch would go.
+    fn issue_branch(&mut self) -> Option<(PathBuf, String, PathBuf)> {
+        let issue = self
+            .selected_issue()
+            .filter(|i| i.typename == IssueKind::Issue)?;
+        let repo = issue.repo()?;
+        let template = self.config.branch_template.as_deref();
+        let template = template.unwrap_or(git::DEFAULT_BRANCH_TEMPLATE);
+        let branch = git::branch_name(template, issue.number, &issue.title);
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let Some(dir) = open::find_checkout(&self.config, &repo, &cwd) else {
+            self.notice = Some(Line::from(format!("No local clone of {repo}")).red());
+            return None;
+        };
+        let worktree = git::worktree_path(&dir, &branch);
+        Some((dir, branch, worktree))
+    }
+
+    // Offer to start work on the selected issue in a branch or a worktree.
+    pub(super) fn open_branch_menu(&mut self) {
+        let Some((dir, branch, worktree)) = self.issue_branch() else {
+            return;
+        };
+        let items = vec![
+            MenuItem {
+                label: "Branch".to_string(),
+                description: format!("{branch} in {}", dir.display()),
+                value: Action::Branch { worktree: false }.into(),
+            },
+            MenuItem {
+                label: "Worktree".to_string(),
+                description: format!("{branch} in {}", worktree.display()),
+                value: Action::Branch { worktree: true }.into(),
+            },
+        ];
+        let title = format!("Work on #{}", self.selected_issue().map_or(0, |i| i.number));
+        self.menu = Some(Menu::new(title, items));
+    }
+
+    // Create the branch for the selected issue and check it out, recording the issue it is for.
+    fn branch(&mut self, worktree: bool) {
+        let Some((dir, branch, _)) = self.issue_branch() else {
+            return;
+        };
+        let Some(issue) = self.selected_issue() else {
+            return;
+        };
+        let (repo, url) = (issue.repo().unwrap_or_default(), issue.url.clone());
+        self.notice = Some(Line::from(format!("Creating {branch}…")).dim());
+        self.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                git::branch_for_issue(&dir, &repo, &branch, &url, worktree)
+                    .map(|path| format!("Checked out {branch} in {}", path.display()))
+            })
+            .await
+            .context("Creating branch")
+            .and_then(|result| result);
+            Message::CheckedOut(result)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::KeyCode;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_checkout() {
+        let mut server = Server::new_async().await;
+        let _search = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let root = tempfile::tempdir().unwrap();
+        let work = crate::git::tests::clone_with_pull(root.path(), "octocat/Hello-World", 2648);
+        let mut config = Config::default();
+        config
+            .checkouts
+            .insert("octocat/Hello-World".into(), work.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, config, QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+
+        // issues can't be checked out
+        app.update(Message::Key(KeyCode::Char('C').into())).unwrap();
+        assert!(app.notice.is_none());
+
+        // uncommitted changes are only stashed if chosen
+        std::fs::write(work.join("file.txt"), "changed").unwrap();
+        for c in ['j', 'C'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("Stash them and check out"), "{screen}");
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.menu.is_none());
+        assert_eq!(
+            std::fs::read_to_string(work.join("file.txt")).unwrap(),
+            "changed"
+        );
+
+        app.update(Message::Key(KeyCode::Char('C').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        while app
+            .notice
+            .as_ref()
+            .is_none_or(|n| n.to_string().ends_with('…'))
+        {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("Checked out #2648 as pr-2648"), "{screen}");
+        assert_eq!(
+            std::fs::read_to_string(work.join("file.txt")).unwrap(),
+            "topic"
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_branch() {
+        let mut server = Server::new_async().await;
+        let _search = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let root = tempfile::tempdir().unwrap();
+        let work = crate::git::tests::clone_with_pull(root.path(), "octocat/Hello-World", 2648);
+        let mut config = Config::default();
+        config
+            .checkouts
+            .insert("octocat/Hello-World".into(), work.clone());
+        config.branch_template = Some("issue/{number}-{slug}".into());
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, config, QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+
+        app.update(Message::Key(KeyCode::Char('B').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("Work on #2356"), "{screen}");
+        for key in [KeyCode::Char('j'), KeyCode::Enter] {
+            app.update(Message::Key(key.into())).unwrap();
+        }
+        while app
+            .notice
+            .as_ref()
+            .is_none_or(|n| n.to_string().ends_with('…'))
+        {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let worktree = root.path().join("work-issue-2356-terraform-aws-code");
+        assert_eq!(
+            app.notice.as_ref().unwrap().to_string(),
+            format!(
+                "Checked out issue/2356-terraform-aws-code in {}",
+                worktree.display()
+            )
+        );
+        assert_eq!(
+            crate::git::current_issue(&worktree).unwrap().as_deref(),
+            Some("https://github.com/octocat/Hello-World/issues/2356")
+        );
+
+        // pull requests are checked out instead
+        for c in ['j', 'B'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        assert!(app.menu.is_none());
+    }
+}
@@ -0,0 +1,413 @@
+use super::{App, Areas, Tab};
+use crate::github::checks::{self as ci, CheckRun};
+use crate::github::issues::IssueKind;
+use crate::tui::checks::{self, ChecksView, Log};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::Frame;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The check runs of pull requests, and the one being read.
+#[derive(Default)]
+pub(super) struct State {
+    // check runs on the head commit of each pull request, by url, or why they couldn't be fetched
+    pub(super) runs: HashMap<String, Result<Arc<Vec<CheckRun>>, String>>,
+    pub(super) requested: HashSet<String>,
+    pub(super) view: ChecksView,
+}
+
+/// Results of fetching and re-running the checks of pull requests.
+#[derive(Debug)]
+pub enum Message {
+    // the check runs on the head commit of a pull request
+    Runs(String, Result<Vec<CheckRun>>),
+    // the log of a GitHub Actions job
+    Log(u64, Result<String>),
+    // the failed jobs of a pull request's workflow run were re-run
+    Rerun { url: String, result: Result<()> },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Checks(msg)
+    }
+}
+
+impl App {
+    pub(super) fn update_checks(&mut self, msg: Message) {
+        match msg {
+            Message::Runs(url, result) => {
+                if let Err(err) = &result {
+                    tracing::error!("Failed to fetch checks of {url}: {err:?}");
+                }
+                let checks = result.map(Arc::new).map_err(|err| format!("{err:#}"));
+                self.checks.runs.insert(url, checks);
+                self.dirty = true;
+            }
+            Message::Log(job_id, result) => {
+                if let Some(log) = self.checks.view.log.as_mut().filter(|l| l.job_id == job_id) {
+                    if let Err(err) = &result {
+                        tracing::error!("Failed to fetch log of job {job_id}: {err:?}");
+                    }
+                    log.load(result.map_err(|err| format!("{err:#}")));
+                    self.dirty = true;
+                }
+            }
+            Message::Rerun { url, result } => {
+                self.rerun_started(url, result);
+                self.dirty = true;
+            }
+        }
+    }
+
+    // The pull request whose checks are shown, if any.
+    pub(super) fn checks_url(&self) -> Option<&str> {
+        if self.tab != Tab::Checks || self.composer.is_some() {
+            return None;
+        }
+        self.selected_issue()
+            .filter(|i| i.typename == IssueKind::PullRequest)
+            .map(|i| i.url.as_str())
+    }
+
+    // The check runs of the pull request whose checks are shown, once fetched.
+    fn shown_checks(&self) -> Option<&Result<Arc<Vec<CheckRun>>, String>> {
+        self.checks.runs.get(self.checks_url()?)
+    }
+
+    // The selected check run.
+    fn selected_run(&self) -> Option<&CheckRun> {
+        match self.shown_checks()? {
+            Ok(runs) => runs.get(self.checks.view.selected),
+            Err(_) => None,
+        }
+    }
+
+    // Fetch the check runs on the head commit of the selected pull request,
+    // if they haven't been already.
+    pub(super) fn request_checks(&mut self) {
+        let Some(url) = self.checks_url().map(str::to_string) else {
+            return;
+        };
+        if self.checks.requested.insert(url.clone()) {
+            self.fetch_checks(url);
+        }
+    }
+
+    fn fetch_checks(&mut self, url: String) {
+        let Some(issue) = self.issues.iter().find(|i| i.url == url) else {
+            return;
+        };
+        let (Some(repo), Some(head)) = (issue.repo(), issue.head()) else {
+            self.checks
+                .runs
+                .insert(url, Err("No commits to check".to_string()));
+            return;
+        };
+        let (github, sha) = (self.github.clone(), head.oid.clone());
+        self.spawn(async move {
+            let runs = ci::fetch_check_runs(&github, &repo, &sha).await;
+            Message::Runs(url, runs)
+        });
+    }
+
+    // The check runs of a pull request, or the log of the selected one.
+    pub(super) fn draw_checks(&self, frame: &mut Frame, block: Block, area: Rect) {
+        let hints = match &self.checks.view.log {
+            Some(_) => "j/k: scroll  g/G: top/bottom  R: re-run failed  esc: back",
+            None => "enter: log  J/K: run  R: re-run failed",
+        };
+        let block = block.title_bottom(Line::from(hints).dim());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let runs = match self.shown_checks() {
+            None => {
+                frame.render_widget(Line::from("Loading checks…").dim(), inner);
+                return;
+            }
+            Some(Err(err)) => {
+                let text = Paragraph::new(err.as_str()).wrap(Wrap { trim: false });
+                frame.render_widget(text.red(), inner);
+                return;
+            }
+            Some(Ok(runs)) if runs.is_empty() => {
+                frame.render_widget(Line::from("No checks").dim(), inner);
+                return;
+            }
+            Some(Ok(runs)) => runs,
+        };
+        let Some(log) = &self.checks.view.log else {
+            let lines = checks::run_list(runs, self.checks.view.selected, chrono::Utc::now());
+            // keep the selected run in view
+            let offset =
+                (self.checks.view.selected as u16).saturating_sub(inner.height.saturating_sub(1));
+            frame.render_widget(Paragraph::new(lines).scroll((offset, 0)), inner);
+            return;
+        };
+        match &log.lines {
+            None => frame.render_widget(
+                Line::from(format!("Loading log of {}…", log.name)).dim(),
+                inner,
+            ),
+            Some(Err(err)) => {
+                let text = Paragraph::new(err.as_str()).wrap(Wrap { trim: false });
+                frame.render_widget(text.red(), inner);
+            }
+            Some(Ok(_)) => {
+                let text = Paragraph::new(log.text()).scroll((log.scroll as u16, 0));
+                frame.render_widget(text, inner);
+            }
+        }
+    }
+
+    // Keys for choosing a check run and reading its log.
+    // Returns false if the key isn't one of them.
+    pub(super) fn handle_checks_key(&mut self, key_event: KeyEvent) -> bool {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let inner = Block::bordered().inner(Areas::new(self.area).preview);
+        let half_page = (inner.height / 2).max(1) as isize;
+        if let Some(log) = &mut self.checks.view.log {
+            match key_event.code {
+                KeyCode::Char('j') => log.scroll(1),
+                KeyCode::Char('k') => log.scroll(-1),
+                KeyCode::Char('d') if ctrl => log.scroll(half_page),
+                KeyCode::Char('u') if ctrl => log.scroll(-half_page),
+                KeyCode::Char('g') => log.scroll(isize::MIN / 2),
+                KeyCode::Char('G') => log.scroll(isize::MAX / 2),
+                KeyCode::Char('R') => self.rerun_failed(),
+                KeyCode::Esc | KeyCode::Char('q') => self.checks.view.log = None,
+                _ => return false,
+            }
+            return true;
+        }
+        let runs = match self.shown_checks() {
+            Some(Ok(runs)) => runs.len(),
+            _ => 0,
+        };
+        match key_event.code {
+            KeyCode::Char('J') => {
+                self.checks.view.selected =
+                    (self.checks.view.selected + 1).min(runs.saturating_sub(1))
+            }
+            KeyCode::Char('K') => {
+                self.checks.view.selected = self.checks.view.selected.saturating_sub(1)
+            }
+            KeyCode::Enter => self.open_log(),
+            KeyCode::Char('R') => self.rerun_failed(),
+            _ => return false,
+        }
+        true
+    }
+
+    // Fetch and show the log of the selected run, if it is a GitHub Actions job.
+    fn open_log(&mut self) {
+        let Some(run) = self.selected_run() else {
+            return;
+        };
+        if !run.is_job() {
+            self.notice = Some(Line::from(format!("{} has no log to show", run.name)).dim());
+            return;
+        }
+        let log = Log::new(run);
+        let Some((repo, _)) = self.pull_request(&self.checks.view.url) else {
+            return;
+        };
+        let (github, job_id) = (self.github.clone(), log.job_id);
+        self.checks.view.log = Some(log);
+        self.spawn(async move {
+            let log = ci::fetch_log(&github, &repo, job_id).await;
+            Message::Log(job_id, log)
+        });
+    }
+
+    // Re-run the failed jobs of the selected run's workflow.
+    fn rerun_failed(&mut self) {
+        let Some(run) = self.selected_run() else {
+            return;
+        };
+        if !run.is_job() || !run.is_failed() {
+            self.notice =
+                Some(Line::from(format!("{} has no failed jobs to re-run", run.name)).dim());
+            return;
+        }
+        let url = self.checks.view.url.clone();
+        let Some((repo, _)) = self.pull_request(&url) else {
+            return;
+        };
+        let (github, job_id) = (self.github.clone(), run.id);
+        self.notice = Some(Line::from(format!("Re-running {}…", run.name)).dim());
+        self.spawn_limited(async move {
+            let result = ci::rerun_failed_jobs(&github, &repo, job_id).await;
+            Message::Rerun { url, result }
+        });
+    }
+
+    // The failed jobs were re-run, so fetch the checks again to show them running.
+    fn rerun_started(&mut self, url: String, result: Result<()>) {
+        self.notice = Some(match result {
+            Ok(()) => {
+                self.fetch_checks(url);
+                Line::from("Re-running failed jobs").dim()
+            }
+            Err(err) => {
+                tracing::error!("Failed to re-run jobs of {url}: {err:?}");
+                Line::from(format!("{err:#}")).red()
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::{Column, QueryArgs};
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_checks() {
+        let mut server = Server::new_async().await;
+        let _search = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        // once to show them, then again after re-running
+        let runs = server
+            .mock(
+                "GET",
+                "/repos/octocat/Hello-World/commits/6dcb09b5b57875f334f61aebed695e2e4193db5e/check-runs",
+            )
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/check_runs.json").unwrap())
+            .expect(2)
+            .create_async()
+            .await;
+        let mut log: String = (1..=20)
+            .map(|i| format!("2024-05-01T12:34:{i:02}.0Z \x1b[32mstep {i}\x1b[0m\n"))
+            .collect();
+        log.push_str("2024-05-01T12:35:00.0Z error[E0308]: mismatched types\n");
+        let log_mock = server
+            .mock(
+                "GET",
+                "/repos/octocat/Hello-World/actions/jobs/399444496/logs",
+            )
+            .with_status(200)
+            .with_body(log)
+            .create_async()
+            .await;
+        let job = server
+            .mock("GET", "/repos/octocat/Hello-World/actions/jobs/399444496")
+            .with_status(200)
+            .with_body(r#"{"id":399444496,"run_id":29679449}"#)
+            .create_async()
+            .await;
+        let rerun = server
+            .mock(
+                "POST",
+                "/repos/octocat/Hello-World/actions/runs/29679449/rerun-failed-jobs",
+            )
+            .with_status(201)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let args = QueryArgs {
+            columns: vec![Column::Checks, Column::Title],
+            ..Default::default()
+        };
+        let mut app = App::new(github, Config::default(), args, history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("✗ changed a bit of code"), "{screen}");
+
+        for c in ['j', '['] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        assert_eq!(app.checks_url(), Some(app.issues[1].url.as_str()));
+        while app.shown_checks().is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains(" Body  Diff  Checks "), "{screen}");
+        assert!(
+            screen.contains("✗ test      failure     2m 13s"),
+            "{screen}"
+        );
+        assert!(screen.contains("• coverage  in progress"), "{screen}");
+
+        // only Actions jobs have logs
+        app.update(Message::Key(KeyCode::Char('J').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(app.checks.view.log.is_none());
+        assert!(render(&mut app).contains("coverage has no log to show"));
+
+        // the log opens at the first error
+        app.update(Message::Key(KeyCode::Char('K').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        while app.checks.view.log.as_ref().unwrap().lines.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        log_mock.assert_async().await;
+        let screen = render(&mut app);
+        assert!(screen.contains("step 18"), "{screen}");
+        assert!(screen.contains("error[E0308]: mismatch"), "{screen}");
+        assert!(!screen.contains("step 17"), "{screen}");
+        assert!(!screen.contains("2024-05-01"), "{screen}");
+        app.update(Message::Key(KeyCode::Char('g').into())).unwrap();
+        assert!(render(&mut app).contains("step 1 "));
+        // the selection doesn't move while reading
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        assert_eq!(app.table_state.selected(), Some(1));
+
+        app.update(Message::Key(KeyCode::Char('R').into())).unwrap();
+        while app
+            .notice
+            .as_ref()
+            .is_none_or(|n| n.to_string().ends_with('…'))
+        {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        job.assert_async().await;
+        rerun.assert_async().await;
+        assert!(render(&mut app).contains("Re-running failed jobs"));
+        while !runs.matched_async().await {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.checks.view.log.is_none());
+        assert!(!app.exit);
+    }
+}
@@ -0,0 +1,241 @@
+use super::App;
+use crate::github::issues::Issue;
+use crate::github::mutation;
+use crate::open;
+use crate::tui::bulk::Batch;
+use crate::tui::compose::{self, Composer, Draft};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
+/// Results of posting comments.
+#[derive(Debug)]
+pub enum Message {
+    Posted {
+        batch: Option<usize>,
+        id: String,
+        number: u32,
+        result: Result<String>,
+    },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Composer(msg)
+    }
+}
+
+impl App {
+    pub(super) fn update_composer(&mut self, msg: Message) {
+        match msg {
+            Message::Posted {
+                batch,
+                id,
+                number,
+                result,
+            } => self.posted(batch, id, number, result),
+        }
+        self.dirty = true;
+    }
+
+    // Edit a comment on the marked or selected issues.
+    pub(super) fn compose(&mut self) {
+        if self.targets().is_empty() {
+            return;
+        }
+        let Some(dir) = self.ensure_drafts_dir() else {
+            return;
+        };
+        let issues: Vec<&Issue> = self
+            .targets()
+            .into_iter()
+            .map(|i| &self.issues[i])
+            .collect();
+        let composer = Composer::new(&dir, &issues);
+        self.edit_draft(composer);
+    }
+
+    pub(super) fn ensure_drafts_dir(&mut self) -> Option<PathBuf> {
+        match self.drafts_dir.clone().map_or_else(compose::drafts_dir, Ok) {
+            Ok(dir) => Some(dir),
+            Err(err) => {
+                self.notice = Some(Line::from(format!("Failed to create drafts: {err}")).red());
+                None
+            }
+        }
+    }
+
+    pub(super) fn edit_draft(&mut self, composer: Composer) {
+        self.command = Some(open::editor_command(
+            open::editor().as_deref(),
+            &composer.path,
+            None,
+        ));
+        self.composer = Some(composer);
+    }
+
+    // The editor closed, so preview the comment before posting it.
+    pub(super) fn edited(&mut self, status: Result<ExitStatus>) {
+        let Some(composer) = &mut self.composer else {
+            return;
+        };
+        let result = status.and_then(|status| {
+            anyhow::ensure!(status.success(), "Editor exited with {status}");
+            composer.load()
+        });
+        match result {
+            Ok(true) => {}
+            Ok(false) if composer.may_be_empty() => {}
+            Ok(false) => {
+                composer.discard();
+                self.composer = None;
+                self.notice = Some(Line::from("Empty comment, not posted").dim());
+            }
+            Err(err) => {
+                tracing::error!("Failed to edit comment: {err:?}");
+                self.notice = Some(
+                    Line::from(format!("{err}, draft kept in {}", composer.path.display())).red(),
+                );
+                self.composer = None;
+            }
+        }
+    }
+
+    // Post the comment on each of its subjects, tracking progress if there are several.
+    fn post_comment(&mut self) {
+        let Some(composer) = &mut self.composer else {
+            return;
+        };
+        composer.posting = true;
+        let (subjects, body) = (composer.subjects.clone(), composer.body.clone());
+        match composer.draft.clone() {
+            Draft::Comment => {}
+            Draft::Merge { method, auto } => {
+                self.send_merge(method, auto, body);
+                return;
+            }
+            draft => {
+                self.post_review(draft, body);
+                return;
+            }
+        }
+        let batch = (subjects.len() > 1).then(|| {
+            self.batches += 1;
+            let description = format!("Commented on {} items", subjects.len());
+            self.batch = Some(Batch::new(self.batches, description, subjects.len()));
+            self.batches
+        });
+        for subject in subjects {
+            let github = self.github.clone();
+            let body = body.clone();
+            self.spawn_limited(async move {
+                let result = mutation::add_comment(&github, &subject.id, &body).await;
+                Message::Posted {
+                    batch,
+                    id: subject.id,
+                    number: subject.number,
+                    result,
+                }
+            });
+        }
+    }
+
+    fn posted(&mut self, batch: Option<usize>, id: String, number: u32, result: Result<String>) {
+        if let Some(batch) = batch {
+            if let Err(err) = &result {
+                tracing::error!("Failed to comment on #{number}: {err:?}");
+            }
+            let result = result.map(drop).map_err(|err| format!("{err:#}"));
+            self.record(batch, &id, number, result);
+            return;
+        }
+        let Some(composer) = self.composer.take() else {
+            return;
+        };
+        self.notice = Some(match result {
+            Ok(url) => {
+                composer.discard();
+                Line::from(format!("Posted {url}")).dim()
+            }
+            Err(err) => {
+                tracing::error!("Failed to post comment: {err:?}");
+                Line::from(format!(
+                    "Failed to post comment, draft kept in {}: {err}",
+                    composer.path.display()
+                ))
+                .red()
+            }
+        });
+    }
+
+    pub(super) fn handle_composer_key(&mut self, key_event: KeyEvent) {
+        let Some(composer) = &self.composer else {
+            return;
+        };
+        if composer.posting {
+            return;
+        }
+        match key_event.code {
+            KeyCode::Char('y') => self.post_comment(),
+            KeyCode::Char('e') => {
+                self.command = Some(open::editor_command(
+                    open::editor().as_deref(),
+                    &composer.path,
+                    None,
+                ))
+            }
+            KeyCode::Char('n') => {
+                composer.discard();
+                self.composer = None;
+                self.notice = Some(Line::from("Comment discarded").dim());
+            }
+            KeyCode::Esc => {
+                self.notice =
+                    Some(Line::from(format!("Draft kept in {}", composer.path.display())).dim());
+                self.composer = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{mock_search, new_app, render, run_until};
+    use super::super::Message;
+    use super::*;
+    use crate::config::Config;
+    use mockito::Server;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_compose() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+
+        // the mock server can't post comments, so the draft should be kept
+        let drafts = tempfile::tempdir().unwrap();
+        app.drafts_dir = Some(drafts.path().to_path_buf());
+        app.update(Message::Key(KeyCode::Char('c').into())).unwrap();
+        assert!(app.command.take().is_some());
+        let draft = drafts.path().join("octocat_Hello-World_2648.md");
+        std::fs::write(&draft, "**LGTM**").unwrap();
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("Comment on #2648"), "{screen}");
+        assert!(screen.contains("LGTM"), "{screen}");
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        run_until(&mut app, |app| app.composer.is_none()).await;
+        assert!(draft.exists());
+        let screen = render(&mut app);
+        assert!(screen.contains("Failed to post comment"), "{screen}");
+    }
+}
@@ -0,0 +1,394 @@
+use super::{App, Areas, Tab};
+use crate::github::issues::IssueKind;
+use crate::github::pulls::{self, PullFile};
+use crate::tui::compose::Draft;
+use crate::tui::diff::{self, DiffView};
+use crate::tui::preview::HighlightedDiff;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::Frame;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+// the narrowest preview that diffs are shown side by side in
+const MIN_SIDE_BY_SIDE_WIDTH: u16 = 100;
+
+/// The files changed by pull requests, and how the shown diff is being read.
+pub(super) struct State {
+    // files changed by each pull request, by url, or why they couldn't be fetched
+    pub(super) files: HashMap<String, Result<Arc<Vec<PullFile>>, String>>,
+    pub(super) requested: HashSet<String>,
+    pub(super) view: DiffView,
+    // whether diffs are shown side by side when the preview is wide enough
+    pub(super) side_by_side: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            files: HashMap::new(),
+            requested: HashSet::new(),
+            view: DiffView::default(),
+            side_by_side: true,
+        }
+    }
+}
+
+/// Results of fetching the diffs of pull requests.
+#[derive(Debug)]
+pub enum Message {
+    // the files changed by a pull request
+    Files(String, Result<Vec<PullFile>>),
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Diff(msg)
+    }
+}
+
+impl App {
+    pub(super) fn update_diff(&mut self, msg: Message) {
+        match msg {
+            Message::Files(url, result) => {
+                if let Err(err) = &result {
+                    tracing::error!("Failed to fetch files of {url}: {err:?}");
+                }
+                let files = result.map(Arc::new).map_err(|err| format!("{err:#}"));
+                self.diff.files.insert(url, files);
+            }
+        }
+        self.dirty = true;
+    }
+
+    // The pull request whose diff is shown, if any.
+    pub(super) fn diff_url(&self) -> Option<&str> {
+        if self.tab != Tab::Diff || self.composer.is_some() {
+            return None;
+        }
+        self.selected_issue()
+            .filter(|i| i.typename == IssueKind::PullRequest)
+            .map(|i| i.url.as_str())
+    }
+
+    pub(super) fn is_side_by_side(&self) -> bool {
+        self.diff.side_by_side && self.preview_width() >= MIN_SIDE_BY_SIDE_WIDTH
+    }
+
+    // The files changed by the pull request whose diff is shown, once fetched.
+    pub(super) fn diff_files(&self) -> Option<&Result<Arc<Vec<PullFile>>, String>> {
+        self.diff.files.get(self.diff_url()?)
+    }
+
+    // Fetch the files changed by the selected pull request and its review threads,
+    // if they haven't been already.
+    pub(super) fn request_files(&mut self) {
+        let Some(url) = self.selected_issue().map(|i| i.url.clone()) else {
+            return;
+        };
+        let Some((repo, number)) = self.pull_request(&url) else {
+            return;
+        };
+        if self.diff.requested.insert(url.clone()) {
+            let github = self.github.clone();
+            let (url, repo) = (url.clone(), repo.clone());
+            self.spawn(async move {
+                let files = pulls::fetch_files(&github, &repo, number).await;
+                Message::Files(url, files)
+            });
+        }
+        if self.reviews.requested.insert(url.clone()) {
+            self.fetch_review(url, repo, number);
+        }
+    }
+
+    // The file of the shown diff, and its highlighted patch once ready.
+    pub(super) fn shown_diff(&self) -> Option<(&PullFile, &HighlightedDiff)> {
+        let Some(Ok(files)) = self.diff_files() else {
+            return None;
+        };
+        let file = files.get(self.diff.view.file)?;
+        Some((file, self.diffs.get(&self.preview_key()?)?))
+    }
+
+    // The lines of the shown diff with its review threads, and where each of its rows is.
+    pub(super) fn diff_display(&self) -> Option<(Vec<Line<'static>>, Vec<usize>)> {
+        let (file, diff) = self.shown_diff()?;
+        let lines = diff::parse(file.patch.as_deref().unwrap_or_default());
+        let threads = self.file_threads(file);
+        Some(diff::layout(
+            diff,
+            &lines,
+            &threads,
+            &self.diff.view,
+            self.preview_width(),
+        ))
+    }
+
+    // How many lines of the diff fit below the list of files.
+    fn diff_height(&self) -> u16 {
+        let files = match self.diff_files() {
+            Some(Ok(files)) => files.len(),
+            _ => 0,
+        };
+        let inner = Block::bordered().inner(Areas::new(self.area).preview);
+        diff_areas(inner, files)[1].height
+    }
+
+    // The changed files above the diff of the chosen one.
+    pub(super) fn draw_diff(&self, frame: &mut Frame, block: Block, area: Rect) {
+        let hints = match (self.diff.view.focused, self.diff_review()) {
+            (true, _) => "c: comment  r: reply  R: resolve  v: range  S: submit  esc: back",
+            (false, Some(review)) if review.pending.is_some() => {
+                "enter: review  S: submit pending review"
+            }
+            (false, _) => "enter: review  J/K: file  D: side by side",
+        };
+        let block = block.title_bottom(Line::from(hints).dim());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let files = match self.diff_files() {
+            None => {
+                frame.render_widget(Line::from("Loading files…").dim(), inner);
+                return;
+            }
+            Some(Err(err)) => {
+                let text = Paragraph::new(err.as_str()).wrap(Wrap { trim: false });
+                frame.render_widget(text.red(), inner);
+                return;
+            }
+            Some(Ok(files)) => files,
+        };
+        let [list_area, diff_area] = diff_areas(inner, files.len());
+        // keep the chosen file in view
+        let offset =
+            (self.diff.view.file as u16).saturating_sub(list_area.height.saturating_sub(1));
+        let list = Paragraph::new(diff::file_list(files, self.diff.view.file)).scroll((offset, 0));
+        frame.render_widget(list, list_area);
+
+        if let Some((lines, _)) = self.diff_display() {
+            let hunks = Paragraph::new(lines).scroll((self.diff.view.scroll, 0));
+            frame.render_widget(hunks, diff_area);
+        }
+    }
+
+    // Scroll the diff so the cursor's row, and the threads under it, are in view.
+    fn scroll_to_cursor(&mut self) {
+        let Some((lines, positions)) = self.diff_display() else {
+            return;
+        };
+        let Some(&top) = positions.get(self.diff.view.cursor) else {
+            return;
+        };
+        let bottom = positions
+            .get(self.diff.view.cursor + 1)
+            .copied()
+            .unwrap_or(lines.len())
+            .saturating_sub(1);
+        let height = self.diff_height().max(1) as usize;
+        let scroll = self.diff.view.scroll as usize;
+        if top < scroll {
+            self.diff.view.scroll = top as u16;
+        } else if bottom >= scroll + height {
+            self.diff.view.scroll = top.min(bottom + 1 - height) as u16;
+        }
+    }
+
+    // Scroll the diff by `lines`, moving the cursor along if it would leave the view.
+    fn scroll_diff(&mut self, lines: isize) {
+        let Some((shown, positions)) = self.diff_display() else {
+            return;
+        };
+        let max = shown.len().saturating_sub(1);
+        let scroll = (self.diff.view.scroll as isize + lines).clamp(0, max as isize) as usize;
+        self.diff.view.scroll = scroll as u16;
+        let height = self.diff_height().max(1) as usize;
+        let visible = |&(_, &p): &(usize, &usize)| (scroll..scroll + height).contains(&p);
+        if positions
+            .get(self.diff.view.cursor)
+            .is_some_and(|p| !(scroll..scroll + height).contains(p))
+        {
+            let mut rows = positions.iter().enumerate().filter(visible);
+            let row = if lines > 0 {
+                rows.next()
+            } else {
+                rows.next_back()
+            };
+            if let Some((row, _)) = row {
+                self.diff.view.cursor = row;
+            }
+        }
+    }
+
+    // Keys for moving around the diff of a pull request, and reviewing it once focused.
+    // Returns false if the key isn't one of them.
+    pub(super) fn handle_diff_key(&mut self, key_event: KeyEvent) -> bool {
+        let files = match self.diff_files() {
+            Some(Ok(files)) => files.len(),
+            _ => 0,
+        };
+        let rows = self.shown_diff().map_or(0, |(_, diff)| diff.rows.len());
+        let half_page = (self.diff_height() / 2).max(1) as isize;
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let focused = self.diff.view.focused;
+        match key_event.code {
+            KeyCode::Char('J') => self
+                .diff
+                .view
+                .select_file((self.diff.view.file + 1).min(files.saturating_sub(1))),
+            KeyCode::Char('K') => self
+                .diff
+                .view
+                .select_file(self.diff.view.file.saturating_sub(1)),
+            KeyCode::Char('d') if ctrl => self.scroll_diff(half_page),
+            KeyCode::Char('u') if ctrl => self.scroll_diff(-half_page),
+            KeyCode::Char('D') => {
+                self.diff.side_by_side = !self.diff.side_by_side;
+                if self.diff.side_by_side && self.preview_width() < MIN_SIDE_BY_SIDE_WIDTH {
+                    self.notice =
+                        Some(Line::from("Too narrow to show the diff side by side").dim());
+                }
+                // the rows differ between the layouts
+                (
+                    self.diff.view.scroll,
+                    self.diff.view.cursor,
+                    self.diff.view.anchor,
+                ) = (0, 0, None);
+            }
+            KeyCode::Char('S') => self.open_review_menu(),
+            KeyCode::Enter if !focused => {
+                self.diff.view.focused = true;
+                self.scroll_diff(0);
+            }
+            _ if !focused || ctrl => return false,
+            KeyCode::Char('j') => {
+                self.diff.view.cursor = (self.diff.view.cursor + 1).min(rows.saturating_sub(1));
+                self.scroll_to_cursor();
+            }
+            KeyCode::Char('k') => {
+                self.diff.view.cursor = self.diff.view.cursor.saturating_sub(1);
+                self.scroll_to_cursor();
+            }
+            KeyCode::Char('v') => {
+                self.diff.view.anchor = match self.diff.view.anchor {
+                    Some(_) => None,
+                    None => Some(self.diff.view.cursor),
+                }
+            }
+            KeyCode::Char('c') => self.comment_on_lines(),
+            KeyCode::Char('r') => {
+                if let Some(thread) = self.thread_at_cursor() {
+                    let thread_id = thread.id.clone();
+                    self.compose_review(Draft::Reply { thread_id });
+                }
+            }
+            KeyCode::Char('R') => self.toggle_resolved(),
+            KeyCode::Esc if self.diff.view.anchor.is_some() => self.diff.view.anchor = None,
+            KeyCode::Esc => self.diff.view.focused = false,
+            _ => return false,
+        }
+        true
+    }
+}
+
+// The list of files above the diff, showing up to a third of the files.
+fn diff_areas(inner: Rect, files: usize) -> [Rect; 2] {
+    let list_height = (files as u16).min(inner.height / 3).max(1);
+    Layout::vertical([Constraint::Length(list_height), Constraint::Fill(1)]).areas(inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_diff() {
+        let mut server = Server::new_async().await;
+        let _search = server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await;
+        let files = server
+            .mock("GET", "/repos/octocat/Hello-World/pulls/2648/files")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/pull_files.json").unwrap())
+            .expect(1)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+
+        // issues only have a body
+        app.update(Message::Key(KeyCode::Char(']').into())).unwrap();
+        assert!(app.diff_url().is_none());
+        assert!(!render(&mut app).contains(" Diff "));
+
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        assert_eq!(app.diff_url(), Some(app.issues[1].url.as_str()));
+        while app.shown_diff().is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        files.assert_async().await;
+        let screen = render(&mut app);
+        assert!(screen.contains(" Body  Diff "), "{screen}");
+        assert!(screen.contains("M src/main.rs +2 -1"), "{screen}");
+        assert!(screen.contains("A logo.png +0 -0"), "{screen}");
+        assert!(screen.contains("   1    1  fn main() {"), "{screen}");
+        assert!(screen.contains("   2      -    println!"), "{screen}");
+
+        // the preview is too narrow for the side by side view
+        assert!(!app.is_side_by_side());
+        app.update(Message::Key(KeyCode::Char('J').into())).unwrap();
+        assert_eq!(app.diff.view.file, 1);
+        app.update(Message::Key(KeyCode::Char('J').into())).unwrap();
+        assert_eq!(app.diff.view.file, 1);
+        while app.shown_diff().is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("No diff for binary"), "{screen}");
+
+        // the position is kept until another pull request is shown
+        app.update(Message::Key(KeyCode::Char('k').into())).unwrap();
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        assert_eq!(app.diff.view.file, 1);
+        app.update(Message::Key(KeyCode::Char('K').into())).unwrap();
+        assert_eq!(app.diff.view.file, 0);
+
+        app.update(Message::Key(KeyCode::Char('[').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("i made changes"), "{screen}");
+    }
+}
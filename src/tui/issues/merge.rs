@@ -0,0 +1,396 @@
+use super::App;
+use crate::github::issues::{IssueKind, IssueState};
+use crate::github::merge::{self, MergeMethod, MergeStatus};
+use crate::tui::compose::{Composer, Draft};
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::Result;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+
+/// Results of merging pull requests.
+#[derive(Debug)]
+pub enum Message {
+    // whether a pull request can be merged, and how
+    Status(String, Result<MergeStatus>),
+    // a pull request was merged, or its auto-merge changed
+    Merged {
+        id: String,
+        description: String,
+        // whether it was merged now, rather than set to merge later
+        merged: bool,
+        result: Result<()>,
+    },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Merge(msg)
+    }
+}
+
+/// Ways of merging the selected pull request.
+#[derive(Clone, Debug)]
+pub(super) enum Action {
+    Merge { method: MergeMethod, auto: bool },
+    DisableAutoMerge,
+}
+
+impl From<Action> for super::Action {
+    fn from(action: Action) -> Self {
+        Self::Merge(action)
+    }
+}
+
+impl App {
+    pub(super) fn update_merge(&mut self, msg: Message) {
+        match msg {
+            Message::Status(url, result) => self.open_merge_menu(url, result),
+            Message::Merged {
+                id,
+                description,
+                merged,
+                result,
+            } => self.merged(id, description, merged, result),
+        }
+        self.dirty = true;
+    }
+
+    pub(super) fn apply_merge(&mut self, action: Action) {
+        match action {
+            Action::Merge { method, auto } => self.start_merge(method, auto),
+            Action::DisableAutoMerge => self.disable_auto_merge(),
+        }
+    }
+
+    // Fetch whether the selected pull request can be merged, to offer the ways it can be.
+    pub(super) fn request_merge_status(&mut self) {
+        let Some(issue) = self
+            .selected_issue()
+            .filter(|i| i.typename == IssueKind::PullRequest)
+        else {
+            return;
+        };
+        let number = issue.number;
+        match issue.state {
+            IssueState::Open => {}
+            IssueState::Closed => {
+                self.notice = Some(Line::from(format!("#{number} is closed")).dim());
+                return;
+            }
+            IssueState::Merged => {
+                self.notice = Some(Line::from(format!("#{number} is already merged")).dim());
+                return;
+            }
+        }
+        let Some(repo) = issue.repo() else {
+            return;
+        };
+        let (github, url) = (self.github.clone(), issue.url.clone());
+        self.notice = Some(Line::from(format!("Checking whether #{number} can be merged…")).dim());
+        self.spawn(async move {
+            let status = merge::fetch_merge_status(&github, &repo, number).await;
+            Message::Status(url, status)
+        });
+    }
+
+    // Offer the ways the pull request can be merged, showing what it still needs.
+    fn open_merge_menu(&mut self, url: String, result: Result<MergeStatus>) {
+        // the user may have moved on while the status was fetched
+        if self.selected_issue().is_none_or(|i| i.url != url)
+            || self.menu.is_some()
+            || self.composer.is_some()
+        {
+            return;
+        }
+        let status = match result {
+            Ok(status) => status,
+            Err(err) => {
+                tracing::error!("Failed to fetch merge status of {url}: {err:?}");
+                self.notice = Some(Line::from(format!("{err:#}")).red());
+                return;
+            }
+        };
+        let number = status.number;
+        self.notice = status
+            .problem()
+            .map(|problem| Line::from(format!("#{number} can't be merged yet: {problem}")).red());
+        let mut items: Vec<_> = status
+            .methods
+            .iter()
+            .map(|&method| MenuItem {
+                label: method.name().to_string(),
+                description: String::new(),
+                value: Action::Merge {
+                    method,
+                    auto: false,
+                }
+                .into(),
+            })
+            .collect();
+        match &status.auto_merge_request {
+            Some(request) => items.push(MenuItem {
+                label: "Disable auto-merge".to_string(),
+                description: format!("set to {}", request.merge_method.name().to_lowercase()),
+                value: Action::DisableAutoMerge.into(),
+            }),
+            None if status.auto_merge_allowed => {
+                items.extend(status.methods.iter().map(|&method| MenuItem {
+                    label: format!("{} when ready", method.name()),
+                    description: "auto-merge".to_string(),
+                    value: Action::Merge { method, auto: true }.into(),
+                }))
+            }
+            None => {}
+        }
+        if items.is_empty() {
+            self.notice = Some(Line::from(format!("#{number} can't be merged")).red());
+            return;
+        }
+        let title = format!("Merge #{number}: {}", status.summary());
+        self.menu = Some(Menu::new(title, items));
+        self.merge_status = Some(status);
+    }
+
+    // Edit the message of the merge commit, starting from the one GitHub would use,
+    // or merge right away if there's no commit to write a message for.
+    fn start_merge(&mut self, method: MergeMethod, auto: bool) {
+        let Some(status) = &self.merge_status else {
+            return;
+        };
+        if !method.has_message() {
+            self.send_merge(method, auto, String::new());
+            return;
+        }
+        let message = status.default_message(method);
+        let id = status.id.clone();
+        let Some(dir) = self.ensure_drafts_dir() else {
+            return;
+        };
+        let Some(issue) = self.issues.iter().find(|i| i.id == id) else {
+            return;
+        };
+        let composer = Composer::review(&dir, issue, Draft::Merge { method, auto });
+        // a draft left from before is kept
+        if !composer.path.exists() {
+            if let Err(err) = std::fs::write(&composer.path, message) {
+                self.notice = Some(Line::from(format!("Failed to write draft: {err}")).red());
+                return;
+            }
+        }
+        self.edit_draft(composer);
+    }
+
+    pub(super) fn send_merge(&mut self, method: MergeMethod, auto: bool, message: String) {
+        let Some(status) = &self.merge_status else {
+            return;
+        };
+        let (id, number) = (status.id.clone(), status.number);
+        let description = match (auto, method) {
+            (true, _) => format!("#{number} will be merged once it can be"),
+            (false, MergeMethod::Merge) => format!("Merged #{number}"),
+            (false, MergeMethod::Squash) => format!("Squashed and merged #{number}"),
+            (false, MergeMethod::Rebase) => format!("Rebased and merged #{number}"),
+        };
+        let github = self.github.clone();
+        self.notice = Some(Line::from(format!("{}…", method.name())).dim());
+        self.spawn_limited(async move {
+            let result = match auto {
+                true => merge::enable_auto_merge(&github, &id, method, &message).await,
+                false => merge::merge(&github, &id, method, &message).await,
+            };
+            Message::Merged {
+                id,
+                description,
+                merged: !auto,
+                result,
+            }
+        });
+    }
+
+    fn disable_auto_merge(&mut self) {
+        let Some(status) = &self.merge_status else {
+            return;
+        };
+        let (id, number) = (status.id.clone(), status.number);
+        let github = self.github.clone();
+        self.spawn_limited(async move {
+            let result = merge::disable_auto_merge(&github, &id).await;
+            Message::Merged {
+                id,
+                description: format!("Disabled auto-merge of #{number}"),
+                merged: false,
+                result,
+            }
+        });
+    }
+
+    fn merged(&mut self, id: String, description: String, merged: bool, result: Result<()>) {
+        // the draft of the commit message, if there was one
+        let composer = self.composer.take_if(|c| c.posting);
+        self.notice = Some(match result {
+            Ok(()) => {
+                if let Some(composer) = composer {
+                    composer.discard();
+                }
+                if let Some(issue) = self
+                    .issues
+                    .iter_mut()
+                    .find(|i| i.id == id)
+                    .filter(|_| merged)
+                {
+                    issue.state = IssueState::Merged;
+                }
+                Line::from(description).dim()
+            }
+            Err(err) => {
+                tracing::error!("Failed to merge {id}: {err:?}");
+                match composer {
+                    Some(composer) => Line::from(format!(
+                        "{err:#}, draft kept in {}",
+                        composer.path.display()
+                    )),
+                    None => Line::from(format!("{err:#}")),
+                }
+                .red()
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::KeyCode;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_merge() {
+        async fn respond(server: &mut Server, pattern: &str, body: &str) -> mockito::Mock {
+            server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::Regex(pattern.into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await
+        }
+        let mut server = Server::new_async().await;
+        let issues = std::fs::read_to_string("testdata/issues2.json").unwrap();
+        let _search = respond(&mut server, r"search\(", &issues).await;
+        let status = std::fs::read_to_string("testdata/merge_status.json").unwrap();
+        let fetch = respond(&mut server, "mergeCommitAllowed", &status)
+            .await
+            .expect(2);
+        let rebase = respond(
+            &mut server,
+            r#""mergeMethod":"REBASE""#,
+            r#"{"data":{"mergePullRequest":null},"errors":[{"type":"UNPROCESSABLE","path":["mergePullRequest"],"message":"Base branch was modified. Review and try the merge again."}]}"#,
+        )
+        .await;
+        let squash = respond(
+            &mut server,
+            r#""mergeMethod":"SQUASH","commitHeadline":"changed a bit of code \(#2648\)","commitBody":"i made changes""#,
+            r#"{"data":{"mergePullRequest":{"pullRequest":{"id":"PR_kwDOAAABLM2648"}}}}"#,
+        )
+        .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        let drafts = tempfile::tempdir().unwrap();
+        app.drafts_dir = Some(drafts.path().to_path_buf());
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+
+        // issues can't be merged
+        app.update(Message::Key(KeyCode::Char('M').into())).unwrap();
+        assert!(app.notice.is_none());
+
+        for c in ['j', 'M'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        while app.menu.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("Merge #2648: mergeable"), "{screen}");
+        assert!(screen.contains("Squash and merge when ready"), "{screen}");
+        assert_eq!(
+            app.notice.as_ref().unwrap().to_string(),
+            "#2648 can't be merged yet: an approving review is required"
+        );
+
+        // rebasing has no message to write, and GitHub's refusal is shown
+        for key in [KeyCode::Char('j'), KeyCode::Char('j'), KeyCode::Enter] {
+            app.update(Message::Key(key.into())).unwrap();
+        }
+        assert!(app.command.is_none());
+        while app
+            .notice
+            .as_ref()
+            .is_none_or(|n| n.to_string().ends_with('…'))
+        {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        rebase.assert_async().await;
+        assert_eq!(
+            app.notice.as_ref().unwrap().to_string(),
+            "Merging: Base branch was modified. Review and try the merge again."
+        );
+        assert_eq!(app.issues[1].state, IssueState::Open);
+
+        // squashing starts from GitHub's message
+        app.update(Message::Key(KeyCode::Char('M').into())).unwrap();
+        while app.menu.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        fetch.assert_async().await;
+        for key in [KeyCode::Char('j'), KeyCode::Enter] {
+            app.update(Message::Key(key.into())).unwrap();
+        }
+        assert!(app.command.take().is_some());
+        let composer = app.composer.as_ref().unwrap();
+        assert_eq!(composer.title(), "Squash and merge #2648");
+        assert_eq!(
+            std::fs::read_to_string(&composer.path).unwrap(),
+            "changed a bit of code (#2648)\n\ni made changes\n"
+        );
+        let path = composer.path.clone();
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        while app.composer.is_some() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        squash.assert_async().await;
+        assert_eq!(
+            app.notice.as_ref().unwrap().to_string(),
+            "Squashed and merged #2648"
+        );
+        assert_eq!(app.issues[1].state, IssueState::Merged);
+        assert!(!path.exists());
+    }
+}
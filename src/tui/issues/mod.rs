@@ -0,0 +1,1514 @@
+use self::bulk_actions::Picker;
+use super::bulk::Batch;
+use super::checks::ChecksView;
+use super::complete::Completion;
+use super::compose::{Composer, Draft};
+use super::diff::DiffView;
+use super::form::Form;
+use super::input::LineInput;
+use super::menu::{Menu, MenuEvent, MenuItem};
+use super::preview::{self, HighlightCache, HighlightedDiff, Highlighter};
+use crate::config::Config;
+use crate::github::issues::{self, Issue, IssueKind, RateBudget};
+use crate::github::issues::{CheckState, IssueState, User};
+use crate::github::merge::MergeStatus;
+use crate::github::mutation::Change;
+use crate::github::reactions::{self as reaction, Comment, ReactionGroup};
+use crate::github::repo::{self, RepoMetadata};
+use crate::github::review::ReviewEvent;
+use crate::github::template::Template;
+use crate::github::Github;
+use crate::history::History;
+use crate::query::{self, Expansion, Query};
+use crate::{clipboard, open};
+use crate::{Column, Expanded, QueryArgs};
+use anyhow::{Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::{Stream, StreamExt as _};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect, Size},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::PathBuf,
+    process::{Command, ExitStatus},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+        Semaphore,
+    },
+    time::Interval,
+};
+
+mod bulk_actions;
+mod checkout;
+mod checks_tab;
+mod composer;
+mod diff_view;
+mod merge;
+mod new_issue;
+mod reactions;
+mod review;
+
+const PAGE_SIZE: u32 = 16;
+pub(super) const TICK_RATE: Duration = Duration::from_millis(100);
+pub(super) const SPINNER: [&str; 8] = ["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"];
+// GitHub's secondary rate limits punish bursts of concurrent mutations,
+// so changes to many items are queued beyond this many requests.
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Everything that can change the state of the app.
+#[derive(Debug)]
+pub enum Message {
+    Key(KeyEvent),
+    Resize(Size),
+    Tick,
+    Issues(Vec<Issue>),
+    SearchDone,
+    Highlighted((String, u16)),
+    Metadata(String, Result<RepoMetadata>),
+    // an external program like $EDITOR finished
+    CommandDone(Result<ExitStatus>),
+    // the comments on an issue or pull request
+    Comments(String, Result<Vec<Comment>>),
+    // text was copied to the clipboard
+    Copied(String, Result<()>),
+    Bulk(bulk_actions::Message),
+    Composer(composer::Message),
+    NewIssue(new_issue::Message),
+    Diff(diff_view::Message),
+    Review(review::Message),
+    Checks(checks_tab::Message),
+    Checkout(checkout::Message),
+    Merge(merge::Message),
+    Reactions(reactions::Message),
+}
+
+/// What choosing a menu item does.
+#[derive(Clone, Debug)]
+enum Action {
+    SavedSearch(String),
+    Yank(String),
+    Change(Change),
+    Template { repo: String, template: Template },
+    Review(ReviewEvent),
+    Checkout(checkout::Action),
+    Merge(merge::Action),
+    React(reactions::Action),
+}
+
+/// What the preview shows of a pull request.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Tab {
+    #[default]
+    Body,
+    Diff,
+    Checks,
+}
+
+impl Tab {
+    const ALL: [Tab; 3] = [Tab::Body, Tab::Diff, Tab::Checks];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tab::Body => "Body",
+            Tab::Diff => "Diff",
+            Tab::Checks => "Checks",
+        }
+    }
+
+    // The tab `offset` places after this one, wrapping around.
+    fn cycle(self, offset: isize) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&t| t == self)
+            .unwrap_or_default() as isize;
+        Self::ALL[(index + offset).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+}
+
+pub(super) struct Areas {
+    pub(super) search: Rect,
+    pub(super) input: Rect,
+    pub(super) list: Rect,
+    pub(super) preview: Rect,
+}
+
+impl Areas {
+    pub(super) fn new(area: Rect) -> Self {
+        let [search, preview] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(area);
+
+        let [input, list] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(2), Constraint::Fill(1)])
+            .margin(1) // to account for the border we draw around everything
+            .areas(search);
+
+        Self {
+            search,
+            input,
+            list,
+            preview,
+        }
+    }
+}
+
+pub struct App {
+    github: Github,
+    config: Config,
+    // the query as typed, and with saved searches expanded
+    args: QueryArgs,
+    resolved: QueryArgs,
+    exit: bool,
+    dirty: bool,
+    area: Rect,
+    table_state: TableState,
+    issues: Vec<Issue>,
+    // urls of the loaded issues
+    seen: HashSet<String>,
+    tx: Sender<u32>,
+    rx: Receiver<Vec<Issue>>,
+    line_input: LineInput,
+    completion: Completion,
+    menu: Option<Menu<Action>>,
+    // a program to run in the terminal, such as $EDITOR, which the app is suspended for
+    command: Option<Command>,
+    composer: Option<Composer>,
+    // where drafts are kept, if not the default
+    drafts_dir: Option<PathBuf>,
+    // results of background work like mutations
+    tasks_tx: UnboundedSender<Message>,
+    tasks_rx: UnboundedReceiver<Message>,
+    // labels, milestones and users of each repo, fetched once when first needed
+    metadata: HashMap<String, Arc<RepoMetadata>>,
+    metadata_requested: HashSet<String>,
+    // a picker waiting for the metadata of the repo it edits
+    pending_picker: Option<(Picker, String)>,
+    // ids of the items operations apply to, instead of the selected one
+    marked: HashSet<String>,
+    // where a range being marked started
+    visual: Option<usize>,
+    // an operation on many items, shown until it finishes or its failures are dismissed
+    batch: Option<Batch>,
+    batches: usize,
+    // limits the mutations in flight at once
+    limiter: Arc<Semaphore>,
+    // printed once the app exits
+    output: Vec<String>,
+    // a new issue being filled in
+    form: Option<Form>,
+    // the repo templates are being fetched for, and the name of the one to use
+    pending_template: Option<(String, Option<String>)>,
+    // set when the app was started just to create an issue
+    exit_after_form: bool,
+    // why the app exited without doing what it was started for
+    failure: Option<String>,
+    tab: Tab,
+    diff: diff_view::State,
+    reviews: review::State,
+    checks: checks_tab::State,
+    // the pull request being merged, fetched when the choice of how to merge it is offered
+    merge_status: Option<MergeStatus>,
+    // comments on each issue, by url, or why they couldn't be fetched
+    comments: HashMap<String, Result<Vec<Comment>, String>>,
+    comments_requested: HashSet<String>,
+    // bumped for an issue when its comments load or its reactions change,
+    // so its preview is highlighted again
+    conversation_revisions: HashMap<String, usize>,
+    history: History,
+    highlighter: Arc<Highlighter>,
+    previews: HighlightCache<(String, u16), Text<'static>>, // (url, width)->body
+    diffs: HighlightCache<(String, u16), HighlightedDiff>,  // (url and file, width)->diff
+    pending_request: bool,
+    search_done: bool,
+    editing_query: bool,
+    // the result of the last action, shown in the status line until the next key
+    notice: Option<Line<'static>>,
+    // relative dates expanded in the running search, e.g. "updated:<2w → updated:<2024-05-17"
+    expansions: Vec<Expansion>,
+    // why the running search may not be what was meant
+    query_errors: Vec<query::Error>,
+    spinner: usize,
+}
+
+/// Give the terminal to another program, like `$EDITOR`, until it exits.
+/// The caller's event stream must be dropped first, or it reads the program's input.
+pub(super) fn suspend(terminal: &mut DefaultTerminal, mut command: Command) -> Result<ExitStatus> {
+    tracing::debug!("Running {command:?}");
+    ratatui::restore();
+    let status = command
+        .status()
+        .with_context(|| format!("Running {command:?}"));
+    // only the modes ratatui::init set, since running it again would add another panic hook
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
+    terminal.clear()?;
+    status
+}
+
+impl App {
+    pub fn new(
+        github: Github,
+        config: Config,
+        args: QueryArgs,
+        mut history: History,
+    ) -> Result<Self> {
+        let resolved = config.resolve(&args);
+        let (tx, rx, expanded) = Self::start_search(&github, &resolved);
+        let (_, query_errors) = Query::parse(&args.query);
+        if let Err(err) = history.add(&args.query) {
+            tracing::error!("Adding to the history: {err:#}");
+        }
+        let mut line_input =
+            LineInput::new(args.query.as_str()).with_highlighter(super::query::styles);
+        line_input.set_history(history.queries());
+        let (tasks_tx, tasks_rx) = mpsc::unbounded_channel();
+        let mut app = Self {
+            line_input,
+            completion: Completion::default(),
+            menu: None,
+            command: None,
+            composer: None,
+            drafts_dir: None,
+            tasks_tx,
+            tasks_rx,
+            metadata: HashMap::new(),
+            metadata_requested: HashSet::new(),
+            pending_picker: None,
+            marked: HashSet::new(),
+            visual: None,
+            batch: None,
+            batches: 0,
+            limiter: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            output: Vec::new(),
+            form: None,
+            pending_template: None,
+            exit_after_form: false,
+            failure: None,
+            tab: Tab::default(),
+            diff: diff_view::State::default(),
+            reviews: review::State::default(),
+            checks: checks_tab::State::default(),
+            merge_status: None,
+            comments: HashMap::new(),
+            comments_requested: HashSet::new(),
+            conversation_revisions: HashMap::new(),
+            history,
+            github,
+            config,
+            args,
+            resolved,
+            exit: false,
+            dirty: true,
+            area: Rect::default(),
+            table_state: TableState::default().with_selected(Some(0)),
+            highlighter: Arc::new(Highlighter::default()),
+            previews: HighlightCache::default(),
+            diffs: HighlightCache::default(),
+            issues: Vec::new(),
+            seen: HashSet::new(),
+            tx,
+            rx,
+            pending_request: false,
+            search_done: false,
+            editing_query: false,
+            notice: None,
+            expansions: expanded.expansions,
+            query_errors,
+            spinner: 0,
+        };
+        app.add_problems(expanded.problems);
+        Ok(app)
+    }
+
+    fn start_search(
+        github: &Github,
+        args: &QueryArgs,
+    ) -> (Sender<u32>, Receiver<Vec<Issue>>, Expanded) {
+        let (req_tx, req_rx) = mpsc::channel(16);
+        let (resp_tx, resp_rx) = mpsc::channel(16);
+        let mut expanded = args.expand(chrono::Local::now().date_naive());
+        issues::search_issues(
+            github.clone(),
+            std::mem::take(&mut expanded.queries),
+            RateBudget::default(),
+            req_rx,
+            resp_tx,
+        );
+        (req_tx, resp_rx, expanded)
+    }
+
+    // Report why the search may fail along with the problems of the query as typed,
+    // which may already include the same ones.
+    fn add_problems(&mut self, problems: Vec<String>) {
+        for message in problems {
+            if !self.query_errors.iter().any(|e| e.message == message) {
+                self.query_errors.push(query::Error {
+                    span: 0..self.args.query.len(),
+                    message,
+                });
+            }
+        }
+    }
+
+    pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let mut events = EventStream::new();
+        let mut ticks = tokio::time::interval(TICK_RATE);
+        self.update(Message::Resize(terminal.size()?))?;
+        while !self.exit {
+            if self.dirty {
+                terminal.draw(|frame| self.view(frame))?;
+                self.dirty = false;
+            }
+            let msg = self.next_message(&mut events, &mut ticks).await?;
+            self.update(msg)?;
+            if let Some(command) = self.command.take() {
+                drop(events);
+                let status = suspend(terminal, command);
+                events = EventStream::new();
+                self.update(Message::CommandDone(status))?;
+            }
+        }
+        match self.failure.take() {
+            Some(failure) => Err(anyhow::anyhow!(failure)),
+            None => Ok(()),
+        }
+    }
+
+    /// Lines to print after the terminal is restored, such as the urls of printed items.
+    pub fn output(&self) -> &[String] {
+        &self.output
+    }
+
+    // Run a future in the background, applying the message it produces when done.
+    fn spawn<M: Into<Message>>(&self, task: impl Future<Output = M> + Send + 'static) {
+        let tx = self.tasks_tx.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(task.await.into());
+        });
+    }
+
+    // Like spawn, for requests that count towards the limit on concurrent requests.
+    fn spawn_limited<M: Into<Message>>(&self, task: impl Future<Output = M> + Send + 'static) {
+        let limiter = self.limiter.clone();
+        self.spawn(async move {
+            let _permit = limiter.acquire().await;
+            task.await
+        });
+    }
+
+    /// Wait for the next thing that might change the state of the app.
+    async fn next_message(
+        &mut self,
+        events: &mut (impl Stream<Item = std::io::Result<Event>> + Unpin),
+        ticks: &mut Interval,
+    ) -> Result<Message> {
+        loop {
+            tracing::trace!("Awaiting message");
+            let msg = tokio::select! {
+                event = events.next() => match event.context("Event stream closed")?? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Message::Key(key),
+                    Event::Resize(width, height) => Message::Resize(Size::new(width, height)),
+                    _ => continue,
+                },
+                _ = ticks.tick() => Message::Tick,
+                issues = self.rx.recv(), if !self.search_done => match issues {
+                    Some(issues) => Message::Issues(issues),
+                    None => Message::SearchDone,
+                },
+                Some(key) = self.previews.recv() => Message::Highlighted(key),
+                Some(key) = self.diffs.recv() => Message::Highlighted(key),
+                Some(msg) = self.tasks_rx.recv() => msg,
+            };
+            return Ok(msg);
+        }
+    }
+
+    /// Apply a message to the app state.
+    pub fn update(&mut self, msg: Message) -> Result<()> {
+        match msg {
+            Message::Key(key_event) => {
+                self.notice = None;
+                self.handle_key_event(key_event)?;
+                self.dirty = true;
+            }
+            Message::Resize(size) => {
+                tracing::debug!("Resized to {size:?}");
+                self.area = Rect::new(0, 0, size.width, size.height);
+                self.dirty = true;
+            }
+            Message::Tick => {
+                // the only animation is the spinner shown while loading
+                self.spinner = (self.spinner + 1) % SPINNER.len();
+                self.dirty |= !self.search_done;
+            }
+            Message::Issues(issues) => {
+                self.pending_request = false;
+                // a search split across queries can find the same item more than once
+                let new = issues
+                    .into_iter()
+                    .filter(|i| self.seen.insert(i.url.clone()));
+                self.issues.extend(new);
+                tracing::debug!(
+                    "Pushing issues into list, total issues: {}",
+                    self.issues.len()
+                );
+                self.dirty = true;
+            }
+            Message::SearchDone => {
+                tracing::debug!("Search complete");
+                self.search_done = true;
+                self.dirty = true;
+            }
+            Message::Highlighted(key) => {
+                self.dirty |= self.preview_key().is_some_and(|k| k == key);
+            }
+            Message::Metadata(repo, Ok(metadata)) => {
+                tracing::debug!("Received metadata for {repo}");
+                self.metadata.insert(repo.clone(), Arc::new(metadata));
+                if self.editing_query {
+                    self.update_completion();
+                    self.dirty = true;
+                }
+                if let Some((picker, _)) =
+                    self.pending_picker.take_if(|(_, pending)| *pending == repo)
+                {
+                    self.open_picker(picker);
+                    self.dirty = true;
+                }
+            }
+            Message::Metadata(repo, Err(err)) => {
+                tracing::error!("Failed to fetch metadata for {repo}: {err:?}");
+                // allow retrying
+                self.metadata_requested.remove(&repo);
+                if self.pending_picker.take_if(|(_, r)| *r == repo).is_some() {
+                    self.notice = Some(Line::from(format!("Failed to fetch {repo}: {err}")).red());
+                    self.dirty = true;
+                }
+            }
+            Message::CommandDone(status) => {
+                match &mut self.form {
+                    Some(form) if form.is_editing() => {
+                        if let Err(err) = form.finish_edit(status) {
+                            tracing::error!("Failed to edit field: {err:?}");
+                            form.error = Some(format!("{err:#}"));
+                        }
+                    }
+                    _ => self.edited(status),
+                }
+                self.dirty = true;
+            }
+            Message::Comments(url, result) => {
+                if let Err(err) = &result {
+                    tracing::error!("Failed to fetch comments on {url}: {err:?}");
+                }
+                *self.conversation_revisions.entry(url.clone()).or_default() += 1;
+                self.comments
+                    .insert(url, result.map_err(|err| format!("{err:#}")));
+                self.dirty = true;
+            }
+            Message::Copied(text, result) => {
+                self.notice = Some(match result {
+                    Ok(()) => Line::from(format!("Copied {text}")).dim(),
+                    Err(err) => {
+                        tracing::error!("Failed to copy {text}: {err:?}");
+                        Line::from(format!("Failed to copy: {err}")).red()
+                    }
+                });
+                self.dirty = true;
+            }
+            Message::Bulk(msg) => self.update_bulk(msg),
+            Message::Composer(msg) => self.update_composer(msg),
+            Message::NewIssue(msg) => self.update_new_issue(msg),
+            Message::Diff(msg) => self.update_diff(msg),
+            Message::Review(msg) => self.update_review(msg),
+            Message::Checks(msg) => self.update_checks(msg),
+            Message::Checkout(msg) => self.update_checkout(msg),
+            Message::Merge(msg) => self.update_merge(msg),
+            Message::Reactions(msg) => self.update_reactions(msg),
+        }
+        self.fetch_more();
+        if let Some(url) = self.diff_url().filter(|&url| url != self.diff.view.url) {
+            self.diff.view = DiffView::new(url);
+        }
+        if let Some(url) = self.checks_url().filter(|&url| url != self.checks.view.url) {
+            self.checks.view = ChecksView::new(url);
+        }
+        self.request_preview();
+        Ok(())
+    }
+
+    // Request another page if the selection is near the end of the loaded items.
+    fn fetch_more(&mut self) {
+        if self.search_done || self.pending_request {
+            return;
+        }
+        let height = Areas::new(self.area).list.height as usize;
+        let selected = self.table_state.selected().unwrap_or_default();
+        if selected + height < self.issues.len() {
+            return;
+        }
+        tracing::debug!("Requesting more items");
+        match self.tx.try_send(PAGE_SIZE) {
+            Ok(()) => self.pending_request = true,
+            Err(mpsc::error::TrySendError::Full(_)) => tracing::debug!("Queue full"),
+            Err(mpsc::error::TrySendError::Closed(_)) => self.search_done = true,
+        }
+    }
+
+    fn selected_issue(&self) -> Option<&Issue> {
+        self.issues.get(self.table_state.selected()?)
+    }
+
+    fn select_next(&mut self) {
+        self.table_state.select_next();
+        // select_next lets the selection run past the end of the list
+        let last = self.issues.len().saturating_sub(1);
+        if self.table_state.selected().is_some_and(|i| i > last) {
+            self.table_state.select(Some(last));
+        }
+        tracing::debug!("Selected next index: {:?}", self.table_state.selected());
+    }
+
+    // What the preview shows: the comment being composed, or else the selected issue
+    // and its comments.
+    // Returns an id for the cache and the markdown to render.
+    fn preview_source(&self) -> Option<(String, Cow<'_, str>)> {
+        match &self.composer {
+            Some(composer) => Some((composer.preview_id(), composer.body.as_str().into())),
+            None => self.selected_issue().map(|i| {
+                let revision = self.conversation_revisions.get(&i.url).unwrap_or(&0);
+                let id = format!("{}:{revision}", i.url);
+                (id, conversation(i, self.comments.get(&i.url)).into())
+            }),
+        }
+    }
+
+    fn preview_width(&self) -> u16 {
+        Block::bordered().inner(Areas::new(self.area).preview).width
+    }
+
+    fn preview_key(&self) -> Option<(String, u16)> {
+        let width = self.preview_width();
+        if let Some(url) = self.diff_url() {
+            let key = format!(
+                "diff:{url}:{}:{}",
+                self.diff.view.file,
+                self.is_side_by_side()
+            );
+            return Some((key, width));
+        }
+        Some((self.preview_source()?.0, width))
+    }
+
+    // The repo and number of a loaded pull request.
+    fn pull_request(&self, url: &str) -> Option<(String, u32)> {
+        let issue = self.issues.iter().find(|i| i.url == url)?;
+        Some((issue.repo()?, issue.number))
+    }
+
+    fn request_comments(&mut self) {
+        let Some(issue) = self.selected_issue() else {
+            return;
+        };
+        let (Some(repo), url, number) = (issue.repo(), issue.url.clone(), issue.number) else {
+            return;
+        };
+        if !self.comments_requested.insert(url.clone()) {
+            return;
+        }
+        let github = self.github.clone();
+        self.spawn(async move {
+            let comments = reaction::fetch_comments(&github, &repo, number).await;
+            Message::Comments(url, comments)
+        });
+    }
+
+    // Start highlighting the selected item if it isn't cached yet.
+    fn request_preview(&mut self) {
+        if self.checks_url().is_some() {
+            self.request_checks();
+            return;
+        }
+        let Some(key) = self.preview_key() else {
+            return;
+        };
+        if self.diff_url().is_some() {
+            self.request_files();
+            if self.diffs.contains(&key) {
+                return;
+            }
+            let Some(Ok(files)) = self.diff_files() else {
+                return;
+            };
+            let Some(file) = files.get(self.diff.view.file).cloned() else {
+                return;
+            };
+            let (highlighter, width) = (self.highlighter.clone(), key.1);
+            let side_by_side = self.is_side_by_side();
+            self.diffs
+                .spawn(key, move || highlighter.diff(&file, width, side_by_side));
+            return;
+        }
+        if self.previews.contains(&key) {
+            return;
+        }
+        if self.composer.is_none() {
+            self.request_comments();
+        }
+        let Some(body) = self.preview_source().map(|(_, body)| body.into_owned()) else {
+            return;
+        };
+        let highlighter = self.highlighter.clone();
+        let width = key.1;
+        self.previews.spawn(key, move || {
+            Ok(preview::wrap(highlighter.markdown(&body)?, width))
+        });
+    }
+
+    /// Render the app state.
+    pub fn view(&mut self, frame: &mut Frame) {
+        tracing::debug!("Drawing");
+        let areas = Areas::new(frame.area());
+
+        frame.render_widget(
+            Block::bordered().title_bottom(self.status_line()),
+            areas.search,
+        );
+        self.line_input.draw(frame, areas.input);
+
+        let status = if self.search_done {
+            None
+        } else {
+            Some(Row::new(vec![
+                SPINNER[self.spinner].to_string(),
+                "loading".to_string(),
+            ]))
+        };
+        let columns = self.resolved.columns();
+        let table = Table::new(
+            self.issues
+                .iter()
+                .enumerate()
+                .map(|(index, i)| {
+                    let row = Row::new(columns.iter().map(|c| cell(i, *c)));
+                    if self.is_marked(index) {
+                        row.style(Style::new().yellow())
+                    } else {
+                        row
+                    }
+                })
+                .chain(status),
+            columns.iter().map(|c| width(*c)),
+        )
+        .row_highlight_style(Style::new().bold().reversed())
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, areas.list, &mut self.table_state);
+
+        match &mut self.form {
+            Some(form) => form.draw(frame, areas.preview),
+            None => self.draw_preview(frame, areas.preview),
+        }
+
+        if self.editing_query {
+            self.completion
+                .draw(frame, areas.input, self.line_input.pattern());
+        }
+        if let Some(menu) = &mut self.menu {
+            menu.draw(frame);
+        }
+        if let Some(batch) = &self.batch {
+            batch.draw(frame);
+        }
+    }
+
+    // Problems with the query being edited are shown below the results,
+    // otherwise the number of marked items and the relative dates that were expanded in the search.
+    fn status_line(&self) -> Line<'static> {
+        match self.query_errors.as_slice() {
+            [] if self.notice.is_some() => self.notice.clone().unwrap_or_default(),
+            [] => {
+                let marked = self.marked_indices().len();
+                let marked = (marked > 0).then(|| Span::from(format!("{marked} marked ")).yellow());
+                let expansions = self
+                    .expansions
+                    .iter()
+                    .map(|(from, to)| format!("{from} → {to}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Line::from_iter(marked.into_iter().chain([Span::from(expansions).dim()]))
+            }
+            [err] => Line::from(err.message.clone()).red(),
+            [err, rest @ ..] => {
+                Line::from(format!("{} (and {} more)", err.message, rest.len())).red()
+            }
+        }
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let Some((_, body)) = self.preview_source() else {
+            return;
+        };
+
+        let block = match &self.composer {
+            Some(composer) if composer.posting => {
+                Block::bordered().title(format!("Posting: {}", composer.title()))
+            }
+            Some(composer) => Block::bordered()
+                .title(composer.title())
+                .title_bottom("y: post  e: edit  n: discard  esc: keep draft"),
+            None => match self.selected_issue() {
+                Some(issue) if issue.typename == IssueKind::PullRequest => Block::bordered()
+                    .title(header(issue))
+                    .title(self.tabs().right_aligned()),
+                Some(issue) => Block::bordered().title(header(issue)),
+                None => Block::bordered(),
+            },
+        };
+        if self.diff_url().is_some() {
+            self.draw_diff(frame, block, area);
+            return;
+        }
+        if self.checks_url().is_some() {
+            self.draw_checks(frame, block, area);
+            return;
+        }
+        let preview = match self.preview_key().and_then(|key| self.previews.get(&key)) {
+            Some(text) => Paragraph::new(text.clone()),
+            // show the plain body until highlighting is done
+            None => Paragraph::new(preview::wrap(
+                Text::from(body.into_owned()),
+                self.preview_width(),
+            )),
+        };
+        frame.render_widget(preview.block(block), area);
+    }
+
+    // The names of the tabs of a pull request, with the shown one highlighted.
+    fn tabs(&self) -> Line<'static> {
+        Line::from_iter(Tab::ALL.into_iter().map(|tab| {
+            let name = Span::from(format!(" {} ", tab.name()));
+            if tab == self.tab {
+                name.reversed()
+            } else {
+                name
+            }
+        }))
+    }
+
+    // Fetch a repo's metadata in the background, if it hasn't been already.
+    fn request_metadata(&mut self, repo: &str) {
+        if !self.metadata_requested.insert(repo.to_string()) {
+            return;
+        }
+        let github = self.github.clone();
+        let repo = repo.to_string();
+        self.spawn(async move {
+            let metadata = repo::fetch_metadata(&github, &repo).await;
+            Message::Metadata(repo, metadata)
+        });
+    }
+
+    fn update_completion(&mut self) {
+        let metadata = self
+            .resolved
+            .single_repo()
+            .and_then(|repo| self.metadata.get(repo));
+        self.completion.update(
+            self.line_input.pattern(),
+            self.line_input.cursor_index(),
+            metadata.map(Arc::as_ref),
+        );
+    }
+
+    // Restart the search with the query from the input line.
+    fn submit_query(&mut self) {
+        let args = QueryArgs {
+            query: self.line_input.pattern().to_string(),
+            ..self.args.clone()
+        };
+        let resolved = self.config.resolve(&args);
+        (_, self.query_errors) = Query::parse(&args.query);
+        // a saved search may have moved the search to another repo
+        let repo = resolved.single_repo().map(str::to_string);
+        if repo.as_deref() != self.resolved.single_repo() {
+            if let Some(repo) = &repo {
+                self.request_metadata(repo);
+            }
+        }
+        (self.args, self.resolved) = (args, resolved);
+        tracing::debug!("Submitting query: {}", self.args.query);
+        self.line_input.push_history(&self.args.query);
+        if let Err(err) = self.history.add(&self.args.query) {
+            tracing::error!("Failed to save history: {err:?}");
+        }
+        let (tx, rx, expanded) = Self::start_search(&self.github, &self.resolved);
+        (self.tx, self.rx, self.expansions) = (tx, rx, expanded.expansions);
+        self.add_problems(expanded.problems);
+        self.issues.clear();
+        self.seen.clear();
+        self.marked.clear();
+        self.visual = None;
+        self.table_state.select(Some(0));
+        self.pending_request = false;
+        self.search_done = false;
+    }
+
+    fn open_saved_searches(&mut self) {
+        if self.config.searches.is_empty() {
+            tracing::debug!("No saved searches configured");
+            return;
+        }
+        let items = self
+            .config
+            .searches
+            .iter()
+            .map(|(name, search)| MenuItem {
+                label: format!("@{name}"),
+                description: search.query.clone(),
+                value: Action::SavedSearch(name.clone()),
+            })
+            .collect();
+        self.menu = Some(Menu::new("Saved searches", items));
+    }
+
+    // Ways of referring to the selected issue that can be copied.
+    fn open_yank_menu(&mut self) {
+        let Some(issue) = self.selected_issue() else {
+            return;
+        };
+        let mut choices = vec![
+            ("URL", issue.url.clone()),
+            ("Number", format!("#{}", issue.number)),
+        ];
+        if let Some(repo) = issue.repo() {
+            choices.push(("Reference", format!("{repo}#{}", issue.number)));
+        }
+        choices.push(("Markdown", format!("[{}]({})", issue.title, issue.url)));
+        let items = choices
+            .into_iter()
+            .map(|(label, text)| MenuItem {
+                label: label.to_string(),
+                description: text.clone(),
+                value: Action::Yank(text),
+            })
+            .collect();
+        self.menu = Some(Menu::new("Copy", items));
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Change(change) => self.change(change),
+            Action::Yank(text) => {
+                let copying = clipboard::copy(&text);
+                self.spawn(async move {
+                    let result = match copying {
+                        Ok(copying) => copying.await,
+                        Err(err) => Err(err),
+                    };
+                    Message::Copied(text, result)
+                });
+            }
+            Action::Template { repo, template } => self.form = Some(Form::new(repo, template)),
+            Action::Review(event) => self.compose_review(Draft::Review(event)),
+            Action::Checkout(action) => self.apply_checkout(action),
+            Action::Merge(action) => self.apply_merge(action),
+            Action::React(action) => self.apply_reaction(action),
+            Action::SavedSearch(name) => {
+                let len = self.line_input.pattern().len();
+                self.line_input.replace(0..len, &format!("@{name}"));
+                self.submit_query();
+            }
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<()> {
+        if let Some(menu) = &mut self.menu {
+            match menu.handle_key_event(key_event) {
+                MenuEvent::Pending => {}
+                MenuEvent::Cancelled => {
+                    self.menu = None;
+                    // the only menu before the form is the choice of template
+                    self.exit |= self.exit_after_form && self.form.is_none();
+                }
+                MenuEvent::Chosen(action) => {
+                    let index = menu.selected();
+                    self.menu = None;
+                    let picker = Picker::chosen_from(&action);
+                    self.apply(action);
+                    if let Some(picker) = picker {
+                        self.reopen_picker(picker, index);
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        // the progress of a batch is shown until it finishes and its failures are dismissed
+        if self.batch.is_some() {
+            self.handle_batch_key(key_event);
+            return Ok(());
+        }
+
+        if self.form.is_some() {
+            self.handle_form_key(key_event);
+            return Ok(());
+        }
+
+        if self.composer.is_some() {
+            self.handle_composer_key(key_event);
+            return Ok(());
+        }
+
+        // reading a log takes the keys that would otherwise move the selection
+        if self.checks.view.log.is_some() && self.checks_url().is_some() {
+            if !self.handle_checks_key(key_event)
+                && key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                self.exit = true;
+            }
+            return Ok(());
+        }
+
+        // reviewing a diff takes the keys that would otherwise move the selection
+        if self.diff.view.focused && self.diff_url().is_some() {
+            if !self.handle_diff_key(key_event)
+                && key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                self.exit = true;
+            }
+            return Ok(());
+        }
+
+        // the history search takes priority so it can use Enter and Esc
+        if self.editing_query && self.line_input.is_searching() {
+            self.line_input.handle_key_event(key_event);
+            if key_event.code == KeyCode::Enter {
+                self.editing_query = false;
+                self.submit_query();
+            }
+            return Ok(());
+        }
+
+        if self.editing_query && self.completion.is_visible() {
+            let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+            match key_event.code {
+                KeyCode::Tab => {
+                    if let Some((range, text)) = self.completion.selected() {
+                        let text = text.to_string();
+                        self.line_input.replace(range, &text);
+                    }
+                    self.update_completion();
+                    return Ok(());
+                }
+                KeyCode::Char('n') if ctrl => {
+                    self.completion.select_next();
+                    return Ok(());
+                }
+                KeyCode::Char('p') if ctrl => {
+                    self.completion.select_previous();
+                    return Ok(());
+                }
+                KeyCode::Esc => {
+                    self.completion.clear();
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        // these keys are handled regardless of whether we're editing the query
+        match key_event.code {
+            KeyCode::Esc if !self.editing_query && self.visual.is_some() => {
+                self.visual = None;
+                return Ok(());
+            }
+            KeyCode::Esc if !self.editing_query && !self.marked.is_empty() => {
+                self.marked.clear();
+                return Ok(());
+            }
+            KeyCode::Esc => {
+                tracing::debug!("Exit requested");
+                self.exit = true;
+                return Ok(());
+            }
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                tracing::debug!("Exit requested");
+                self.exit = true;
+                return Ok(());
+            }
+            KeyCode::Enter if self.editing_query => {
+                self.editing_query = false;
+                self.completion.clear();
+                self.submit_query();
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        if self.editing_query {
+            self.line_input.handle_key_event(key_event);
+            self.update_completion();
+            return Ok(());
+        }
+
+        // operations apply to the range being marked, which stays marked
+        if matches!(
+            key_event.code,
+            KeyCode::Char('c' | 'x' | 'l' | 'a' | 'm' | 'p')
+        ) {
+            self.commit_visual();
+        }
+
+        if self.diff_url().is_some() && self.handle_diff_key(key_event) {
+            return Ok(());
+        }
+        if self.checks_url().is_some() && self.handle_checks_key(key_event) {
+            return Ok(());
+        }
+
+        // these keys are only handled if not editing the query
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('[') => self.tab = self.tab.cycle(-1),
+            KeyCode::Char(']') => self.tab = self.tab.cycle(1),
+            KeyCode::Char('k') => {
+                self.table_state.select_previous();
+                tracing::debug!("Selected previous index: {:?}", self.table_state.selected());
+            }
+            KeyCode::Char('j') => self.select_next(),
+            KeyCode::Char(' ') | KeyCode::Tab => self.toggle_mark(),
+            KeyCode::Char('v') => match self.visual {
+                Some(_) => self.commit_visual(),
+                None => self.visual = self.table_state.selected(),
+            },
+            KeyCode::Char('a') if ctrl => self.toggle_all(),
+            KeyCode::Char('/') => {
+                tracing::debug!("Editing query");
+                self.editing_query = true;
+                if let Some(repo) = self.resolved.single_repo().map(str::to_string) {
+                    self.request_metadata(&repo);
+                }
+            }
+            KeyCode::Char('s') => self.open_saved_searches(),
+            KeyCode::Char('y') => self.open_yank_menu(),
+            KeyCode::Char('c') => self.compose(),
+            KeyCode::Char('x') => self.open_state_menu(),
+            KeyCode::Char('l') => self.open_picker(Picker::Labels),
+            KeyCode::Char('a') => self.open_picker(Picker::Assignees),
+            KeyCode::Char('m') => self.open_picker(Picker::Milestone),
+            KeyCode::Char('n') => self.new_issue(None),
+            KeyCode::Char('C') => self.checkout(false),
+            KeyCode::Char('B') => self.open_branch_menu(),
+            KeyCode::Char('M') => self.request_merge_status(),
+            KeyCode::Char('r') => self.open_reaction_menu(),
+            KeyCode::Char('p') => {
+                let targets = self.targets();
+                self.output = targets
+                    .iter()
+                    .map(|&i| self.issues[i].url.clone())
+                    .collect();
+                self.exit = true;
+            }
+            KeyCode::Char('o') => {
+                if let Some(url) = self.selected_issue().map(|i| i.url.clone()) {
+                    if let Err(err) = open::open_url(&url) {
+                        tracing::error!("Failed to open {url}: {err:?}");
+                        self.notice =
+                            Some(Line::from(format!("Failed to open {url}: {err}")).red());
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn cell(issue: &Issue, column: Column) -> Span<'static> {
+    match column {
+        Column::Number => issue.number.to_string().into(),
+        Column::Title => issue.title.clone().into(),
+        Column::Author => issue
+            .author
+            .as_ref()
+            .map(|a| a.login.clone())
+            .unwrap_or_default()
+            .into(),
+        Column::Kind => match issue.typename {
+            IssueKind::Issue => "issue".into(),
+            IssueKind::PullRequest => "pr".into(),
+        },
+        Column::Checks => match issue.check_state() {
+            Some(CheckState::Success) => Span::raw("✓").green(),
+            Some(CheckState::Failure | CheckState::Error) => Span::raw("✗").red(),
+            Some(CheckState::Pending | CheckState::Expected) => Span::raw("•").yellow(),
+            None => Span::default(),
+        },
+    }
+}
+
+// The login of a user, or "ghost" for deleted accounts.
+pub(super) fn author(user: &Option<User>) -> &str {
+    user.as_ref().map_or("ghost", |u| u.login.as_str())
+}
+
+// The markdown of an issue's body followed by its comments, each with a summary of its reactions.
+pub(super) fn conversation(
+    issue: &Issue,
+    comments: Option<&Result<Vec<Comment>, String>>,
+) -> String {
+    fn push_reactions(text: &mut String, groups: &[ReactionGroup]) {
+        let summary = reaction::summary(groups);
+        if !summary.is_empty() {
+            text.push_str(&format!("\n\n{summary}"));
+        }
+    }
+    let mut text = issue.body.clone();
+    push_reactions(&mut text, &issue.reaction_groups);
+    match comments {
+        Some(Ok(comments)) => {
+            for comment in comments {
+                text.push_str(&format!(
+                    "\n\n---\n\n**{}** commented on {}:\n\n{}",
+                    author(&comment.author),
+                    comment.created_at.format("%Y-%m-%d"),
+                    comment.body
+                ));
+                push_reactions(&mut text, &comment.reaction_groups);
+            }
+        }
+        Some(Err(err)) => text.push_str(&format!("\n\n---\n\nFailed to load comments: {err}")),
+        None => {}
+    }
+    text
+}
+
+// The state and metadata of an issue, e.g. "open · bug · @octocat · v1.0".
+pub(super) fn header(issue: &Issue) -> Line<'static> {
+    let state = match issue.state {
+        IssueState::Open => Span::raw("open").green(),
+        IssueState::Closed => Span::raw("closed").red(),
+        IssueState::Merged => Span::raw("merged").magenta(),
+    };
+    let labels = issue.labels.nodes.iter().map(|l| l.name.clone());
+    let assignees = issue
+        .assignees
+        .nodes
+        .iter()
+        .map(|a| format!("@{}", a.login));
+    let milestone = issue.milestone.iter().map(|m| m.title.clone());
+    let mut spans = vec![state];
+    for part in [
+        labels.collect::<Vec<_>>(),
+        assignees.collect(),
+        milestone.collect(),
+    ] {
+        if !part.is_empty() {
+            spans.push(Span::raw(" · "));
+            spans.push(Span::raw(part.join(", ")));
+        }
+    }
+    Line::from(spans)
+}
+
+fn width(column: Column) -> Constraint {
+    match column {
+        Column::Number => Constraint::Max(8),
+        Column::Title => Constraint::Fill(1),
+        Column::Author => Constraint::Max(16),
+        Column::Kind => Constraint::Length(5),
+        Column::Checks => Constraint::Length(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::SavedSearch;
+    use mockito::Server;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    pub(super) fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| app.view(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(80)
+            .map(|line| line.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // A server answering every search with the two items of testdata/issues2.json.
+    pub(super) async fn mock_search(server: &mut Server) -> mockito::Mock {
+        server
+            .mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/issues2.json").unwrap())
+            .create_async()
+            .await
+    }
+
+    // An app searching `server` for `query`, keeping its history in `dir`.
+    pub(super) fn new_app(
+        server: &Server,
+        config: Config,
+        query: &str,
+        dir: &std::path::Path,
+    ) -> App {
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let history = History::load(dir.join("history"), "issues", None).unwrap();
+        let args = QueryArgs {
+            query: query.into(),
+            ..Default::default()
+        };
+        let mut app = App::new(github, config, args, history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        app
+    }
+
+    // Apply the results of the app's background work until `done` holds.
+    pub(super) async fn run_until(app: &mut App, done: impl Fn(&App) -> bool) {
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !done(app) {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_search() {
+        let mut server = Server::new_async().await;
+        let mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+        mock.assert_async().await;
+
+        let screen = render(&mut app);
+        assert!(screen.contains("foo"), "{screen}");
+        assert!(screen.contains(">2356     Terraform AWS CODE"), "{screen}");
+        assert!(
+            screen.contains(" 2648     changed a bit of code"),
+            "{screen}"
+        );
+        assert!(!screen.contains("loading"), "{screen}");
+
+        // results repeated by another query of the same search are dropped
+        app.update(Message::Issues(app.issues.clone())).unwrap();
+        assert_eq!(app.issues.len(), 2);
+
+        assert!(!app.exit);
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.exit);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_paging() {
+        let mut server = Server::new_async().await;
+        let mut mocks = Vec::new();
+        for (after, file) in [
+            ("", "testdata/issues1.json"),
+            ("Y3Vyc29yOjI=", "testdata/issues2.json"),
+        ] {
+            let mock = server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::PartialJsonString(format!(
+                    r#"{{"variables":{{"after":"{after}"}}}}"#
+                )))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(std::fs::read_to_string(file).unwrap())
+                .create_async()
+                .await;
+            mocks.push(mock);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+
+        // the first page doesn't fill the list, so the next one is requested
+        run_until(&mut app, |app| app.search_done).await;
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+        let numbers: Vec<u32> = app.issues.iter().map(|i| i.number).collect();
+        assert_eq!(numbers, vec![3556, 3564, 2356, 2648]);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_preview() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+
+        // the selection stops at the last item
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        assert_eq!(app.table_state.selected(), Some(1));
+        let key = app.preview_key().unwrap();
+        run_until(&mut app, |app| app.previews.get(&key).is_some()).await;
+        let screen = render(&mut app);
+        assert!(
+            screen.contains(">2648     changed a bit of code"),
+            "{screen}"
+        );
+        assert!(screen.contains("i made changes"), "{screen}");
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_resize() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+        let (url, width) = app.preview_key().unwrap();
+        assert_eq!(width, 38);
+
+        // the preview is highlighted again for the new width
+        app.dirty = false;
+        app.update(Message::Resize(Size::new(120, 30))).unwrap();
+        assert!(app.dirty);
+        assert_eq!(app.area, Rect::new(0, 0, 120, 30));
+        let key = app.preview_key().unwrap();
+        assert_eq!(key, (url, 58));
+        assert!(app.previews.contains(&key));
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_yank() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(
+            screen.contains("Reference  octocat/Hello-World#2648"),
+            "{screen}"
+        );
+        assert!(
+            screen.contains("Markdown  [changed a bit of code]("),
+            "{screen}"
+        );
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(app.menu.is_none());
+        assert!(!app.exit);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_query_edit() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, Config::default(), "foo", dir.path());
+        run_until(&mut app, |app| app.search_done).await;
+
+        app.update(Message::Key(KeyCode::Char('/').into())).unwrap();
+        for c in " bar closed:today".chars() {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(!app.editing_query);
+        assert_eq!(app.args.query, "foo bar closed:today");
+        assert_eq!(app.history.queries(), vec!["foo", "foo bar closed:today"]);
+        let screen = render(&mut app);
+        assert!(screen.contains("closed:today → closed:20"), "{screen}");
+        // the new search starts from scratch
+        assert!(app.issues.is_empty());
+        assert!(!app.search_done);
+
+        // sorted queries over the limits can't be split, which is reported with the query errors
+        app.update(Message::Key(KeyCode::Char('/').into())).unwrap();
+        for c in " sort:updated (a OR b OR c OR d OR e OR f OR g)".chars() {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        let errors: Vec<_> = app.query_errors.iter().map(|e| &e.message).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "queries can have at most 5 AND, OR and NOT operators",
+                "sorted queries aren't split, and can have at most 5 AND, OR and NOT operators"
+            ]
+        );
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_saved_searches() {
+        let mut server = Server::new_async().await;
+        let _mock = mock_search(&mut server).await;
+        let mut config = Config::default();
+        config.searches.insert(
+            "triage".into(),
+            SavedSearch {
+                query: "is:open no:label".into(),
+                columns: Some(vec![Column::Kind, Column::Title]),
+                ..Default::default()
+            },
+        );
+        config.searches.insert(
+            "work".into(),
+            SavedSearch {
+                query: "assignee:@me".into(),
+                repo: Some("foo/bar".into()),
+                ..Default::default()
+            },
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = new_app(&server, config, "foo", dir.path());
+
+        app.update(Message::Key(KeyCode::Char('s').into())).unwrap();
+        assert!(render(&mut app).contains("@triage  is:open no:label"));
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(app.menu.is_none());
+        assert_eq!(app.args.query, "@triage");
+        assert_eq!(app.resolved.query, "is:open no:label");
+        assert_eq!(app.resolved.columns(), &[Column::Kind, Column::Title]);
+
+        // the saved search moves the search to its repo, so the repo's labels are fetched
+        app.update(Message::Key(KeyCode::Char('/').into())).unwrap();
+        for c in " @work @nope".chars() {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert_eq!(app.resolved.query, "is:open no:label assignee:@me @nope");
+        assert_eq!(app.resolved.repo, vec!["foo/bar"]);
+        assert!(app.metadata_requested.contains("foo/bar"));
+    }
+}
@@ -0,0 +1,348 @@
+use super::{Action, App};
+use crate::github::issues::{self, Issue};
+use crate::github::mutation::{self, Created};
+use crate::github::repo;
+use crate::github::template::{self, Templates};
+use crate::open;
+use crate::tui::compose;
+use crate::tui::form::{Form, FormEvent};
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::Result;
+use crossterm::event::KeyEvent;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use std::sync::Arc;
+
+// possible duplicates shown before creating an issue
+const MAX_DUPLICATES: u32 = 5;
+
+/// Results of the requests made while creating an issue.
+#[derive(Debug)]
+pub enum Message {
+    Templates(String, Result<Templates>),
+    // issues with titles like that of the new issue
+    Duplicates(String, Result<Vec<Issue>>),
+    Created(Result<Created>),
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::NewIssue(msg)
+    }
+}
+
+impl App {
+    pub(super) fn handle_form_key(&mut self, key_event: KeyEvent) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        match form.handle_key_event(key_event) {
+            FormEvent::Pending => {}
+            FormEvent::Edit => self.edit_form_field(),
+            FormEvent::Submit => self.submit_issue(),
+            FormEvent::Cancelled => {
+                self.form = None;
+                self.exit |= self.exit_after_form;
+            }
+        }
+    }
+
+    pub(super) fn update_new_issue(&mut self, msg: Message) {
+        match msg {
+            Message::Templates(repo, result) => self.templates_fetched(repo, result),
+            Message::Duplicates(title, result) => self.duplicates_found(title, result),
+            Message::Created(result) => self.created(result),
+        }
+        self.dirty = true;
+    }
+
+    /// Start creating an issue, exiting once it is created or cancelled.
+    /// `template` names the template to use instead of offering a choice.
+    pub fn new_issue_then_exit(&mut self, template: Option<String>) {
+        self.exit_after_form = true;
+        self.new_issue(template);
+    }
+
+    // Fetch the templates of the searched repo, or else the selected issue's, to create an issue.
+    pub(super) fn new_issue(&mut self, template: Option<String>) {
+        let repo = self
+            .resolved
+            .single_repo()
+            .map(str::to_string)
+            .or_else(|| self.selected_issue().and_then(Issue::repo));
+        let Some(repo) = repo else {
+            self.fail("Search a single repository to create an issue".to_string());
+            return;
+        };
+        self.notice = Some(Line::from(format!("Fetching templates of {repo}…")).dim());
+        self.pending_template = Some((repo.clone(), template));
+        self.request_metadata(&repo);
+        let github = self.github.clone();
+        self.spawn(async move {
+            let templates = template::fetch_templates(&github, &repo).await;
+            Message::Templates(repo, templates)
+        });
+    }
+
+    // Open the form for the named template, or offer a choice if there are several.
+    fn templates_fetched(&mut self, repo: String, result: Result<Templates>) {
+        let Some((_, name)) = self.pending_template.take_if(|(r, _)| *r == repo) else {
+            return;
+        };
+        self.notice = None;
+        let templates = result.unwrap_or_else(|err| {
+            // a blank issue can still be created
+            tracing::error!("Failed to fetch templates of {repo}: {err:?}");
+            Templates::default()
+        });
+        if let Some(name) = name {
+            match templates.find(&name) {
+                Some(template) => self.form = Some(Form::new(repo, template.clone())),
+                None => self.fail(format!("{repo} has no template named {name}")),
+            }
+            return;
+        }
+        let mut choices = templates.choices();
+        if choices.len() == 1 {
+            self.form = Some(Form::new(repo, choices.remove(0)));
+            return;
+        }
+        let items = choices
+            .into_iter()
+            .map(|template| MenuItem {
+                label: template.name.clone(),
+                description: template.about.clone(),
+                value: Action::Template {
+                    repo: repo.clone(),
+                    template,
+                },
+            })
+            .collect();
+        self.menu = Some(Menu::new(format!("New issue in {repo}"), items));
+    }
+
+    // Report why an issue can't be created, which ends the app if that's all it was for.
+    fn fail(&mut self, message: String) {
+        self.notice = Some(Line::from(message.clone()).red());
+        if self.exit_after_form {
+            self.failure = Some(message);
+            self.exit = true;
+        }
+    }
+
+    // Edit the focused textarea of the form in $EDITOR.
+    pub(super) fn edit_form_field(&mut self) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        let path = self
+            .drafts_dir
+            .clone()
+            .map_or_else(compose::drafts_dir, Ok)
+            .and_then(|dir| form.start_edit(&dir));
+        match path {
+            Ok(path) => {
+                self.command = Some(open::editor_command(open::editor().as_deref(), &path, None))
+            }
+            Err(err) => form.error = Some(format!("{err:#}")),
+        }
+    }
+
+    // Look for issues with a similar title before creating one,
+    // unless that was already done for this title.
+    pub(super) fn submit_issue(&mut self) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        let title = form.title.trim().to_string();
+        if form.checked_title.as_deref() != Some(title.as_str()) {
+            if let Some(query) = form.duplicate_query() {
+                form.busy = Some("Searching for duplicates…".to_string());
+                let github = self.github.clone();
+                self.spawn(async move {
+                    let result = issues::search_first(&github, &query, MAX_DUPLICATES).await;
+                    Message::Duplicates(title, result)
+                });
+                return;
+            }
+        }
+        self.create_issue();
+    }
+
+    fn duplicates_found(&mut self, title: String, result: Result<Vec<Issue>>) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        form.busy = None;
+        form.checked_title = Some(title);
+        match result {
+            Ok(duplicates) if duplicates.is_empty() => {
+                form.duplicates.clear();
+                self.create_issue();
+            }
+            // shown until the issue is submitted again
+            Ok(duplicates) => form.duplicates = duplicates,
+            Err(err) => {
+                tracing::error!("Failed to search for duplicates: {err:?}");
+                form.error = Some(format!("Searching for duplicates failed: {err:#}"));
+            }
+        }
+    }
+
+    fn create_issue(&mut self) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        form.busy = Some("Creating issue…".to_string());
+        let mut issue = Form::new(form.repo.clone(), form.template.clone());
+        issue.title = form.title.clone();
+        let metadata = self.metadata.get(&form.repo).cloned();
+        let github = self.github.clone();
+        self.spawn_limited(async move {
+            let result = async {
+                // the repo id and label ids come from the metadata
+                let metadata = match metadata {
+                    Some(metadata) => metadata,
+                    None => Arc::new(repo::fetch_metadata(&github, &issue.repo).await?),
+                };
+                mutation::create_issue(&github, &issue.new_issue(&metadata)).await
+            }
+            .await;
+            Message::Created(result)
+        });
+    }
+
+    fn created(&mut self, result: Result<Created>) {
+        match result {
+            Ok(created) => {
+                self.form = None;
+                self.notice =
+                    Some(Line::from(format!("Created #{} {}", created.number, created.url)).dim());
+                if self.exit_after_form {
+                    self.output = vec![created.url];
+                    self.exit = true;
+                }
+            }
+            Err(err) => {
+                tracing::error!("Failed to create issue: {err:?}");
+                if let Some(form) = &mut self.form {
+                    form.busy = None;
+                    form.error = Some(format!("{err:#}"));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::KeyCode;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_new_issue() {
+        let mut server = Server::new_async().await;
+        async fn respond(server: &mut Server, body: &str, file: &str) -> mockito::Mock {
+            server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::Regex(body.into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(std::fs::read_to_string(file).unwrap())
+                .create_async()
+                .await
+        }
+        let _search = respond(&mut server, "is:issue is:open", "testdata/issues2.json").await;
+        let templates = respond(&mut server, "ISSUE_TEMPLATE", "testdata/templates.json").await;
+        let _metadata = respond(&mut server, "assignableUsers", "testdata/metadata.json").await;
+        let duplicates = respond(&mut server, "in:title crash", "testdata/issues2.json").await;
+        let create = respond(&mut server, "createIssue", "testdata/create_issue.json").await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let args = QueryArgs {
+            query: "is:issue is:open".into(),
+            repo: vec!["octocat/Hello-World".into()],
+            ..Default::default()
+        };
+        let mut app = App::new(github, Config::default(), args, history).unwrap();
+        app.update(Message::Resize(Size::new(80, 24))).unwrap();
+        let drafts = tempfile::tempdir().unwrap();
+        app.drafts_dir = Some(drafts.path().to_path_buf());
+        app.new_issue_then_exit(None);
+
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while app.menu.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        templates.assert_async().await;
+        // blank issues are disabled in config.yml
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        app.update(Message::Key(KeyCode::Char('k').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert_eq!(
+            app.form.as_ref().map(|f| f.template.name.as_str()),
+            Some("Bug Report")
+        );
+
+        let key = |app: &mut App, code: KeyCode| app.update(Message::Key(code.into())).unwrap();
+        key(&mut app, KeyCode::Enter);
+        for c in "crash".chars() {
+            key(&mut app, KeyCode::Char(c));
+        }
+        key(&mut app, KeyCode::Enter);
+        key(&mut app, KeyCode::Char('j'));
+        key(&mut app, KeyCode::Char('j'));
+        key(&mut app, KeyCode::Enter);
+        assert!(app.command.take().is_some());
+        std::fs::write(
+            drafts.path().join("octocat_Hello-World_new_2.md"),
+            "It crashed",
+        )
+        .unwrap();
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        for _ in 0..3 {
+            key(&mut app, KeyCode::Char('j'));
+        }
+        key(&mut app, KeyCode::Char(' '));
+
+        // issues with a similar title are shown before creating it
+        key(&mut app, KeyCode::Char('s'));
+        while app.form.as_ref().is_some_and(|f| f.busy.is_some()) {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        duplicates.assert_async().await;
+        let screen = render(&mut app);
+        assert!(screen.contains("Possible duplicates"), "{screen}");
+
+        key(&mut app, KeyCode::Char('s'));
+        while !app.exit {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        create.assert_async().await;
+        assert_eq!(
+            app.output(),
+            &["https://github.com/octocat/Hello-World/issues/2357"]
+        );
+        assert!(app.failure.is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use super::{author, App};
+use crate::github::reactions::{self, Reaction, ReactionGroup};
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::Result;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+
+/// Results of reacting to issues and comments.
+#[derive(Debug)]
+pub enum Message {
+    // the viewer's reaction to an issue or comment was added or removed
+    Reacted {
+        subject_id: String,
+        reaction: Reaction,
+        added: bool,
+        result: Result<()>,
+    },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Reactions(msg)
+    }
+}
+
+/// Choices in the menus of reactions.
+#[derive(Clone, Debug)]
+pub(super) enum Action {
+    // choose a reaction to an issue or one of its comments
+    ReactTo(String),
+    React {
+        subject_id: String,
+        reaction: Reaction,
+        add: bool,
+    },
+}
+
+impl From<Action> for super::Action {
+    fn from(action: Action) -> Self {
+        Self::React(action)
+    }
+}
+
+impl App {
+    pub(super) fn update_reactions(&mut self, msg: Message) {
+        match msg {
+            Message::Reacted {
+                subject_id,
+                reaction,
+                added,
+                result,
+            } => self.reacted(subject_id, reaction, added, result),
+        }
+        self.dirty = true;
+    }
+
+    pub(super) fn apply_reaction(&mut self, action: Action) {
+        match action {
+            Action::ReactTo(subject_id) => self.open_reactions(subject_id),
+            Action::React {
+                subject_id,
+                reaction,
+                add,
+            } => self.react(subject_id, reaction, add),
+        }
+    }
+
+    // Choose what to react to: the selected issue, or one of its comments.
+    pub(super) fn open_reaction_menu(&mut self) {
+        let Some(issue) = self.selected_issue() else {
+            return;
+        };
+        let comments = match self.comments.get(&issue.url) {
+            Some(Ok(comments)) if !comments.is_empty() => comments,
+            _ => {
+                let id = issue.id.clone();
+                self.open_reactions(id);
+                return;
+            }
+        };
+        let describe = |body: &str, groups: &[ReactionGroup]| {
+            let first = body
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or_default();
+            match reactions::summary(groups) {
+                summary if summary.is_empty() => first.to_string(),
+                summary => format!("{summary}  {first}"),
+            }
+        };
+        let mut items = vec![MenuItem {
+            label: format!("#{}", issue.number),
+            description: describe(&issue.title, &issue.reaction_groups),
+            value: Action::ReactTo(issue.id.clone()).into(),
+        }];
+        items.extend(comments.iter().map(|c| MenuItem {
+            label: author(&c.author).to_string(),
+            description: describe(&c.body, &c.reaction_groups),
+            value: Action::ReactTo(c.id.clone()).into(),
+        }));
+        self.menu = Some(Menu::new("React to", items));
+    }
+
+    // Choose a reaction to toggle on the issue or comment `subject_id`.
+    fn open_reactions(&mut self, subject_id: String) {
+        let Some(issue) = self.selected_issue() else {
+            return;
+        };
+        let comment = match self.comments.get(&issue.url) {
+            Some(Ok(comments)) => comments.iter().find(|c| c.id == subject_id),
+            _ => None,
+        };
+        let (title, groups) = match comment {
+            Some(c) => (
+                format!("React to {}'s comment", author(&c.author)),
+                &c.reaction_groups,
+            ),
+            None if issue.id == subject_id => (
+                format!("React to #{}", issue.number),
+                &issue.reaction_groups,
+            ),
+            None => return,
+        };
+        let items = Reaction::ALL
+            .into_iter()
+            .map(|reaction| {
+                let count = groups
+                    .iter()
+                    .find(|g| g.content == reaction)
+                    .map_or(0, |g| g.reactors.total_count);
+                let reacted = reactions::has_reacted(groups, reaction);
+                MenuItem {
+                    label: format!("{} {}", reaction.emoji(), reaction.name()),
+                    description: match (count, reacted) {
+                        (_, true) => format!("{count}, including yours"),
+                        (0, false) => String::new(),
+                        (_, false) => count.to_string(),
+                    },
+                    value: Action::React {
+                        subject_id: subject_id.clone(),
+                        reaction,
+                        add: !reacted,
+                    }
+                    .into(),
+                }
+            })
+            .collect();
+        self.menu = Some(Menu::new(title, items));
+    }
+
+    fn react(&mut self, subject_id: String, reaction: Reaction, add: bool) {
+        let github = self.github.clone();
+        self.spawn_limited(async move {
+            let result = reactions::react(&github, &subject_id, reaction, add).await;
+            Message::Reacted {
+                subject_id,
+                reaction,
+                added: add,
+                result,
+            }
+        });
+    }
+
+    fn reacted(&mut self, subject_id: String, reaction: Reaction, added: bool, result: Result<()>) {
+        if let Err(err) = result {
+            tracing::error!("Failed to react to {subject_id}: {err:?}");
+            self.notice = Some(Line::from(format!("{err:#}")).red());
+            return;
+        }
+        if let Some((url, groups)) = self.reaction_groups_mut(&subject_id) {
+            reactions::record(groups, reaction, added);
+            *self.conversation_revisions.entry(url).or_default() += 1;
+        }
+        let description = match added {
+            true => format!("Reacted with {}", reaction.emoji()),
+            false => format!("Removed {}", reaction.emoji()),
+        };
+        self.notice = Some(Line::from(description).dim());
+    }
+
+    // The reactions to a loaded issue or comment, and the url of the issue whose preview shows them.
+    fn reaction_groups_mut(
+        &mut self,
+        subject_id: &str,
+    ) -> Option<(String, &mut Vec<ReactionGroup>)> {
+        if let Some(issue) = self.issues.iter_mut().find(|i| i.id == subject_id) {
+            return Some((issue.url.clone(), &mut issue.reaction_groups));
+        }
+        self.comments.iter_mut().find_map(|(url, comments)| {
+            let comment = comments
+                .as_mut()
+                .ok()?
+                .iter_mut()
+                .find(|c| c.id == subject_id)?;
+            Some((url.clone(), &mut comment.reaction_groups))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::KeyCode;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_reactions() {
+        async fn respond(server: &mut Server, pattern: &str, body: &str) -> mockito::Mock {
+            server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::Regex(pattern.into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await
+        }
+        let mut server = Server::new_async().await;
+        let issues = std::fs::read_to_string("testdata/issues2.json").unwrap();
+        let _search = respond(&mut server, r"search\(", &issues).await;
+        let comments = std::fs::read_to_string("testdata/comments.json").unwrap();
+        let fetch = respond(
+            &mut server,
+            r#"issueOrPullRequest.*"number":2356"#,
+            &comments,
+        )
+        .await;
+        let add = respond(
+            &mut server,
+            r#"addReaction\(.*"subjectId":"IC_1","content":"THUMBS_UP""#,
+            r#"{"data":{"addReaction":{"reaction":{"content":"THUMBS_UP"}}}}"#,
+        )
+        .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done || app.comments.is_empty() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        fetch.assert_async().await;
+        let (loaded, text) = app.preview_source().unwrap();
+        assert!(text.contains("$Env:Path += ';C:\\Tools'\n\n👍 5\n\n---\n\n"));
+        assert!(text.contains(
+            "**monalisa** commented on 2024-05-01:\n\nSame here, please fix.\n\n👍 12 🎉 3\n\n"
+        ));
+        assert!(text.ends_with("**ghost** commented on 2024-05-02:\n\nFixed in #2648."));
+
+        app.update(Message::Key(KeyCode::Char('r').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("monalisa"), "{screen}");
+        assert!(screen.contains("Same here, please fix."), "{screen}");
+        for key in [KeyCode::Char('j'), KeyCode::Enter] {
+            app.update(Message::Key(key.into())).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("React to monalisa's comment"), "{screen}");
+        assert!(screen.contains("3, including yours"), "{screen}");
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        while app.notice.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        add.assert_async().await;
+        assert_eq!(app.notice.as_ref().unwrap().to_string(), "Reacted with 👍");
+        let (reacted, text) = app.preview_source().unwrap();
+        assert!(text.contains("Same here, please fix.\n\n👍 13 🎉 3\n\n"));
+        // only the preview of the issue reacted to is highlighted again
+        assert_ne!(reacted, loaded);
+        let url = &app.selected_issue().unwrap().url;
+        assert_eq!(app.conversation_revisions[url], 2);
+    }
+}
@@ -0,0 +1,414 @@
+use super::{Action, App};
+use crate::github::mutation;
+use crate::github::pulls::PullFile;
+use crate::github::review::{self, Review, ReviewEvent, Thread};
+use crate::tui::compose::{Composer, Draft};
+use crate::tui::diff;
+use crate::tui::menu::{Menu, MenuItem};
+use anyhow::Result;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The review threads of pull requests.
+#[derive(Default)]
+pub(super) struct State {
+    // review threads of each pull request, by url, or why they couldn't be fetched
+    pub(super) fetched: HashMap<String, Result<Arc<Review>, String>>,
+    pub(super) requested: HashSet<String>,
+}
+
+/// Results of fetching and changing the reviews of pull requests.
+#[derive(Debug)]
+pub enum Message {
+    // the review threads of a pull request
+    Threads(String, Result<Review>),
+    // a review comment was added, a thread resolved or a review submitted
+    Reviewed {
+        url: String,
+        description: String,
+        result: Result<()>,
+    },
+}
+
+impl From<Message> for super::Message {
+    fn from(msg: Message) -> Self {
+        Self::Review(msg)
+    }
+}
+
+impl App {
+    pub(super) fn update_review(&mut self, msg: Message) {
+        match msg {
+            Message::Threads(url, result) => {
+                if let Err(err) = &result {
+                    tracing::error!("Failed to fetch review threads of {url}: {err:?}");
+                }
+                let review = result.map(Arc::new).map_err(|err| format!("{err:#}"));
+                self.reviews.fetched.insert(url, review);
+            }
+            Message::Reviewed {
+                url,
+                description,
+                result,
+            } => self.reviewed(url, description, result),
+        }
+        self.dirty = true;
+    }
+
+    pub(super) fn fetch_review(&self, url: String, repo: String, number: u32) {
+        let github = self.github.clone();
+        self.spawn(async move {
+            let review = review::fetch_review(&github, &repo, number).await;
+            Message::Threads(url, review)
+        });
+    }
+
+    // The review of the pull request whose diff is shown, once fetched.
+    pub(super) fn diff_review(&self) -> Option<&Arc<Review>> {
+        match self.reviews.fetched.get(self.diff_url()?) {
+            Some(Ok(review)) => Some(review),
+            _ => None,
+        }
+    }
+
+    // The review threads on a file of the shown diff.
+    pub(super) fn file_threads(&self, file: &PullFile) -> Vec<&Thread> {
+        self.diff_review()
+            .map(|review| {
+                review
+                    .threads
+                    .iter()
+                    .filter(|t| t.path == file.filename)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // The first review thread on the cursor's row.
+    pub(super) fn thread_at_cursor(&mut self) -> Option<&Thread> {
+        let thread = self.shown_diff().and_then(|(file, diff)| {
+            let lines = diff::parse(file.patch.as_deref().unwrap_or_default());
+            let threads = self.file_threads(file);
+            let on_row = diff::threads_on(diff, &lines, &threads, self.diff.view.cursor);
+            on_row.first().map(|t| t.id.clone())
+        });
+        if thread.is_none() {
+            self.notice = Some(Line::from("No review thread on this line").dim());
+        }
+        let review = self.diff_review()?;
+        review
+            .threads
+            .iter()
+            .find(|t| Some(&t.id) == thread.as_ref())
+    }
+
+    // Start a review comment on the selected lines of the diff.
+    pub(super) fn comment_on_lines(&mut self) {
+        let thread = self.shown_diff().and_then(|(file, diff)| {
+            let lines = diff::parse(file.patch.as_deref()?);
+            let (start, end) = self.diff.view.selected_lines(diff)?;
+            diff::new_thread(&file.filename, &lines, start, end)
+        });
+        let Some(thread) = thread else {
+            self.notice = Some(Line::from("Only lines of the diff can be commented on").dim());
+            return;
+        };
+        self.diff.view.anchor = None;
+        self.compose_review(Draft::Thread(thread));
+    }
+
+    pub(super) fn toggle_resolved(&mut self) {
+        let Some(thread) = self.thread_at_cursor() else {
+            return;
+        };
+        let (id, resolved) = (thread.id.clone(), !thread.is_resolved);
+        let description = match resolved {
+            true => "Resolved thread",
+            false => "Unresolved thread",
+        };
+        let (github, url) = (self.github.clone(), self.diff.view.url.clone());
+        self.spawn_limited(async move {
+            let result = review::set_resolved(&github, &id, resolved).await;
+            Message::Reviewed {
+                url,
+                description: description.to_string(),
+                result,
+            }
+        });
+    }
+
+    pub(super) fn open_review_menu(&mut self) {
+        let pending = self.diff_review().is_some_and(|r| r.pending.is_some());
+        let items = ReviewEvent::ALL
+            .into_iter()
+            .map(|event| MenuItem {
+                label: event.name().to_string(),
+                description: match event {
+                    ReviewEvent::Comment => "Submit general feedback",
+                    ReviewEvent::Approve => "Approve merging these changes",
+                    ReviewEvent::RequestChanges => "Submit feedback that must be addressed",
+                }
+                .to_string(),
+                value: Action::Review(event),
+            })
+            .collect();
+        let title = match pending {
+            true => "Submit pending review",
+            false => "Submit review",
+        };
+        self.menu = Some(Menu::new(title, items));
+    }
+
+    // The comment was posted, the thread resolved or the review submitted,
+    // so fetch the threads again to show it.
+    fn reviewed(&mut self, url: String, description: String, result: Result<()>) {
+        // the draft of the comment that was posted, if it was one
+        let composer = self.composer.take_if(|c| c.posting);
+        self.notice = Some(match result {
+            Ok(()) => {
+                if let Some(composer) = composer {
+                    composer.discard();
+                }
+                if let Some((repo, number)) = self.pull_request(&url) {
+                    self.fetch_review(url, repo, number);
+                }
+                Line::from(description).dim()
+            }
+            Err(err) => {
+                tracing::error!("Failed to review {url}: {err:?}");
+                match composer {
+                    Some(composer) => Line::from(format!(
+                        "{err:#}, draft kept in {}",
+                        composer.path.display()
+                    )),
+                    None => Line::from(format!("{err:#}")),
+                }
+                .red()
+            }
+        });
+    }
+
+    // Write a comment that is part of a review of the pull request whose diff is shown.
+    pub(super) fn compose_review(&mut self, draft: Draft) {
+        let Some(dir) = self.ensure_drafts_dir() else {
+            return;
+        };
+        let Some(issue) = self.issues.iter().find(|i| i.url == self.diff.view.url) else {
+            return;
+        };
+        let composer = Composer::review(&dir, issue, draft);
+        self.edit_draft(composer);
+    }
+
+    // Post a comment that is part of a review of the pull request whose diff is shown.
+    pub(super) fn post_review(&mut self, draft: Draft, body: String) {
+        let url = self.diff.view.url.clone();
+        let Some(issue) = self.issues.iter().find(|i| i.url == url) else {
+            return;
+        };
+        let number = issue.number;
+        // without the review, comments start a new one and reviews are submitted without comments
+        let review = match self.reviews.fetched.get(&url) {
+            Some(Ok(review)) => Review::clone(review),
+            _ => Review {
+                pull_request_id: issue.id.clone(),
+                ..Default::default()
+            },
+        };
+        let description = match &draft {
+            Draft::Comment => format!("Commented on #{number}"),
+            Draft::Thread(thread) => {
+                format!("Added review comment on {}:{}", thread.path, thread.line)
+            }
+            Draft::Reply { .. } => format!("Replied on #{number}"),
+            Draft::Review(ReviewEvent::Comment) => format!("Reviewed #{number}"),
+            Draft::Review(ReviewEvent::Approve) => format!("Approved #{number}"),
+            Draft::Review(ReviewEvent::RequestChanges) => {
+                format!("Requested changes on #{number}")
+            }
+            Draft::Merge { .. } => unreachable!("merge commit messages are sent by send_merge"),
+        };
+        let github = self.github.clone();
+        self.spawn_limited(async move {
+            let result = match draft {
+                Draft::Comment => mutation::add_comment(&github, &review.pull_request_id, &body)
+                    .await
+                    .map(drop),
+                Draft::Thread(thread) => review::add_thread(&github, &review, &thread, &body).await,
+                Draft::Reply { thread_id } => {
+                    review::reply(&github, &review, &thread_id, &body).await
+                }
+                Draft::Review(event) => review::submit(&github, &review, event, &body).await,
+                Draft::Merge { .. } => unreachable!("merge commit messages are sent by send_merge"),
+            };
+            Message::Reviewed {
+                url,
+                description,
+                result,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tests::render, Message};
+    use super::*;
+    use crate::config::Config;
+    use crate::github::Github;
+    use crate::history::History;
+    use crate::QueryArgs;
+    use crossterm::event::KeyCode;
+    use mockito::Server;
+    use ratatui::layout::Size;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_review() {
+        async fn respond(server: &mut Server, pattern: &str, body: &str) -> mockito::Mock {
+            server
+                .mock("POST", "/graphql")
+                .match_body(mockito::Matcher::Regex(pattern.into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await
+        }
+        let mut server = Server::new_async().await;
+        let issues = std::fs::read_to_string("testdata/issues2.json").unwrap();
+        let _search = respond(&mut server, r"search\(", &issues).await;
+        let threads = std::fs::read_to_string("testdata/review_threads.json").unwrap();
+        // once to show the threads, then again after each of the three changes
+        let fetch = respond(&mut server, "reviewThreads", &threads)
+            .await
+            .expect(4);
+        let _files = server
+            .mock("GET", "/repos/octocat/Hello-World/pulls/2648/files")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/pull_files.json").unwrap())
+            .create_async()
+            .await;
+        let start = respond(
+            &mut server,
+            r#""input":\{"pullRequestId":"PR_1"\}"#,
+            r#"{"data":{"addPullRequestReview":{"pullRequestReview":{"id":"PRR_1"}}}}"#,
+        )
+        .await;
+        let add = respond(
+            &mut server,
+            r#"addPullRequestReviewThread\(.*"body":"nit","path":"src/main.rs","line":3,"side":"RIGHT","startLine":2,"startSide":"RIGHT""#,
+            r#"{"data":{"addPullRequestReviewThread":{"thread":{"id":"PRRT_2"}}}}"#,
+        )
+        .await;
+        let resolve = respond(
+            &mut server,
+            r#"resolveReviewThread\(.*"threadId":"PRRT_1""#,
+            r#"{"data":{"resolveReviewThread":{"thread":{"id":"PRRT_1"}}}}"#,
+        )
+        .await;
+        let approve = respond(
+            &mut server,
+            r#""input":\{"pullRequestId":"PR_1","event":"APPROVE","body":""\}"#,
+            r#"{"data":{"addPullRequestReview":{"pullRequestReview":{"id":"PRR_2"}}}}"#,
+        )
+        .await;
+
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "issues", None).unwrap();
+        let mut app = App::new(github, Config::default(), QueryArgs::default(), history).unwrap();
+        let drafts = tempfile::tempdir().unwrap();
+        app.drafts_dir = Some(drafts.path().to_path_buf());
+        app.update(Message::Resize(Size::new(80, 12))).unwrap();
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !app.search_done {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        for c in ['j', ']'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        while app.diff_display().is_none() || app.diff_review().is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        let screen = render(&mut app);
+        assert!(screen.contains("   2      -    println!"), "{screen}");
+        assert!(screen.contains(" ┃ monalisa: Why?"), "{screen}");
+        assert!(screen.contains(" ┃   Now it's right."), "{screen}");
+        assert!(screen.contains("enter: review"), "{screen}");
+
+        // comment on the two added lines
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        for c in ['j', 'j', 'j', 'v', 'j', 'c'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        assert!(app.command.take().is_some());
+        let composer = app.composer.as_ref().unwrap();
+        assert_eq!(composer.title(), "Review comment on src/main.rs:2-3");
+        std::fs::write(&composer.path, "nit").unwrap();
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        while app.composer.is_some() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        start.assert_async().await;
+        add.assert_async().await;
+        let screen = render(&mut app);
+        assert!(
+            screen.contains("Added review comment on src/main.rs:3"),
+            "{screen}"
+        );
+
+        // resolve the thread on the removed line
+        for c in ['k', 'k', 'R'] {
+            app.update(Message::Key(KeyCode::Char(c).into())).unwrap();
+        }
+        while app.notice.is_none() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        resolve.assert_async().await;
+        assert!(render(&mut app).contains("Resolved thread"));
+
+        // approving doesn't need a summary
+        app.update(Message::Key(KeyCode::Char('S').into())).unwrap();
+        let screen = render(&mut app);
+        assert!(screen.contains("Request changes"), "{screen}");
+        app.update(Message::Key(KeyCode::Char('j').into())).unwrap();
+        app.update(Message::Key(KeyCode::Enter.into())).unwrap();
+        assert!(app.command.take().is_some());
+        app.update(Message::CommandDone(Ok(ExitStatus::default())))
+            .unwrap();
+        assert!(render(&mut app).contains("Approve #2648"));
+        app.update(Message::Key(KeyCode::Char('y').into())).unwrap();
+        while app.composer.is_some() {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        approve.assert_async().await;
+        assert!(render(&mut app).contains("Approved #2648"));
+
+        // wait for the threads to be fetched again after each change
+        while !fetch.matched_async().await {
+            let msg = app.next_message(&mut events, &mut ticks).await.unwrap();
+            app.update(msg).unwrap();
+        }
+        app.update(Message::Key(KeyCode::Esc.into())).unwrap();
+        assert!(!app.diff.view.focused);
+        assert!(!app.exit);
+    }
+}
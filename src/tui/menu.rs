@@ -0,0 +1,148 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Style, Stylize as _},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr as _;
+
+pub struct MenuItem<T> {
+    pub label: String,
+    // shown dimmed after the label
+    pub description: String,
+    pub value: T,
+}
+
+/// The result of passing a key to a menu.
+#[derive(Debug, PartialEq)]
+pub enum MenuEvent<T> {
+    Pending,
+    Chosen(T),
+    Cancelled,
+}
+
+/// A popup list of choices, drawn over the middle of the screen.
+pub struct Menu<T> {
+    title: String,
+    items: Vec<MenuItem<T>>,
+    state: ListState,
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new(title: impl Into<String>, items: Vec<MenuItem<T>>) -> Self {
+        Self {
+            title: title.into(),
+            items,
+            state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> MenuEvent<T> {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+            KeyCode::Char('n') if ctrl => self.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('p') if ctrl => self.state.select_previous(),
+            KeyCode::Enter => {
+                if let Some(item) = self.state.selected().and_then(|i| self.items.get(i)) {
+                    return MenuEvent::Chosen(item.value.clone());
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return MenuEvent::Cancelled,
+            _ => {}
+        }
+        MenuEvent::Pending
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.state
+            .select(index.map(|i| i.min(self.items.len().saturating_sub(1))));
+    }
+
+    fn select_next(&mut self) {
+        let last = self.items.len().saturating_sub(1);
+        self.state.select(Some(
+            (self.state.selected().unwrap_or_default() + 1).min(last),
+        ));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let width = self
+            .items
+            .iter()
+            .map(|i| i.label.width() + i.description.width() + 2)
+            .chain([self.title.width()])
+            .max()
+            .unwrap_or_default() as u16
+            + 4;
+        let height = self.items.len() as u16 + 2;
+        let area = centered(frame.area(), width, height);
+
+        let list = List::new(self.items.iter().map(|i| {
+            Line::from(vec![
+                Span::raw(i.label.as_str()),
+                Span::raw("  "),
+                Span::raw(i.description.as_str()).dim(),
+            ])
+        }))
+        .block(Block::bordered().title(self.title.as_str()))
+        .highlight_style(Style::new().reversed());
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+/// A rectangle of the given size in the middle of `area`.
+pub fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu() {
+        let item = |label: &str, value| MenuItem {
+            label: label.into(),
+            description: String::new(),
+            value,
+        };
+        let mut menu = Menu::new("pick", vec![item("a", 1), item("b", 2)]);
+        assert_eq!(
+            menu.handle_key_event(KeyCode::Enter.into()),
+            MenuEvent::Chosen(1)
+        );
+        assert_eq!(
+            menu.handle_key_event(KeyCode::Char('j').into()),
+            MenuEvent::Pending
+        );
+        menu.handle_key_event(KeyCode::Char('j').into());
+        assert_eq!(
+            menu.handle_key_event(KeyCode::Enter.into()),
+            MenuEvent::Chosen(2)
+        );
+        menu.handle_key_event(KeyCode::Char('k').into());
+        assert_eq!(
+            menu.handle_key_event(KeyCode::Enter.into()),
+            MenuEvent::Chosen(1)
+        );
+        assert_eq!(
+            menu.handle_key_event(KeyCode::Esc.into()),
+            MenuEvent::Cancelled
+        );
+    }
+}
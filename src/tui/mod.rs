@@ -1,3 +1,15 @@
+pub mod bulk;
+pub mod checks;
+pub mod complete;
+pub mod compose;
+pub mod dashboard;
+pub mod diff;
+pub mod form;
 pub mod input;
 pub mod issues;
+pub mod menu;
+pub mod notifications;
+pub mod picker;
 pub mod preview;
+pub mod query;
+pub mod searches;
@@ -0,0 +1,261 @@
+use super::issues::author;
+use super::picker::Mode;
+use crate::github::{
+    notifications::{self, Notification, SubjectDetails},
+    search::{Page, Poll},
+    Github,
+};
+use anyhow::{Context, Result};
+use ratatui::{layout::Constraint, style::Stylize, text::Span};
+
+/// The notifications inbox, previewed with the issue, pull request or release they're about.
+/// Polls for new notifications while it's open.
+pub struct Notifications;
+
+impl Mode for Notifications {
+    type Item = Notification;
+    const NAME: &'static str = "notifications";
+    const ACTIONS: &'static [(char, &'static str)] = &[
+        ('r', "Marked as read"),
+        ('d', "Marked as done"),
+        ('u', "Unsubscribed"),
+    ];
+
+    fn widths() -> Vec<Constraint> {
+        vec![
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Length(16),
+        ]
+    }
+
+    fn cells(notification: &Notification) -> Vec<Span<'static>> {
+        let title = Span::from(notification.subject.title.clone());
+        vec![
+            match notification.unread {
+                true => Span::from("●").blue(),
+                false => Span::from(""),
+            },
+            Span::from(notification.repository.full_name.clone()).dim(),
+            match notification.unread {
+                true => title.bold(),
+                false => title,
+            },
+            Span::from(notification.reason.replace('_', " ")).dim(),
+        ]
+    }
+
+    fn url(notification: &Notification) -> String {
+        notification.html_url()
+    }
+
+    // the thread's preview is fetched again when there's news on it
+    fn key(notification: &Notification) -> String {
+        format!("{}@{}", notification.id, notification.updated_at)
+    }
+
+    fn title(notification: &Notification) -> String {
+        notification.subject.title.clone()
+    }
+
+    async fn search(
+        github: Github,
+        query: String,
+        next: Option<String>,
+    ) -> Result<Page<Notification>> {
+        notifications::fetch_notifications(&github, &query, next.as_deref(), None)
+            .await?
+            .context("Notifications weren't modified, though nothing was polled")
+    }
+
+    async fn preview(github: Github, notification: Notification) -> Result<String> {
+        let details = notifications::fetch_subject(&github, &notification).await?;
+        Ok(notification_preview(&notification, details.as_ref()))
+    }
+
+    async fn act(
+        github: Github,
+        mut notification: Notification,
+        key: char,
+    ) -> Result<Option<Notification>> {
+        match key {
+            'r' => {
+                notifications::mark_read(&github, &notification.id).await?;
+                notification.unread = false;
+                Ok(Some(notification))
+            }
+            'd' => {
+                notifications::mark_done(&github, &notification.id).await?;
+                Ok(None)
+            }
+            'u' => {
+                notifications::unsubscribe(&github, &notification.id).await?;
+                Ok(Some(notification))
+            }
+            _ => unreachable!("not one of the ACTIONS"),
+        }
+    }
+
+    async fn poll(github: Github, query: String, poll: Poll) -> Result<Option<Page<Notification>>> {
+        notifications::fetch_notifications(&github, &query, None, poll.last_modified.as_deref())
+            .await
+    }
+}
+
+// The markdown of why the viewer was notified, then of what they were notified about.
+fn notification_preview(notification: &Notification, details: Option<&SubjectDetails>) -> String {
+    let mut text = format!(
+        "{} · {} · {}",
+        notification.repository.full_name,
+        notification.reason.replace('_', " "),
+        notification.updated_at.format("%Y-%m-%d %H:%M"),
+    );
+    let Some(details) = details else {
+        text.push_str(&format!(
+            "\n\n---\n\n*No preview for {}*",
+            notification.subject.kind
+        ));
+        return text;
+    };
+    let title = match (&details.name, &details.tag_name) {
+        (Some(name), Some(tag)) if !name.is_empty() => format!("{name} ({tag})"),
+        (_, Some(tag)) => tag.clone(),
+        _ => details
+            .title
+            .clone()
+            .unwrap_or_else(|| notification.subject.title.clone()),
+    };
+    text.push_str(&format!("\n\n---\n\n**{title}**"));
+    let state = match (details.merged, &details.state) {
+        (true, _) => Some("merged"),
+        (false, state) => state.as_deref(),
+    };
+    // releases have an author instead of a user
+    let by = match details.user {
+        Some(_) => author(&details.user),
+        None => author(&details.author),
+    };
+    match state {
+        Some(state) => text.push_str(&format!("\n\n{state} · by **{by}**")),
+        None => text.push_str(&format!("\n\nby **{by}**")),
+    }
+    if let Some(body) = details.body.as_deref().filter(|b| !b.is_empty()) {
+        text.push_str(&format!("\n\n{body}"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::history::History;
+    use crate::tui::picker::{Message, Picker};
+    use crossterm::event::KeyCode;
+    use mockito::{Matcher, Server};
+    use ratatui::layout::Size;
+    use std::time::Duration;
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_notifications() {
+        let mut server = Server::new_async().await;
+        let list = server
+            .mock("GET", "/notifications")
+            .match_query(Matcher::Any)
+            .match_header("if-modified-since", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("last-modified", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_header("x-poll-interval", "0")
+            .with_body(std::fs::read_to_string("testdata/notifications.json").unwrap())
+            .expect(1)
+            .create_async()
+            .await;
+        // a new notification arrives
+        let polled = server
+            .mock("GET", "/notifications")
+            .match_query(Matcher::Any)
+            .match_header("if-modified-since", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("last-modified", "Thu, 02 May 2024 10:00:00 GMT")
+            .with_header("x-poll-interval", "3600")
+            .with_body(
+                std::fs::read_to_string("testdata/notifications.json")
+                    .unwrap()
+                    .replacen(r#""id": "3""#, r#""id": "4""#, 1),
+            )
+            .create_async()
+            .await;
+        let issue = server
+            .mock("GET", "/repos/octocat/Hello-World/issues/123")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"title":"Greetings","state":"open","user":{"login":"monalisa"},"body":"Hi @octocat!"}"#)
+            .create_async()
+            .await;
+        let read = server
+            .mock("PATCH", "/notifications/threads/1")
+            .with_status(205)
+            .create_async()
+            .await;
+        let done = server
+            .mock("DELETE", "/notifications/threads/1")
+            .with_status(204)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "notifications", None).unwrap();
+        let mut picker =
+            Picker::<Notifications>::new(github, Config::default(), "", history).unwrap();
+        picker.update(Message::Resize(Size::new(80, 12)));
+
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        // the first page, the issue's preview, then the poll that finds the new notification
+        let mut seen_new = false;
+        let mut previewed = false;
+        while !seen_new || !previewed {
+            let msg = picker.next_message(&mut events, &mut ticks).await.unwrap();
+            previewed |= matches!(&msg, Message::Preview(key, Ok(text))
+                if key.starts_with("1@") && text.contains("Hi @octocat!"));
+            seen_new |= matches!(
+                &msg,
+                Message::Polled {
+                    result: Ok(Some(_)),
+                    ..
+                }
+            );
+            picker.update(msg);
+        }
+        list.assert_async().await;
+        polled.assert_async().await;
+        issue.assert_async().await;
+
+        for key in ['r', 'd'] {
+            picker.update(Message::Key(KeyCode::Char(key).into()));
+            loop {
+                let msg = picker.next_message(&mut events, &mut ticks).await.unwrap();
+                let acted = matches!(msg, Message::Acted { .. });
+                picker.update(msg);
+                if acted {
+                    break;
+                }
+            }
+        }
+        read.assert_async().await;
+        done.assert_async().await;
+
+        picker.update(Message::Key(KeyCode::Char('p').into()));
+        assert_eq!(
+            picker.output(),
+            ["https://github.com/octocat/Hello-World/pull/124"]
+        );
+    }
+}
@@ -0,0 +1,895 @@
+use super::input::LineInput;
+use super::issues::{suspend, Areas, SPINNER, TICK_RATE};
+use super::menu::{Menu, MenuEvent, MenuItem};
+use super::preview::{self, HighlightCache, Highlighter};
+use crate::config::Config;
+use crate::github::{
+    search::{Page, Poll},
+    Github,
+};
+use crate::history::History;
+use crate::{clipboard, open};
+use anyhow::{Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::{Stream, StreamExt as _};
+use ratatui::{
+    layout::{Constraint, Rect, Size},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Row, Table, TableState, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    future::Future,
+    marker::PhantomData,
+    process::{Command, ExitStatus},
+    sync::Arc,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::Interval,
+};
+
+/// A kind of search whose results the picker lists, with a preview of the selected one.
+pub trait Mode {
+    type Item: Clone + Debug + Send + 'static;
+
+    /// Names the search history of the mode.
+    const NAME: &'static str;
+    /// The extension of the files whose language the preview is highlighted as.
+    const PREVIEW_SYNTAX: &'static str = "md";
+
+    /// The widths of the columns of the results table.
+    fn widths() -> Vec<Constraint>;
+    fn cells(item: &Self::Item) -> Vec<Span<'static>>;
+    /// The item's page on GitHub, printed when it is picked.
+    fn url(item: &Self::Item) -> String;
+    /// Identifies the item. Its preview is fetched again when this changes.
+    fn key(item: &Self::Item) -> String {
+        Self::url(item)
+    }
+    /// Shown above the preview.
+    fn title(item: &Self::Item) -> String;
+    /// The file `e` opens in `$EDITOR`, in a local checkout of its repo:
+    /// the repo, the path in the repo and the line to open it at.
+    fn file(_item: &Self::Item) -> Option<(String, String, Option<usize>)> {
+        None
+    }
+    /// What `y` offers to copy, by label. A single choice is copied without asking.
+    fn yanks(item: &Self::Item) -> Vec<(&'static str, String)> {
+        vec![("URL", Self::url(item))]
+    }
+    /// Fetch the page of results that starts at `next`, or the first page.
+    fn search(
+        github: Github,
+        query: String,
+        next: Option<String>,
+    ) -> impl Future<Output = Result<Page<Self::Item>>> + Send + 'static;
+    /// The text of the preview, fetching whatever it needs.
+    fn preview(
+        github: Github,
+        item: Self::Item,
+    ) -> impl Future<Output = Result<String>> + Send + 'static;
+
+    /// Keys for actions on the selected item, each with what to report once it's done.
+    const ACTIONS: &'static [(char, &'static str)] = &[];
+
+    /// Do the action bound to `key` on an item.
+    /// Returns the item as it is now, or None if it should leave the list.
+    fn act(
+        _github: Github,
+        item: Self::Item,
+        _key: char,
+    ) -> impl Future<Output = Result<Option<Self::Item>>> + Send + 'static {
+        async { Ok(Some(item)) }
+    }
+
+    /// Search again if the results have changed since a page said to poll them.
+    /// Returns None if they haven't.
+    fn poll(
+        _github: Github,
+        _query: String,
+        _poll: Poll,
+    ) -> impl Future<Output = Result<Option<Page<Self::Item>>>> + Send + 'static {
+        async { Ok(None) }
+    }
+}
+
+/// Everything that can change the state of the picker.
+#[derive(Debug)]
+pub enum Message<T> {
+    Key(KeyEvent),
+    Resize(Size),
+    Tick,
+    // a page of the results of search `generation`
+    Page {
+        generation: usize,
+        result: Result<Page<T>>,
+    },
+    // the first page of the results of search `generation` if they've changed since `poll`
+    Polled {
+        generation: usize,
+        poll: Poll,
+        result: Result<Option<Page<T>>>,
+    },
+    // the text of the preview of an item, by key
+    Preview(String, Result<String>),
+    // an action on the item with `key` is done, with the message to report
+    Acted {
+        key: String,
+        done: &'static str,
+        result: Result<Option<T>>,
+    },
+    Highlighted((String, u16)),
+    // the program the terminal was suspended for exited
+    CommandDone(Result<ExitStatus>),
+    // text was copied to the clipboard
+    Copied(String, Result<()>),
+}
+
+/// Searches for one kind of thing and shows a preview of the selected result.
+/// Shared by the searches other than issues, which have a picker of their own.
+pub struct Picker<M: Mode> {
+    github: Github,
+    config: Config,
+    history: History,
+    line_input: LineInput,
+    editing_query: bool,
+    exit: bool,
+    dirty: bool,
+    area: Rect,
+    table_state: TableState,
+    items: Vec<M::Item>,
+    // keys of the loaded items
+    seen: HashSet<String>,
+    // bumped for each search, so pages of earlier ones are ignored
+    generation: usize,
+    // where the next page starts
+    next: Option<String>,
+    loading: bool,
+    search_done: bool,
+    // the text of each item's preview, by key, or why it couldn't be fetched
+    texts: HashMap<String, Result<String, String>>,
+    texts_requested: HashSet<String>,
+    highlighter: Arc<Highlighter>,
+    previews: HighlightCache<(String, u16), Text<'static>>, // (key, width)->preview
+    tasks_tx: UnboundedSender<Message<M::Item>>,
+    tasks_rx: UnboundedReceiver<Message<M::Item>>,
+    // the result of the last action, shown in the status line until the next key
+    notice: Option<Line<'static>>,
+    // a program to suspend the terminal for, like $EDITOR
+    pub(super) command: Option<Command>,
+    // the choice of what to copy
+    menu: Option<Menu<String>>,
+    spinner: usize,
+    output: Vec<String>,
+    mode: PhantomData<M>,
+}
+
+impl<M: Mode> Picker<M> {
+    pub fn new(github: Github, config: Config, query: &str, mut history: History) -> Result<Self> {
+        if let Err(err) = history.add(query) {
+            tracing::error!("Adding to the history: {err:#}");
+        }
+        let mut line_input = LineInput::new(query);
+        line_input.set_history(history.queries());
+        let (tasks_tx, tasks_rx) = mpsc::unbounded_channel();
+        let mut picker = Self {
+            github,
+            config,
+            history,
+            line_input,
+            editing_query: false,
+            exit: false,
+            dirty: true,
+            area: Rect::default(),
+            table_state: TableState::default().with_selected(Some(0)),
+            items: Vec::new(),
+            seen: HashSet::new(),
+            generation: 0,
+            next: None,
+            loading: false,
+            search_done: false,
+            texts: HashMap::new(),
+            texts_requested: HashSet::new(),
+            highlighter: Arc::new(Highlighter::default()),
+            previews: HighlightCache::default(),
+            tasks_tx,
+            tasks_rx,
+            notice: None,
+            command: None,
+            menu: None,
+            spinner: 0,
+            output: Vec::new(),
+            mode: PhantomData,
+        };
+        picker.fetch_page();
+        Ok(picker)
+    }
+
+    pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let mut events = EventStream::new();
+        let mut ticks = tokio::time::interval(TICK_RATE);
+        self.update(Message::Resize(terminal.size()?));
+        while !self.exit {
+            if self.dirty {
+                terminal.draw(|frame| self.view(frame))?;
+                self.dirty = false;
+            }
+            let msg = self.next_message(&mut events, &mut ticks).await?;
+            self.update(msg);
+            if let Some(command) = self.command.take() {
+                drop(events);
+                let status = suspend(terminal, command);
+                events = EventStream::new();
+                self.update(Message::CommandDone(status));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lines to print after the terminal is restored, such as the url of the picked item.
+    pub fn output(&self) -> &[String] {
+        &self.output
+    }
+
+    // Run a future in the background, applying the message it produces when done.
+    fn spawn(&self, task: impl Future<Output = Message<M::Item>> + Send + 'static) {
+        let tx = self.tasks_tx.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(task.await);
+        });
+    }
+
+    /// Wait for the next thing that might change the state of the picker.
+    pub(super) async fn next_message(
+        &mut self,
+        events: &mut (impl Stream<Item = std::io::Result<Event>> + Unpin),
+        ticks: &mut Interval,
+    ) -> Result<Message<M::Item>> {
+        loop {
+            let msg = tokio::select! {
+                event = events.next() => match event.context("Event stream closed")?? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Message::Key(key),
+                    Event::Resize(width, height) => Message::Resize(Size::new(width, height)),
+                    _ => continue,
+                },
+                _ = ticks.tick() => Message::Tick,
+                Some(key) = self.previews.recv() => Message::Highlighted(key),
+                Some(msg) = self.tasks_rx.recv() => msg,
+            };
+            return Ok(msg);
+        }
+    }
+
+    /// Apply a message to the picker state.
+    pub fn update(&mut self, msg: Message<M::Item>) {
+        match msg {
+            Message::Key(key_event) => {
+                self.notice = None;
+                self.handle_key_event(key_event);
+                self.dirty = true;
+            }
+            Message::Resize(size) => {
+                self.area = Rect::new(0, 0, size.width, size.height);
+                self.dirty = true;
+            }
+            Message::Tick => {
+                self.spinner = (self.spinner + 1) % SPINNER.len();
+                self.dirty |= self.loading;
+            }
+            Message::Page { generation, result } => {
+                if generation != self.generation {
+                    return;
+                }
+                self.loading = false;
+                match result {
+                    Ok(page) => {
+                        if let Some(poll) = page.poll.clone() {
+                            self.schedule_poll(poll);
+                        }
+                        self.extend(page);
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to search {}: {err:?}", M::NAME);
+                        self.notice = Some(Line::from(format!("{err:#}")).red());
+                        self.search_done = true;
+                    }
+                }
+                self.dirty = true;
+            }
+            Message::Polled {
+                generation,
+                poll,
+                result,
+            } => {
+                if generation != self.generation {
+                    return;
+                }
+                match result {
+                    Ok(Some(page)) => {
+                        let poll = page.poll.clone().unwrap_or(poll);
+                        self.replace(page);
+                        // the next poll is of the new results
+                        self.schedule_poll(poll);
+                        self.dirty = true;
+                    }
+                    Ok(None) => self.schedule_poll(poll),
+                    Err(err) => {
+                        tracing::error!("Failed to poll {}: {err:?}", M::NAME);
+                        self.notice = Some(Line::from(format!("{err:#}")).red());
+                        self.schedule_poll(poll);
+                        self.dirty = true;
+                    }
+                }
+            }
+            Message::Preview(key, result) => {
+                if let Err(err) = &result {
+                    tracing::error!("Failed to fetch preview of {key}: {err:?}");
+                }
+                self.texts
+                    .insert(key, result.map_err(|err| format!("{err:#}")));
+                self.dirty = true;
+            }
+            Message::Acted { key, done, result } => {
+                let index = self.items.iter().position(|i| M::key(i) == key);
+                match (result, index) {
+                    (Ok(Some(item)), Some(index)) => {
+                        self.items[index] = item;
+                        self.notice = Some(Line::from(done).dim());
+                    }
+                    (Ok(None), Some(index)) => {
+                        self.items.remove(index);
+                        self.seen.remove(&key);
+                        self.clamp_selection();
+                        self.notice = Some(Line::from(done).dim());
+                    }
+                    // the item left the list while the action was running
+                    (Ok(_), None) => self.notice = Some(Line::from(done).dim()),
+                    (Err(err), _) => {
+                        tracing::error!("Failed to act on {key}: {err:?}");
+                        self.notice = Some(Line::from(format!("{err:#}")).red());
+                    }
+                }
+                self.dirty = true;
+            }
+            Message::Highlighted(key) => {
+                self.dirty |= self.preview_key().is_some_and(|k| k == key);
+            }
+            Message::CommandDone(status) => {
+                let result = status.and_then(|status| {
+                    anyhow::ensure!(status.success(), "Editor exited with {status}");
+                    Ok(())
+                });
+                if let Err(err) = result {
+                    tracing::error!("Failed to edit: {err:?}");
+                    self.notice = Some(Line::from(format!("{err:#}")).red());
+                }
+                self.dirty = true;
+            }
+            Message::Copied(text, result) => {
+                self.notice = Some(match result {
+                    Ok(()) => Line::from(format!("Copied {text}")).dim(),
+                    Err(err) => {
+                        tracing::error!("Failed to copy {text}: {err:?}");
+                        Line::from(format!("Failed to copy: {err}")).red()
+                    }
+                });
+                self.dirty = true;
+            }
+        }
+        self.fetch_more();
+        self.request_preview();
+    }
+
+    fn selected_item(&self) -> Option<&M::Item> {
+        self.items.get(self.table_state.selected()?)
+    }
+
+    fn select_next(&mut self) {
+        self.table_state.select_next();
+        self.clamp_selection();
+    }
+
+    // Keep the selection within the list, since select_next and removing items can move it past the end.
+    fn clamp_selection(&mut self) {
+        let last = self.items.len().saturating_sub(1);
+        if self.table_state.selected().is_some_and(|i| i > last) {
+            self.table_state.select(Some(last));
+        }
+    }
+
+    // Add the items of a page that aren't already listed.
+    fn extend(&mut self, page: Page<M::Item>) {
+        let new = page
+            .items
+            .into_iter()
+            .filter(|i| self.seen.insert(M::key(i)));
+        self.items.extend(new);
+        self.search_done = page.next.is_none();
+        self.next = page.next;
+    }
+
+    // Replace the items with a new first page, keeping the selected item selected if it's still there.
+    // Later pages are fetched again as needed, and those of the old results are ignored.
+    fn replace(&mut self, page: Page<M::Item>) {
+        let selected = self.selected_item().map(M::key);
+        self.generation += 1;
+        self.items.clear();
+        self.seen.clear();
+        self.loading = false;
+        self.extend(page);
+        let index = selected.and_then(|key| self.items.iter().position(|i| M::key(i) == key));
+        if let Some(index) = index {
+            self.table_state.select(Some(index));
+        }
+        self.clamp_selection();
+    }
+
+    // Check the results for changes once the poll interval has passed.
+    fn schedule_poll(&self, poll: Poll) {
+        let (github, query) = (self.github.clone(), self.line_input.pattern().to_string());
+        let generation = self.generation;
+        self.spawn(async move {
+            tokio::time::sleep(poll.interval).await;
+            let result = M::poll(github, query, poll.clone()).await;
+            Message::Polled {
+                generation,
+                poll,
+                result,
+            }
+        });
+    }
+
+    fn fetch_page(&mut self) {
+        if self.loading || self.search_done {
+            return;
+        }
+        self.loading = true;
+        let (github, query) = (self.github.clone(), self.line_input.pattern().to_string());
+        let (generation, next) = (self.generation, self.next.clone());
+        self.spawn(async move {
+            let result = M::search(github, query, next).await;
+            Message::Page { generation, result }
+        });
+    }
+
+    // Request another page if the selection is near the end of the loaded items.
+    fn fetch_more(&mut self) {
+        let height = Areas::new(self.area).list.height as usize;
+        let selected = self.table_state.selected().unwrap_or_default();
+        if selected + height >= self.items.len() {
+            self.fetch_page();
+        }
+    }
+
+    fn submit_query(&mut self) {
+        let query = self.line_input.pattern().to_string();
+        tracing::debug!("Submitting query: {query}");
+        self.line_input.push_history(&query);
+        if let Err(err) = self.history.add(&query) {
+            tracing::error!("Failed to save history: {err:?}");
+        }
+        self.generation += 1;
+        self.items.clear();
+        self.seen.clear();
+        self.next = None;
+        self.loading = false;
+        self.search_done = false;
+        self.table_state.select(Some(0));
+        self.fetch_page();
+    }
+
+    fn preview_key(&self) -> Option<(String, u16)> {
+        let width = Block::bordered().inner(Areas::new(self.area).preview).width;
+        Some((M::key(self.selected_item()?), width))
+    }
+
+    // Fetch the text of the selected item's preview, then highlight it.
+    fn request_preview(&mut self) {
+        let Some(item) = self.selected_item().cloned() else {
+            return;
+        };
+        let item_key = M::key(&item);
+        if self.texts_requested.insert(item_key.clone()) {
+            let github = self.github.clone();
+            self.spawn(async move {
+                let text = M::preview(github, item).await;
+                Message::Preview(item_key, text)
+            });
+            return;
+        }
+        let Some(key) = self.preview_key() else {
+            return;
+        };
+        let Some(Ok(text)) = self.texts.get(&item_key) else {
+            return;
+        };
+        if self.previews.contains(&key) {
+            return;
+        }
+        let (highlighter, text, width) = (self.highlighter.clone(), text.clone(), key.1);
+        self.previews.spawn(key, move || {
+            Ok(preview::wrap(
+                highlighter.text(&text, M::PREVIEW_SYNTAX)?,
+                width,
+            ))
+        });
+    }
+
+    // Do a mode's action on the selected item in the background.
+    fn act(&mut self, action: char, done: &'static str) {
+        let Some(item) = self.selected_item().cloned() else {
+            return;
+        };
+        let github = self.github.clone();
+        self.spawn(async move {
+            Message::Acted {
+                key: M::key(&item),
+                done,
+                result: M::act(github, item, action).await,
+            }
+        });
+    }
+
+    // Copy the only thing there is to copy, or offer a choice.
+    fn open_yank_menu(&mut self) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let mut choices = M::yanks(item);
+        if choices.len() == 1 {
+            let (_, text) = choices.remove(0);
+            self.yank(text);
+            return;
+        }
+        let items = choices
+            .into_iter()
+            .map(|(label, text)| MenuItem {
+                label: label.to_string(),
+                description: text.clone(),
+                value: text,
+            })
+            .collect();
+        self.menu = Some(Menu::new("Copy", items));
+    }
+
+    fn yank(&mut self, text: String) {
+        let copying = clipboard::copy(&text);
+        self.spawn(async move {
+            let result = match copying {
+                Ok(copying) => copying.await,
+                Err(err) => Err(err),
+            };
+            Message::Copied(text, result)
+        });
+    }
+
+    // Open the selected item's file in $EDITOR, in a local checkout of its repo.
+    fn edit(&mut self) {
+        let Some((repo, path, line)) = self.selected_item().and_then(M::file) else {
+            return;
+        };
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let Some(dir) = open::find_checkout(&self.config, &repo, &cwd) else {
+            self.notice = Some(Line::from(format!("No local clone of {repo}")).red());
+            return;
+        };
+        let command = open::editor_command(open::editor().as_deref(), &dir.join(path), line);
+        self.command = Some(command);
+    }
+
+    /// Render the picker state.
+    pub fn view(&mut self, frame: &mut Frame) {
+        let areas = Areas::new(frame.area());
+        let status = self
+            .notice
+            .clone()
+            .unwrap_or_else(|| Line::from(format!("{} {}", self.items.len(), M::NAME)).dim());
+        frame.render_widget(Block::bordered().title_bottom(status), areas.search);
+        self.line_input.draw(frame, areas.input);
+
+        let loading = self.loading.then(|| {
+            Row::new(vec![
+                SPINNER[self.spinner].to_string(),
+                "loading".to_string(),
+            ])
+        });
+        let table = Table::new(
+            self.items
+                .iter()
+                .map(|i| Row::new(M::cells(i)))
+                .chain(loading),
+            M::widths(),
+        )
+        .row_highlight_style(Style::new().bold().reversed())
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, areas.list, &mut self.table_state);
+
+        self.draw_preview(frame, areas.preview);
+        if let Some(menu) = &mut self.menu {
+            menu.draw(frame);
+        }
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let Some(item) = self.selected_item() else {
+            frame.render_widget(Block::bordered(), area);
+            return;
+        };
+        let block = Block::bordered().title(M::title(item));
+        let preview = match self.texts.get(&M::key(item)) {
+            None => Paragraph::new(Line::from("Loading…").dim()),
+            Some(Err(err)) => Paragraph::new(err.as_str())
+                .wrap(Wrap { trim: false })
+                .red(),
+            Some(Ok(text)) => match self.preview_key().and_then(|key| self.previews.get(&key)) {
+                Some(highlighted) => Paragraph::new(highlighted.clone()),
+                // show the plain text until highlighting is done
+                None => Paragraph::new(preview::wrap(
+                    Text::from(text.clone()),
+                    block.inner(area).width,
+                )),
+            },
+        };
+        frame.render_widget(preview.block(block), area);
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(menu) = &mut self.menu {
+            match menu.handle_key_event(key_event) {
+                MenuEvent::Pending => {}
+                MenuEvent::Cancelled => self.menu = None,
+                MenuEvent::Chosen(text) => {
+                    self.menu = None;
+                    self.yank(text);
+                }
+            }
+            return;
+        }
+        // the history search takes priority so it can use Enter and Esc
+        if self.editing_query && self.line_input.is_searching() {
+            self.line_input.handle_key_event(key_event);
+            if key_event.code == KeyCode::Enter {
+                self.editing_query = false;
+                self.submit_query();
+            }
+            return;
+        }
+
+        // these keys are handled regardless of whether we're editing the query
+        match key_event.code {
+            KeyCode::Esc => {
+                self.exit = true;
+                return;
+            }
+            KeyCode::Char('c') if ctrl => {
+                self.exit = true;
+                return;
+            }
+            KeyCode::Enter if self.editing_query => {
+                self.editing_query = false;
+                self.submit_query();
+                return;
+            }
+            _ => {}
+        }
+
+        if self.editing_query {
+            self.line_input.handle_key_event(key_event);
+            return;
+        }
+
+        // combinations like Ctrl-d scroll rather than act
+        let plain = (key_event.modifiers - KeyModifiers::SHIFT).is_empty();
+        if let (KeyCode::Char(c), true) = (key_event.code, plain) {
+            if let Some(&(key, done)) = M::ACTIONS.iter().find(|(key, _)| *key == c) {
+                self.act(key, done);
+                return;
+            }
+        }
+
+        let url = self.selected_item().map(M::url);
+        match key_event.code {
+            KeyCode::Char('k') => self.table_state.select_previous(),
+            KeyCode::Char('j') => self.select_next(),
+            KeyCode::Char('/') => self.editing_query = true,
+            KeyCode::Char('e') => self.edit(),
+            KeyCode::Char('p') | KeyCode::Enter => {
+                self.output.extend(url);
+                self.exit = true;
+            }
+            KeyCode::Char('y') => self.open_yank_menu(),
+            KeyCode::Char('o') => {
+                let Some(url) = url else {
+                    return;
+                };
+                if let Err(err) = open::open_url(&url) {
+                    tracing::error!("Failed to open {url}: {err:?}");
+                    self.notice = Some(Line::from(format!("Failed to open {url}: {err}")).red());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::searches::Repos;
+    use mockito::Server;
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::time::Duration;
+
+    fn render<M: Mode>(picker: &mut Picker<M>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| picker.view(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(80)
+            .map(|line| line.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_picker() {
+        let mut server = Server::new_async().await;
+        let first = server
+            .mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"variables":{"query":"hello","after":null}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(std::fs::read_to_string("testdata/search_repos.json").unwrap())
+            .create_async()
+            .await;
+        // the same results again, which aren't shown twice, and no more pages
+        let last = server
+            .mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"variables":{"query":"hello","after":"Y3Vyc29yOjMw"}}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                std::fs::read_to_string("testdata/search_repos.json")
+                    .unwrap()
+                    .replace(r#""hasNextPage": true"#, r#""hasNextPage": false"#),
+            )
+            .create_async()
+            .await;
+        let readme = server
+            .mock("GET", "/repos/octocat/Hello-World/readme")
+            .with_status(200)
+            .with_body("Hello World!\n")
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "repos", None).unwrap();
+        let mut picker = Picker::<Repos>::new(github, Config::default(), "hello", history).unwrap();
+        picker.update(Message::Resize(Size::new(80, 12)));
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        while !picker.search_done || !picker.texts.contains_key(&picker.items[0].url) {
+            let msg = picker.next_message(&mut events, &mut ticks).await.unwrap();
+            picker.update(msg);
+        }
+        first.assert_async().await;
+        last.assert_async().await;
+        readme.assert_async().await;
+        assert_eq!(picker.items.len(), 2);
+        let screen = render(&mut picker);
+        assert!(screen.contains("★ 2.6k  Ruby"), "{screen}");
+        assert!(screen.contains("Hello World!"), "{screen}");
+        assert!(screen.contains("2 repos"), "{screen}");
+
+        for c in ['j', 'p'] {
+            picker.update(Message::Key(KeyCode::Char(c).into()));
+        }
+        assert_eq!(picker.output(), ["https://github.com/octocat/Spoon-Knife"]);
+        assert!(picker.exit);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_polls_and_actions() {
+        use crate::tui::notifications::Notifications;
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/notifications")
+            .match_query(mockito::Matcher::Any)
+            .match_header("if-modified-since", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("last-modified", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_header("x-poll-interval", "0")
+            .with_body(std::fs::read_to_string("testdata/notifications.json").unwrap())
+            .create_async()
+            .await;
+        let _polled = server
+            .mock("GET", "/notifications")
+            .match_query(mockito::Matcher::Any)
+            .match_header("if-modified-since", "Thu, 02 May 2024 09:00:00 GMT")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("x-poll-interval", "3600")
+            .with_body(
+                std::fs::read_to_string("testdata/notifications.json")
+                    .unwrap()
+                    .replacen(r#""id": "3""#, r#""id": "4""#, 1),
+            )
+            .create_async()
+            .await;
+        let done = server
+            .mock("DELETE", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "notifications", None).unwrap();
+        let mut picker =
+            Picker::<Notifications>::new(github, Config::default(), "", history).unwrap();
+        picker.update(Message::Resize(Size::new(80, 12)));
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        let generation = picker.generation;
+        loop {
+            let msg = picker.next_message(&mut events, &mut ticks).await.unwrap();
+            let polled = matches!(
+                &msg,
+                Message::Polled {
+                    result: Ok(Some(_)),
+                    ..
+                }
+            );
+            picker.update(msg);
+            if polled {
+                break;
+            }
+        }
+
+        // a page of the results from before the poll isn't added to the new ones
+        let mut stale = picker.items[0].clone();
+        stale.id = "3".to_string();
+        let page = Page {
+            items: vec![stale],
+            next: None,
+            poll: None,
+        };
+        picker.update(Message::Page {
+            generation,
+            result: Ok(page),
+        });
+        assert!(picker.items.iter().all(|n| n.id != "3"));
+
+        // Ctrl-d isn't the action bound to d
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        picker.update(Message::Key(ctrl_d));
+        let wait = Duration::from_millis(200);
+        while let Ok(msg) =
+            tokio::time::timeout(wait, picker.next_message(&mut events, &mut ticks)).await
+        {
+            let msg = msg.unwrap();
+            assert!(!matches!(msg, Message::Acted { .. }), "{msg:?}");
+            picker.update(msg);
+        }
+        done.assert_async().await;
+    }
+}
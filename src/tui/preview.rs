@@ -1,29 +1,44 @@
 use anyhow::{Context, Result};
-use ratatui::text::{Line, Span, Text};
+use ratatui::{
+    style::Stylize as _,
+    text::{Line, Span, Text},
+};
 use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    hash::Hash,
     io::Cursor,
     path::Path,
+    sync::Arc,
 };
 use syntect::{
     easy::HighlightLines,
     highlighting::{self, Color, FontStyle, Theme, ThemeSet},
-    parsing::SyntaxSet,
+    parsing::{SyntaxReference, SyntaxSet},
 };
 
-use crate::github::{code::SearchItem, TextMatch};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use unicode_width::UnicodeWidthChar as _;
+
+use super::diff::{self, DiffLine, SplitRow};
+use crate::github::{code::SearchItem, pulls::PullFile, TextMatch};
 
 const ANSI_THEME: &[u8] = include_bytes!("ansi.tmTheme");
+// Backgrounds of added and removed lines, dark green and red from the 256 color palette
+const ADDED_BG: ratatui::style::Color = ratatui::style::Color::Indexed(22);
+const REMOVED_BG: ratatui::style::Color = ratatui::style::Color::Indexed(52);
 
 pub type Fragments = Vec<Text<'static>>;
 
-pub struct MarkdownHighlighter {
+/// Owns the syntax definitions and theme used for all highlighting.
+/// Highlighting is CPU bound, so callers should run it through [`HighlightCache`]
+/// rather than on the UI task.
+pub struct Highlighter {
     syntax: SyntaxSet,
     theme: Theme,
 }
 
-impl Default for MarkdownHighlighter {
+impl Default for Highlighter {
     fn default() -> Self {
         let mut theme_cursor = Cursor::new(ANSI_THEME);
         Self {
@@ -33,12 +48,17 @@ impl Default for MarkdownHighlighter {
     }
 }
 
-impl MarkdownHighlighter {
-    pub fn highlight(&self, text: &str) -> Result<Text> {
+impl Highlighter {
+    pub fn markdown(&self, text: &str) -> Result<Text<'static>> {
+        self.text(text, "md")
+    }
+
+    /// Highlight `text` as the language of files ending in `extension`, e.g. "md" or "diff".
+    pub fn text(&self, text: &str, extension: &str) -> Result<Text<'static>> {
         let syntax = self
             .syntax
-            .find_syntax_by_extension("md")
-            .context("markdown syntax not found")?;
+            .find_syntax_by_extension(extension)
+            .with_context(|| format!("{extension} syntax not found"))?;
         let mut h = HighlightLines::new(syntax, &self.theme);
 
         let mut highlighted_lines = Vec::new();
@@ -49,55 +69,18 @@ impl MarkdownHighlighter {
 
         Ok(Text::from(highlighted_lines))
     }
-}
-
-pub struct PreviewCache {
-    cache: HashMap<String, Fragments>, // url->content
-    syntax: SyntaxSet,
-    theme: Theme,
-}
-
-impl Default for PreviewCache {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-impl PreviewCache {
-    pub fn new() -> Self {
-        let mut theme_cursor = Cursor::new(ANSI_THEME);
-        Self {
-            cache: HashMap::new(),
-            syntax: SyntaxSet::load_defaults_newlines(),
-            theme: ThemeSet::load_from_reader(&mut theme_cursor).expect("Loading theme"),
-        }
-    }
-
-    pub fn contains(&self, url: &str) -> bool {
-        self.cache.contains_key(url)
-    }
-
-    pub fn get(&self, url: &str) -> Option<&Fragments> {
-        self.cache.get(url)
-    }
-
-    pub fn insert_placeholder(&mut self, url: impl Into<String> + Display) {
-        self.cache.insert(url.into(), vec![]);
-    }
-
-    pub fn insert(&mut self, item: SearchItem, content: &str) -> Result<()> {
-        tracing::debug!("Caching content for: {}", item.url);
-        let syntax = Path::new(&item.path)
+    fn syntax_for(&self, path: &str, first_line: Option<&str>) -> &SyntaxReference {
+        Path::new(path)
             .extension()
             .and_then(|ext| ext.to_str())
             .and_then(|ext| self.syntax.find_syntax_by_extension(ext))
-            .or_else(|| {
-                content
-                    .lines()
-                    .next()
-                    .and_then(|line| self.syntax.find_syntax_by_first_line(line))
-            })
-            .unwrap_or_else(|| self.syntax.find_syntax_plain_text());
+            .or_else(|| first_line.and_then(|line| self.syntax.find_syntax_by_first_line(line)))
+            .unwrap_or_else(|| self.syntax.find_syntax_plain_text())
+    }
+
+    pub fn code(&self, item: &SearchItem, content: &str) -> Result<Fragments> {
+        let syntax = self.syntax_for(&item.path, content.lines().next());
         let mut h = HighlightLines::new(syntax, &self.theme);
 
         let mut matching_lines = Vec::new();
@@ -127,21 +110,257 @@ impl PreviewCache {
             highlighted_lines.push(highlights);
         }
 
-        let spans = line_spans(matching_lines, highlighted_lines.len() - 1);
+        let spans = line_spans(matching_lines, highlighted_lines.len().saturating_sub(1));
         if spans.is_empty() {
             tracing::error!("No matches found: {}", item.url);
         }
 
-        self.cache.insert(
-            item.url,
-            spans
-                .into_iter()
-                .map(|range| {
-                    Text::from_iter(range.map(|n| to_line_widget(highlighted_lines[n].clone())))
-                })
-                .collect(),
-        );
-        Ok(())
+        Ok(spans
+            .into_iter()
+            .map(|range| {
+                Text::from_iter(range.map(|n| to_line_widget(highlighted_lines[n].clone())))
+            })
+            .collect())
+    }
+
+    /// Highlight the patch of a changed file, with line numbers and the changed lines shaded.
+    /// Side by side, the old lines are on the left and the new ones on the right,
+    /// otherwise the diff is unified and wrapped to `width`.
+    pub fn diff(&self, file: &PullFile, width: u16, side_by_side: bool) -> Result<HighlightedDiff> {
+        let Some(patch) = &file.patch else {
+            return Ok(HighlightedDiff {
+                text: Text::from(Line::from("No diff for binary or large files").dim()),
+                rows: vec![Vec::new()],
+            });
+        };
+        let syntax = self.syntax_for(&file.filename, None);
+        // old and new lines are highlighted separately, so each side keeps its own state
+        let mut old = HighlightLines::new(syntax, &self.theme);
+        let mut new = HighlightLines::new(syntax, &self.theme);
+        let lines = diff::parse(patch);
+        let width = width.max(1) as usize;
+
+        let mut out = Vec::new();
+        let mut rows = Vec::new();
+        if side_by_side {
+            let half = width.saturating_sub(1) / 2;
+            for row in diff::split(&lines) {
+                match row {
+                    SplitRow::Full(i) => {
+                        out.push(fit(note(lines[i]), width, None));
+                        rows.push(vec![i]);
+                    }
+                    SplitRow::Pair(left, right) => {
+                        let (mut row, mut shown) = (Line::default(), Vec::new());
+                        for (i, h, width) in [(left, &mut old, half), (right, &mut new, half)] {
+                            let (half, bg) = match i {
+                                Some(i) => self.diff_line(lines[i], h, None)?,
+                                None => (Line::default(), None),
+                            };
+                            if !row.spans.is_empty() {
+                                row.push_span(Span::raw("│").dim());
+                            }
+                            row.spans.extend(fit(half, width, bg).spans);
+                            shown.extend(i.filter(|i| !shown.contains(i)));
+                        }
+                        out.push(row);
+                        rows.push(shown);
+                    }
+                }
+            }
+        } else {
+            for (i, &line) in lines.iter().enumerate() {
+                let (line, bg) = match line {
+                    DiffLine::Hunk(_) | DiffLine::Note(_) => (note(line), None),
+                    _ => self.diff_line(line, &mut new, Some(&mut old))?,
+                };
+                for line in wrap(Text::from(line), width as u16).lines {
+                    out.push(fit(line, width, bg));
+                    rows.push(vec![i]);
+                }
+            }
+        }
+        Ok(HighlightedDiff {
+            text: Text::from(out),
+            rows,
+        })
+    }
+
+    // Highlight a line of a hunk after its line numbers, returning the background to shade it with.
+    // A unified diff passes the highlighter of the old side as `other`,
+    // which context lines are also fed to so it keeps in step.
+    fn diff_line(
+        &self,
+        line: DiffLine,
+        h: &mut HighlightLines,
+        other: Option<&mut HighlightLines>,
+    ) -> Result<(Line<'static>, Option<ratatui::style::Color>)> {
+        let number = |n: Option<u32>| n.map(|n| format!("{n:>4} ")).unwrap_or("     ".into());
+        let unified = other.is_some();
+        let (old, new, marker, text, bg) = match line {
+            DiffLine::Context { old, new, text } => {
+                if let Some(other) = other {
+                    other.highlight_line(text, &self.syntax)?;
+                }
+                (Some(old), Some(new), ' ', text, None)
+            }
+            DiffLine::Removed { old, text } => (Some(old), None, '-', text, Some(REMOVED_BG)),
+            DiffLine::Added { new, text } => (None, Some(new), '+', text, Some(ADDED_BG)),
+            DiffLine::Hunk(_) | DiffLine::Note(_) => return Ok((note(line), None)),
+        };
+        // side by side, each half has its own column of numbers
+        let gutter = match unified {
+            true => format!("{}{}", number(old), number(new)),
+            false => number(old.or(new)),
+        };
+        let mut widget = to_line_widget(h.highlight_line(text, &self.syntax)?);
+        widget
+            .spans
+            .insert(0, Span::raw(format!("{gutter}{marker}")).dim());
+        Ok((widget, bg))
+    }
+}
+
+/// A highlighted diff, and the lines of the patch each of its rows shows,
+/// as indices into the lines [`diff::parse`] returns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighlightedDiff {
+    pub text: Text<'static>,
+    pub rows: Vec<Vec<usize>>,
+}
+
+// Highlighted items kept by a cache, e.g. previews of the same issue at several widths.
+const CACHE_CAPACITY: usize = 256;
+
+/// Runs highlighting jobs on tokio's blocking pool and caches the results by key.
+/// A key is present but has no value while its job is still running.
+/// Holds at most CACHE_CAPACITY keys, forgetting the oldest first.
+pub struct HighlightCache<K, V> {
+    cache: HashMap<K, Option<V>>,
+    // keys oldest first
+    order: VecDeque<K>,
+    tx: UnboundedSender<(K, Result<V>)>,
+    rx: UnboundedReceiver<(K, Result<V>)>,
+}
+
+impl<K, V> Default for HighlightCache<K, V> {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            cache: HashMap::new(),
+            order: VecDeque::new(),
+            tx,
+            rx,
+        }
+    }
+}
+
+impl<K, V> HighlightCache<K, V>
+where
+    K: Eq + Hash + Clone + Debug + Send + 'static,
+    V: Send + 'static,
+{
+    /// Returns true if the key is cached or a job for it is in flight.
+    pub fn contains(&self, key: &K) -> bool {
+        self.cache.contains_key(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.cache.get(key).and_then(Option::as_ref)
+    }
+
+    /// Mark a key as pending before its job can be started,
+    /// e.g. while the content to highlight is still being fetched.
+    pub fn insert_placeholder(&mut self, key: K) {
+        self.insert(key, None);
+    }
+
+    // Add or replace a key, forgetting the oldest if the cache is full.
+    fn insert(&mut self, key: K, value: Option<V>) {
+        if self.cache.insert(key.clone(), value).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.cache.remove(&oldest);
+            }
+        }
+    }
+
+    /// Run `job` on the blocking pool, replacing any cached value for the key.
+    pub fn spawn(&mut self, key: K, job: impl FnOnce() -> Result<V> + Send + 'static) {
+        tracing::debug!("Spawning highlight job for {key:?}");
+        self.insert(key.clone(), None);
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            // the receiver lives as long as the cache, so there's nobody to notify if this fails
+            let _ = tx.send((key, job()));
+        });
+    }
+
+    /// Wait for the next job to finish and store its result.
+    /// Returns the key of the finished job.
+    /// Failed jobs are logged and left pending so they are not retried on every frame.
+    pub async fn recv(&mut self) -> Option<K> {
+        let (key, res) = self.rx.recv().await?;
+        match res {
+            // the key may have been forgotten while the job ran
+            Ok(value) => match self.cache.get_mut(&key) {
+                Some(slot) => {
+                    tracing::debug!("Highlighting finished for {key:?}");
+                    *slot = Some(value);
+                }
+                None => tracing::debug!("Highlighting finished for forgotten {key:?}"),
+            },
+            Err(err) => tracing::error!("Highlighting failed for {key:?}: {err:?}"),
+        }
+        Some(key)
+    }
+}
+
+pub struct PreviewCache {
+    cache: HighlightCache<String, Fragments>, // url->content
+    highlighter: Arc<Highlighter>,
+}
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        Self::new(Arc::new(Highlighter::default()))
+    }
+}
+
+impl PreviewCache {
+    pub fn new(highlighter: Arc<Highlighter>) -> Self {
+        Self {
+            cache: HighlightCache::default(),
+            highlighter,
+        }
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.cache.contains(&url.to_string())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Fragments> {
+        self.cache.get(&url.to_string())
+    }
+
+    pub fn insert_placeholder(&mut self, url: impl Into<String>) {
+        self.cache.insert_placeholder(url.into());
+    }
+
+    // Highlight content in the background.
+    // The result is available from `get` once `recv` returns its url.
+    pub fn insert(&mut self, item: SearchItem, content: String) {
+        tracing::debug!("Highlighting content for: {}", item.url);
+        let highlighter = self.highlighter.clone();
+        self.cache
+            .spawn(item.url.clone(), move || highlighter.code(&item, &content));
+    }
+
+    pub async fn recv(&mut self) -> Option<String> {
+        self.cache.recv().await
     }
 }
 
@@ -180,6 +399,99 @@ fn test_line_spans() {
     );
 }
 
+// Hard-wrap text to the given width, preserving span styles.
+// Done alongside highlighting so rendering a cached preview doesn't need to re-flow it.
+pub fn wrap(text: Text<'static>, width: u16) -> Text<'static> {
+    let width = width.max(1) as usize;
+    let mut lines = Vec::new();
+    for line in text.lines {
+        let mut current = Line::default();
+        let mut current_width = 0;
+        for span in line.spans {
+            let mut chunk = String::new();
+            for c in span.content.chars() {
+                let w = c.width().unwrap_or(0);
+                if current_width + w > width && current_width > 0 {
+                    if !chunk.is_empty() {
+                        current.push_span(Span::styled(std::mem::take(&mut chunk), span.style));
+                    }
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
+                chunk.push(c);
+                current_width += w;
+            }
+            if !chunk.is_empty() {
+                current.push_span(Span::styled(chunk, span.style));
+            }
+        }
+        lines.push(current);
+    }
+    Text::from(lines)
+}
+
+#[test]
+fn test_wrap() {
+    use ratatui::style::Stylize as _;
+
+    let text = Text::from(vec![
+        Line::from(vec![Span::raw("abc"), Span::raw("defg").bold()]),
+        Line::from("hi"),
+        Line::default(),
+        Line::from("日本語"),
+    ]);
+    assert_eq!(
+        wrap(text, 4),
+        Text::from(vec![
+            Line::from(vec![Span::raw("abc"), Span::raw("d").bold()]),
+            Line::from(vec![Span::raw("efg").bold()]),
+            Line::from("hi"),
+            Line::default(),
+            Line::from("日本"),
+            Line::from("語"),
+        ])
+    );
+}
+
+// Hunk headers and notes span the whole width, without line numbers.
+fn note(line: DiffLine) -> Line<'static> {
+    match line {
+        DiffLine::Hunk(text) => Line::from(text.to_string()).cyan(),
+        DiffLine::Note(text) => Line::from(text.to_string()).dim(),
+        _ => Line::default(),
+    }
+}
+
+/// Truncate or pad a line to exactly `width` columns, shading it with `bg` where the theme doesn't.
+pub fn fit(line: Line<'static>, width: usize, bg: Option<ratatui::style::Color>) -> Line<'static> {
+    let mut fitted = Line::default();
+    let mut used = 0;
+    for span in line.spans {
+        let mut style = span.style;
+        style.bg = style.bg.or(bg);
+        let mut content = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width {
+                break;
+            }
+            content.push(c);
+            used += w;
+        }
+        if !content.is_empty() {
+            fitted.push_span(Span::styled(content, style));
+        }
+    }
+    if used < width {
+        let style = ratatui::style::Style {
+            bg,
+            ..Default::default()
+        };
+        fitted.push_span(Span::styled(" ".repeat(width - used), style));
+    }
+    fitted
+}
+
 // Borrowed from https://github.com/sxyazi/yazi/pull/460/files
 fn to_ansi_color(color: highlighting::Color) -> Option<ratatui::style::Color> {
     if color.a == 0 {
@@ -244,7 +556,7 @@ fn to_line_widget(regions: Vec<(highlighting::Style, &str)>) -> Line<'static> {
             content: s.to_string().into(),
             style: ratatui::style::Style {
                 fg: to_ansi_color(style.foreground),
-                // bg: Self::to_ansi_color(style.background),
+                bg: to_ansi_color(style.background),
                 add_modifier: modifier,
                 ..Default::default()
             },
@@ -253,3 +565,94 @@ fn to_line_widget(regions: Vec<(highlighting::Style, &str)>) -> Line<'static> {
 
     line
 }
+
+#[test]
+fn test_diff() {
+    let file = PullFile {
+        filename: "main.rs".into(),
+        patch: Some("@@ -1,2 +1,2 @@\n-let a = 1;\n+let b = 2;\n x".into()),
+        ..Default::default()
+    };
+    let plain = |line: &Line| {
+        line.spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect::<String>()
+    };
+    let highlighter = Highlighter::default();
+
+    let diff = highlighter.diff(&file, 20, false).unwrap();
+    let text = diff.text;
+    let lines: Vec<String> = text.lines.iter().map(plain).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "@@ -1,2 +1,2 @@     ",
+            "   1      -let a = 1",
+            ";                   ",
+            "        1 +let b = 2",
+            ";                   ",
+            "   2    2  x        ",
+        ]
+    );
+    assert_eq!(
+        text.lines[1].spans.last().unwrap().style.bg,
+        Some(REMOVED_BG)
+    );
+    assert_eq!(text.lines[4].spans.last().unwrap().style.bg, Some(ADDED_BG));
+    assert_eq!(text.lines[5].spans.last().unwrap().style.bg, None);
+    assert_eq!(
+        diff.rows,
+        vec![vec![0], vec![1], vec![1], vec![2], vec![2], vec![3]]
+    );
+
+    let diff = highlighter.diff(&file, 31, true).unwrap();
+    let lines: Vec<String> = diff.text.lines.iter().map(plain).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "@@ -1,2 +1,2 @@                ",
+            "   1 -let a = 1│   1 +let b = 2",
+            "   2  x        │   2  x        ",
+        ]
+    );
+    assert_eq!(diff.rows, vec![vec![0], vec![1, 2], vec![3]]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_highlight_cache() {
+        let mut cache = HighlightCache::default();
+        cache.spawn("a", || Ok(1));
+        assert!(cache.contains(&"a"));
+        assert_eq!(cache.get(&"a"), None);
+
+        assert_eq!(cache.recv().await, Some("a"));
+        assert_eq!(cache.get(&"a"), Some(&1));
+
+        cache.spawn("b", || anyhow::bail!("oops"));
+        assert_eq!(cache.recv().await, Some("b"));
+        assert!(cache.contains(&"b"));
+        assert_eq!(cache.get(&"b"), None);
+    }
+
+    #[tokio::test]
+    async fn test_highlight_cache_capacity() {
+        let mut cache = HighlightCache::default();
+        for width in 0..CACHE_CAPACITY as u16 {
+            cache.spawn(("url", width), move || Ok(width));
+        }
+        // the oldest is forgotten while its job may still be running
+        cache.spawn(("url", 1000), || Ok(1000));
+        for _ in 0..=CACHE_CAPACITY {
+            cache.recv().await.unwrap();
+        }
+        assert!(!cache.contains(&("url", 0)));
+        assert_eq!(cache.get(&("url", 1)), Some(&1));
+        assert_eq!(cache.get(&("url", 1000)), Some(&1000));
+        assert_eq!(cache.cache.len(), CACHE_CAPACITY);
+    }
+}
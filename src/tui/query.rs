@@ -0,0 +1,93 @@
+use std::ops::Range;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::query::{Node, Query, Term};
+
+fn node_styles(pattern: &str, nodes: &[Node], styles: &mut Vec<(Range<usize>, Style)>) {
+    for node in nodes {
+        let span = node.span.clone();
+        match &node.term {
+            // saved searches
+            Term::Word(word) if word.len() > 1 && word.starts_with('@') => {
+                styles.push((span, Style::new().fg(Color::Magenta)))
+            }
+            Term::Word(_) => {}
+            Term::Phrase(_) => styles.push((span, Style::new().fg(Color::Green))),
+            Term::And | Term::Or | Term::Not => styles.push((
+                span,
+                Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            )),
+            Term::Qualifier { negated, name, .. } => {
+                let name_start = span.start + usize::from(*negated);
+                let name_end = name_start + name.len();
+                if *negated {
+                    styles.push((span.start..name_start, Style::new().fg(Color::Red)));
+                }
+                styles.push((name_start..name_end, Style::new().fg(Color::Blue)));
+                styles.push((name_end..span.end, Style::new().fg(Color::Cyan)));
+            }
+            Term::Group(children) => {
+                let paren = Style::new().fg(Color::Yellow);
+                styles.push((span.start..span.start + 1, paren));
+                node_styles(pattern, children, styles);
+                if span.len() > 1 && pattern[span.clone()].ends_with(')') {
+                    styles.push((span.end - 1..span.end, paren));
+                }
+            }
+        }
+    }
+}
+
+/// Styles for the parts of a search query, for syntax highlighting as it is typed.
+/// Errors are underlined, and listed after the other styles so they take precedence.
+pub fn styles(pattern: &str) -> Vec<(Range<usize>, Style)> {
+    let (query, errors) = Query::parse(pattern);
+    let mut styles = Vec::new();
+    node_styles(pattern, &query.nodes, &mut styles);
+    for err in errors {
+        // an empty span (e.g. a missing value) is marked by the character before it
+        let span = match err.span {
+            s if s.is_empty() => s.start.saturating_sub(1)..s.start,
+            s => s,
+        };
+        styles.push((
+            span,
+            Style::new()
+                .fg(Color::Red)
+                .add_modifier(Modifier::UNDERLINED),
+        ));
+    }
+    styles
+}
+
+#[test]
+fn test_styles() {
+    let blue = Style::new().fg(Color::Blue);
+    let cyan = Style::new().fg(Color::Cyan);
+    let error = Style::new()
+        .fg(Color::Red)
+        .add_modifier(Modifier::UNDERLINED);
+    assert_eq!(
+        styles("foo -is:open (\"a\" OR lable:x)"),
+        vec![
+            (4..5, Style::new().fg(Color::Red)),
+            (5..7, blue),
+            (7..12, cyan),
+            (13..14, Style::new().fg(Color::Yellow)),
+            (14..17, Style::new().fg(Color::Green)),
+            (
+                18..20,
+                Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+            ),
+            (21..26, blue),
+            (26..28, cyan),
+            (28..29, Style::new().fg(Color::Yellow)),
+            (21..26, error),
+        ]
+    );
+    assert_eq!(
+        styles("label:"),
+        vec![(0..5, blue), (5..6, cyan), (5..6, error)]
+    );
+}
@@ -0,0 +1,576 @@
+use super::issues::author;
+use super::picker::Mode;
+use crate::github::{
+    code::{self, CodeHit},
+    search::{self, CommitItem, Discussion, Page, Profile, Repo, UserItem},
+    Github,
+};
+use anyhow::{Context, Result};
+use ratatui::{layout::Constraint, style::Stylize, text::Span};
+
+// Lines of a file shown on each side of the matched line.
+const CONTEXT_LINES: usize = 10;
+
+// A count as GitHub shows it, e.g. 2.6k.
+fn count(n: u32) -> String {
+    match n {
+        0..1000 => n.to_string(),
+        _ => format!("{:.1}k", n as f64 / 1000.0),
+    }
+}
+
+/// Code, previewed around the first match in its file.
+/// The file's content is fetched with each page to find the matched line,
+/// which the permalink points at and `e` opens the file at.
+pub struct Code;
+
+impl Mode for Code {
+    type Item = CodeHit;
+    const NAME: &'static str = "code";
+
+    fn widths() -> Vec<Constraint> {
+        vec![
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Length(6),
+        ]
+    }
+
+    fn cells(hit: &CodeHit) -> Vec<Span<'static>> {
+        let line = hit.line.map(|line| format!(":{line}")).unwrap_or_default();
+        vec![
+            Span::from(hit.item.repository.full_name.clone()).dim(),
+            Span::from(hit.item.path.clone()),
+            Span::from(line).yellow(),
+        ]
+    }
+
+    fn url(hit: &CodeHit) -> String {
+        hit.item.permalink(hit.line)
+    }
+
+    fn yanks(hit: &CodeHit) -> Vec<(&'static str, String)> {
+        let path = match hit.line {
+            Some(line) => format!("{}:{line}", hit.item.path),
+            None => hit.item.path.clone(),
+        };
+        vec![
+            ("Permalink", hit.item.permalink(hit.line)),
+            ("File", hit.item.permalink(None)),
+            ("Path", path),
+        ]
+    }
+
+    fn title(hit: &CodeHit) -> String {
+        format!("{} {}", hit.item.repository.full_name, hit.item.path)
+    }
+
+    fn file(hit: &CodeHit) -> Option<(String, String, Option<usize>)> {
+        Some((
+            hit.item.repository.full_name.clone(),
+            hit.item.path.clone(),
+            hit.line,
+        ))
+    }
+
+    async fn search(github: Github, query: String, next: Option<String>) -> Result<Page<CodeHit>> {
+        let page = search::search_code(&github, &query, next.as_deref()).await?;
+        let hits = page.items.into_iter().map(|item| {
+            let github = github.clone();
+            async move {
+                let content = match code::fetch_content(&github, &item).await {
+                    Ok(content) => Some(content),
+                    Err(err) => {
+                        tracing::warn!("Failed to fetch {}: {err:?}", item.url);
+                        None
+                    }
+                };
+                CodeHit::new(item, content)
+            }
+        });
+        Ok(Page {
+            items: futures::future::join_all(hits).await,
+            next: page.next,
+            poll: page.poll,
+        })
+    }
+
+    async fn preview(_github: Github, hit: CodeHit) -> Result<String> {
+        let content = hit
+            .content
+            .as_deref()
+            .with_context(|| format!("Couldn't fetch {}", hit.item.path))?;
+        Ok(code_preview(&hit.item.path, content, hit.line))
+    }
+}
+
+// The lines around `line` of a file in a code block, highlighted as the language of its extension.
+fn code_preview(path: &str, content: &str, line: Option<usize>) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let first = line.map_or(0, |line| line.saturating_sub(CONTEXT_LINES + 1));
+    let last = (first + 2 * CONTEXT_LINES + 1).min(lines.len());
+    let language = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    format!(
+        "Lines {}–{last} of {}\n\n```{language}\n{}\n```",
+        first + 1,
+        lines.len(),
+        lines[first.min(last)..last].join("\n"),
+    )
+}
+
+/// Repositories, previewed with their README.
+pub struct Repos;
+
+impl Mode for Repos {
+    type Item = Repo;
+    const NAME: &'static str = "repos";
+
+    fn widths() -> Vec<Constraint> {
+        vec![
+            Constraint::Fill(1),
+            Constraint::Length(7),
+            Constraint::Length(10),
+        ]
+    }
+
+    fn cells(repo: &Repo) -> Vec<Span<'static>> {
+        let name = Span::from(repo.name_with_owner.clone());
+        vec![
+            match repo.is_archived {
+                true => name.dim(),
+                false => name,
+            },
+            Span::from(format!("★ {}", count(repo.stargazer_count))).yellow(),
+            Span::from(
+                repo.primary_language
+                    .as_ref()
+                    .map_or_else(String::new, |l| l.name.clone()),
+            )
+            .dim(),
+        ]
+    }
+
+    fn url(repo: &Repo) -> String {
+        repo.url.clone()
+    }
+
+    fn title(repo: &Repo) -> String {
+        repo.name_with_owner.clone()
+    }
+
+    async fn search(github: Github, query: String, next: Option<String>) -> Result<Page<Repo>> {
+        search::search_repos(&github, &query, next.as_deref()).await
+    }
+
+    async fn preview(github: Github, repo: Repo) -> Result<String> {
+        let readme = search::fetch_readme(&github, &repo.name_with_owner).await?;
+        Ok(repo_preview(&repo, readme.as_deref()))
+    }
+}
+
+// The markdown of a repository's details followed by its README.
+fn repo_preview(repo: &Repo, readme: Option<&str>) -> String {
+    let mut text = String::new();
+    if let Some(description) = &repo.description {
+        text.push_str(&format!("{description}\n\n"));
+    }
+    let mut facts = vec![
+        format!("★ {}", count(repo.stargazer_count)),
+        format!("{} forks", count(repo.fork_count)),
+    ];
+    facts.extend(repo.primary_language.as_ref().map(|l| l.name.clone()));
+    facts.extend(repo.is_fork.then(|| "fork".to_string()));
+    facts.extend(repo.is_archived.then(|| "archived".to_string()));
+    text.push_str(&facts.join(" · "));
+    let topics = repo
+        .repository_topics
+        .nodes
+        .iter()
+        .map(|t| format!("`{}`", t.topic.name))
+        .collect::<Vec<_>>();
+    if !topics.is_empty() {
+        text.push_str(&format!("\n\n{}", topics.join(" ")));
+    }
+    text.push_str("\n\n---\n\n");
+    text.push_str(readme.unwrap_or("*No README*"));
+    text
+}
+
+/// Discussions, previewed with their answer and first comments.
+pub struct Discussions;
+
+impl Mode for Discussions {
+    type Item = Discussion;
+    const NAME: &'static str = "discussions";
+
+    fn widths() -> Vec<Constraint> {
+        vec![
+            Constraint::Length(6),
+            Constraint::Fill(1),
+            Constraint::Length(12),
+            Constraint::Length(1),
+        ]
+    }
+
+    fn cells(discussion: &Discussion) -> Vec<Span<'static>> {
+        vec![
+            Span::from(discussion.number.to_string()).dim(),
+            Span::from(discussion.title.clone()),
+            Span::from(discussion.category.name.clone()).dim(),
+            match discussion.answer {
+                Some(_) => Span::from("✓").green(),
+                None => Span::from(""),
+            },
+        ]
+    }
+
+    fn url(discussion: &Discussion) -> String {
+        discussion.url.clone()
+    }
+
+    fn title(discussion: &Discussion) -> String {
+        format!("#{} {}", discussion.number, discussion.title)
+    }
+
+    async fn search(
+        github: Github,
+        query: String,
+        next: Option<String>,
+    ) -> Result<Page<Discussion>> {
+        search::search_discussions(&github, &query, next.as_deref()).await
+    }
+
+    // the answer and comments come with the search results
+    async fn preview(_github: Github, discussion: Discussion) -> Result<String> {
+        Ok(discussion_preview(&discussion))
+    }
+}
+
+// The markdown of a discussion's body, its answer, then its comments.
+fn discussion_preview(discussion: &Discussion) -> String {
+    let mut text = format!(
+        "**{}** started on {} in {} · ▲ {}\n\n{}",
+        author(&discussion.author),
+        discussion.created_at.format("%Y-%m-%d"),
+        discussion.category.name,
+        discussion.upvote_count,
+        discussion.body,
+    );
+    if let Some(answer) = &discussion.answer {
+        text.push_str(&format!(
+            "\n\n---\n\n**Answer** by **{}**:\n\n{}",
+            author(&answer.author),
+            answer.body
+        ));
+    }
+    for comment in &discussion.comments.nodes {
+        text.push_str(&format!(
+            "\n\n---\n\n**{}** commented:\n\n{}",
+            author(&comment.author),
+            comment.body
+        ));
+    }
+    let more =
+        (discussion.comments.total_count as usize).saturating_sub(discussion.comments.nodes.len());
+    if more > 0 {
+        text.push_str(&format!("\n\n---\n\n*{more} more comments*"));
+    }
+    text
+}
+
+/// Commits, previewed with their diff.
+pub struct Commits;
+
+impl Mode for Commits {
+    type Item = CommitItem;
+    const NAME: &'static str = "commits";
+    const PREVIEW_SYNTAX: &'static str = "diff";
+
+    fn widths() -> Vec<Constraint> {
+        vec![
+            Constraint::Length(7),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Length(12),
+        ]
+    }
+
+    fn cells(commit: &CommitItem) -> Vec<Span<'static>> {
+        vec![
+            Span::from(commit.sha.chars().take(7).collect::<String>()).yellow(),
+            Span::from(commit.repository.full_name.clone()).dim(),
+            Span::from(commit.subject().to_string()),
+            Span::from(commit.commit.author.name.clone()).dim(),
+        ]
+    }
+
+    fn url(commit: &CommitItem) -> String {
+        commit.html_url.clone()
+    }
+
+    fn title(commit: &CommitItem) -> String {
+        format!(
+            "{}@{}",
+            commit.repository.full_name,
+            commit.sha.chars().take(7).collect::<String>()
+        )
+    }
+
+    async fn search(
+        github: Github,
+        query: String,
+        next: Option<String>,
+    ) -> Result<Page<CommitItem>> {
+        search::search_commits(&github, &query, next.as_deref()).await
+    }
+
+    async fn preview(github: Github, commit: CommitItem) -> Result<String> {
+        let diff =
+            search::fetch_commit_diff(&github, &commit.repository.full_name, &commit.sha).await?;
+        Ok(commit_preview(&commit, &diff))
+    }
+}
+
+// A commit's header and message like `git show`, then its diff.
+fn commit_preview(commit: &CommitItem, diff: &str) -> String {
+    let message = commit
+        .commit
+        .message
+        .lines()
+        .map(|line| format!("    {line}").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "commit {}\nAuthor: {}\nDate:   {}\n\n{message}\n\n{diff}",
+        commit.sha,
+        commit.commit.author.name,
+        commit.commit.author.date.format("%Y-%m-%d %H:%M"),
+    )
+}
+
+/// Users and organizations, previewed with their profile.
+pub struct Users;
+
+impl Mode for Users {
+    type Item = UserItem;
+    const NAME: &'static str = "users";
+
+    fn widths() -> Vec<Constraint> {
+        vec![Constraint::Fill(1), Constraint::Length(12)]
+    }
+
+    fn cells(user: &UserItem) -> Vec<Span<'static>> {
+        vec![
+            Span::from(user.login.clone()),
+            Span::from(user.kind.clone()).dim(),
+        ]
+    }
+
+    fn url(user: &UserItem) -> String {
+        user.html_url.clone()
+    }
+
+    fn title(user: &UserItem) -> String {
+        user.login.clone()
+    }
+
+    async fn search(github: Github, query: String, next: Option<String>) -> Result<Page<UserItem>> {
+        search::search_users(&github, &query, next.as_deref()).await
+    }
+
+    async fn preview(github: Github, user: UserItem) -> Result<String> {
+        let profile = search::fetch_profile(&github, &user.login).await?;
+        Ok(profile_preview(&profile))
+    }
+}
+
+// The markdown of a user's or organization's profile.
+fn profile_preview(profile: &Profile) -> String {
+    let mut text = match &profile.name {
+        Some(name) => format!("**{name}** ({})", profile.login),
+        None => format!("**{}**", profile.login),
+    };
+    if let Some(bio) = &profile.bio {
+        text.push_str(&format!("\n\n{bio}"));
+    }
+    let details = [
+        ("Company", &profile.company),
+        ("Location", &profile.location),
+        ("Blog", &profile.blog),
+    ];
+    let details = details
+        .iter()
+        .filter_map(|(label, value)| {
+            let value = value.as_deref().filter(|v| !v.is_empty())?;
+            Some(format!("- {label}: {value}"))
+        })
+        .collect::<Vec<_>>();
+    if !details.is_empty() {
+        text.push_str(&format!("\n\n{}", details.join("\n")));
+    }
+    text.push_str(&format!(
+        "\n\n{} followers · {} following · {} public repos · joined {}",
+        count(profile.followers),
+        count(profile.following),
+        profile.public_repos,
+        profile.created_at.format("%Y-%m-%d"),
+    ));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::history::History;
+    use crate::tui::picker::{Message, Picker};
+    use base64::prelude::*;
+    use crossterm::event::KeyCode;
+    use mockito::{Matcher, Server};
+    use ratatui::{backend::TestBackend, layout::Size, Terminal};
+    use std::time::Duration;
+
+    fn render(picker: &mut Picker<Code>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| picker.view(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(100)
+            .map(|line| line.iter().map(|c| c.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_previews() {
+        let data: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string("testdata/search_discussions.json").unwrap(),
+        )
+        .unwrap();
+        let discussion: Discussion =
+            serde_json::from_value(data["data"]["search"]["nodes"][0].clone()).unwrap();
+        let text = discussion_preview(&discussion);
+        assert!(text.contains("**Answer** by"), "{text}");
+        assert!(text.contains("Use a token."), "{text}");
+        assert!(text.find("**Answer**") < text.find("commented:"), "{text}");
+
+        let profile: Profile =
+            serde_json::from_str(&std::fs::read_to_string("testdata/user.json").unwrap()).unwrap();
+        let text = profile_preview(&profile);
+        assert!(text.starts_with("**The Octocat** (octocat)"), "{text}");
+        assert!(
+            text.contains("3.9k followers · 9 following · 8 public repos"),
+            "{text}"
+        );
+
+        let repo = Repo {
+            name_with_owner: "octocat/Spoon-Knife".to_string(),
+            stargazer_count: 12,
+            is_archived: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            repo_preview(&repo, None),
+            "★ 12 · 0 forks · archived\n\n---\n\n*No README*"
+        );
+        assert_eq!(count(999), "999");
+        assert_eq!(count(2600), "2.6k");
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_code() {
+        let mut server = Server::new_async().await;
+        let search = server
+            .mock("GET", "/search/code")
+            .match_query(Matcher::UrlEncoded("q".into(), "stuff".into()))
+            .match_header("accept", "application/vnd.github.text-match+json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "total_count": 1,
+                    "items": [{
+                        "url": format!("{}/repositories/1/contents/src/lib.rs?ref=abc", server.url()),
+                        "html_url": "https://github.com/octocat/hello/blob/abc/src/lib.rs",
+                        "path": "src/lib.rs",
+                        "repository": {"full_name": "octocat/hello"},
+                        "text_matches": [{"matches": [{"text": "stuff"}]}],
+                    }],
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let content = server
+            .mock("GET", "/repositories/1/contents/src/lib.rs")
+            .match_query(Matcher::UrlEncoded("ref".into(), "abc".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "content": BASE64_STANDARD.encode("fn main() {\n}\n\nfn stuff() {}\n"),
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let github = Github {
+            host: server.url(),
+            token: "token".to_string(),
+        };
+        let checkout = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config
+            .checkouts
+            .insert("octocat/hello".into(), checkout.path().to_path_buf());
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::load(dir.path().join("history"), "code", None).unwrap();
+        let mut picker = Picker::<Code>::new(github, config, "stuff", history).unwrap();
+        picker.update(Message::Resize(Size::new(80, 12)));
+
+        let mut events = futures::stream::pending();
+        let mut ticks = tokio::time::interval(Duration::from_secs(3600));
+        let mut previewed = false;
+        while !previewed {
+            let msg = picker.next_message(&mut events, &mut ticks).await.unwrap();
+            previewed = matches!(&msg, Message::Preview(_, Ok(text))
+                if text.starts_with("Lines 1–4 of 4\n\n```rs\n") && text.contains("fn stuff()"));
+            picker.update(msg);
+        }
+        search.assert_async().await;
+        content.assert_async().await;
+
+        // the editor opens the file in the checkout at the matched line
+        picker.update(Message::Key(KeyCode::Char('e').into()));
+        let command = picker.command.take().unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert!(args.contains(&"+4".as_ref()), "{command:?}");
+        assert_eq!(
+            args.last(),
+            Some(&checkout.path().join("src/lib.rs").as_os_str())
+        );
+
+        // the permalink to the line comes first of what can be copied
+        picker.update(Message::Key(KeyCode::Char('y').into()));
+        let screen = render(&mut picker);
+        assert!(
+            screen.contains("Permalink  https://github.com/octocat/hello/blob/abc/src/lib.rs#L4"),
+            "{screen}"
+        );
+        assert!(screen.contains("Path  src/lib.rs:4"), "{screen}");
+        picker.update(Message::Key(KeyCode::Esc.into()));
+        assert!(!render(&mut picker).contains("Permalink"));
+
+        picker.update(Message::Key(KeyCode::Char('p').into()));
+        assert_eq!(
+            picker.output(),
+            ["https://github.com/octocat/hello/blob/abc/src/lib.rs#L4"]
+        );
+    }
+}